@@ -0,0 +1,100 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// The message of the IPC system calls. It is copied between processes as `BYTES` bytes, encoded by
+// `to_bytes`.
+
+pub const INLINE_WORDS: usize = 4;
+
+// Used where no handle is given.
+pub const NO_HANDLE: u64 = u64::MAX;
+
+// A flag of `Grant`. Without this, the receiver maps the pages as read-only.
+pub const GRANT_WRITE: u64 = 1;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Message {
+    pub label: u64,
+    pub data: [u64; INLINE_WORDS],
+    // When sending, the pages of the sender to share. When receiving, where the kernel mapped
+    // them in the receiver.
+    pub grant: Grant,
+    // When sending, a handle of an endpoint to copy to the receiver. When receiving, the new
+    // handle of the receiver.
+    pub handle: u64,
+    // Set by the kernel. The handle to reply to a `call`, or `NO_HANDLE`.
+    pub reply: u64,
+    // Set by the kernel. The ID of the sending process, or zero for the kernel.
+    pub sender: u64,
+}
+impl Message {
+    pub const BYTES: usize = WORDS * 8;
+
+    #[must_use]
+    pub fn new(label: u64, data: [u64; INLINE_WORDS]) -> Self {
+        Self {
+            label,
+            data,
+            grant: Grant::default(),
+            handle: NO_HANDLE,
+            reply: NO_HANDLE,
+            sender: 0,
+        }
+    }
+
+    #[must_use]
+    pub fn to_bytes(self) -> [u8; Self::BYTES] {
+        let [d0, d1, d2, d3] = self.data;
+        let words: [u64; WORDS] = [
+            self.label,
+            d0,
+            d1,
+            d2,
+            d3,
+            self.grant.addr,
+            self.grant.num_of_pages,
+            self.grant.flags,
+            self.handle,
+            self.reply,
+            self.sender,
+        ];
+
+        let mut bytes = [0; Self::BYTES];
+        for (chunk, word) in bytes.chunks_exact_mut(8).zip(words.iter()) {
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+        bytes
+    }
+
+    #[must_use]
+    pub fn from_bytes(bytes: [u8; Self::BYTES]) -> Self {
+        let mut words = [0; WORDS];
+        for (word, chunk) in words.iter_mut().zip(bytes.chunks_exact(8)) {
+            let mut buf = [0; 8];
+            buf.copy_from_slice(chunk);
+            *word = u64::from_le_bytes(buf);
+        }
+
+        Self {
+            label: words[0],
+            data: [words[1], words[2], words[3], words[4]],
+            grant: Grant {
+                addr: words[5],
+                num_of_pages: words[6],
+                flags: words[7],
+            },
+            handle: words[8],
+            reply: words[9],
+            sender: words[10],
+        }
+    }
+}
+
+// No pages are shared if `num_of_pages` is zero. `addr` must be aligned to a page.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Grant {
+    pub addr: u64,
+    pub num_of_pages: u64,
+    pub flags: u64,
+}
+
+const WORDS: usize = 11;
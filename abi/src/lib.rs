@@ -0,0 +1,19 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// The interface between the kernel and user processes.
+//
+// A system call is made with `syscall`. `rax` holds the number, and the arguments are passed in
+// `rdi`, `rsi`, `rdx`, `r10`, `r8` and `r9` in this order. The result is returned in `rax`. `rcx`
+// and `r11` are clobbered, and the other registers are preserved.
+
+#![no_std]
+#![deny(clippy::pedantic)]
+#![deny(clippy::all)]
+#![allow(clippy::missing_errors_doc)]
+
+pub mod ipc;
+pub mod mouse;
+pub mod process;
+pub mod syscall;
+
+pub use {ipc::Message, mouse::MouseEvent, syscall::Error};
@@ -0,0 +1,44 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// A movement or a button change of the PS/2 mouse. `y` grows downwards, as on the screen.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct MouseEvent {
+    pub dx: i32,
+    pub dy: i32,
+    pub left: bool,
+    pub right: bool,
+    pub center: bool,
+}
+impl MouseEvent {
+    pub const BYTES: usize = 12;
+
+    #[must_use]
+    pub fn to_bytes(self) -> [u8; Self::BYTES] {
+        let buttons =
+            u32::from(self.left) | u32::from(self.right) << 1 | u32::from(self.center) << 2;
+
+        let mut bytes = [0; Self::BYTES];
+        bytes[..4].copy_from_slice(&self.dx.to_le_bytes());
+        bytes[4..8].copy_from_slice(&self.dy.to_le_bytes());
+        bytes[8..].copy_from_slice(&buttons.to_le_bytes());
+        bytes
+    }
+
+    #[must_use]
+    pub fn from_bytes(bytes: [u8; Self::BYTES]) -> Self {
+        let word = |i: usize| {
+            let mut buf = [0; 4];
+            buf.copy_from_slice(&bytes[i..i + 4]);
+            buf
+        };
+        let buttons = u32::from_le_bytes(word(8));
+
+        Self {
+            dx: i32::from_le_bytes(word(0)),
+            dy: i32::from_le_bytes(word(4)),
+            left: buttons & 1 != 0,
+            right: buttons & 2 != 0,
+            center: buttons & 4 != 0,
+        }
+    }
+}
@@ -0,0 +1,89 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// How a process ended. `wait` returns this encoded by `to_u64`.
+
+use core::convert::TryFrom;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Status {
+    Exited(u32),
+    // An exception in user mode.
+    Faulted(Fault),
+    // By `kill`.
+    Killed,
+}
+impl Status {
+    const KIND_EXITED: u64 = 0;
+    const KIND_FAULTED: u64 = 1;
+    const KIND_KILLED: u64 = 2;
+
+    // The kind is in the upper 32 bits, and the exit code or the fault in the lower ones.
+    #[must_use]
+    pub fn to_u64(self) -> u64 {
+        match self {
+            Self::Exited(code) => Self::KIND_EXITED << 32 | u64::from(code),
+            Self::Faulted(fault) => Self::KIND_FAULTED << 32 | fault.code(),
+            Self::Killed => Self::KIND_KILLED << 32,
+        }
+    }
+
+    #[must_use]
+    pub fn from_u64(raw: u64) -> Option<Self> {
+        let low = raw & 0xffff_ffff;
+        match raw >> 32 {
+            Self::KIND_EXITED => u32::try_from(low).ok().map(Self::Exited),
+            Self::KIND_FAULTED => Fault::from_code(low).map(Self::Faulted),
+            Self::KIND_KILLED => Some(Self::Killed),
+            _ => None,
+        }
+    }
+
+    #[must_use]
+    pub fn success(self) -> bool {
+        self == Self::Exited(0)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Fault {
+    DivideByZero,
+    Breakpoint,
+    Overflow,
+    BoundRange,
+    InvalidOpcode,
+    GeneralProtection,
+    // An access to an unmapped page, or one which the page does not allow.
+    Segmentation,
+    FloatingPoint,
+}
+impl Fault {
+    const ALL: [Self; 8] = [
+        Self::DivideByZero,
+        Self::Breakpoint,
+        Self::Overflow,
+        Self::BoundRange,
+        Self::InvalidOpcode,
+        Self::GeneralProtection,
+        Self::Segmentation,
+        Self::FloatingPoint,
+    ];
+
+    #[must_use]
+    pub fn code(self) -> u64 {
+        match self {
+            Self::DivideByZero => 0,
+            Self::Breakpoint => 1,
+            Self::Overflow => 2,
+            Self::BoundRange => 3,
+            Self::InvalidOpcode => 4,
+            Self::GeneralProtection => 5,
+            Self::Segmentation => 6,
+            Self::FloatingPoint => 7,
+        }
+    }
+
+    #[must_use]
+    pub fn from_code(code: u64) -> Option<Self> {
+        Self::ALL.iter().copied().find(|f| f.code() == code)
+    }
+}
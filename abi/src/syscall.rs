@@ -0,0 +1,137 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// `write(buf: *const u8, len: usize) -> usize`
+// Prints UTF-8 text to the console. Returns the number of bytes written.
+pub const WRITE: u64 = 0;
+// `read_keys(buf: *mut u8, len: usize) -> usize`
+// Copies pending PS/2 scancodes without blocking. Returns the number of bytes copied.
+pub const READ_KEYS: u64 = 1;
+// `exit(code: u32) -> !`
+// Fails with `InvalidArgument` if `code` does not fit in 32 bits.
+pub const EXIT: u64 = 2;
+// `yield() -> 0`
+pub const YIELD: u64 = 3;
+// `sleep(ms: u64) -> 0`
+pub const SLEEP: u64 = 4;
+// `mmap(len: usize, prot: u64) -> *mut u8`
+// Maps zeroed anonymous memory. `len` is rounded up to pages.
+pub const MMAP: u64 = 5;
+// `getpid() -> u64`
+pub const GETPID: u64 = 6;
+// `read_mouse(buf: *mut u8, len: usize) -> usize`
+// Copies pending mouse events, each encoded by `MouseEvent::to_bytes`. `len` is in bytes.
+// Returns the number of events copied.
+pub const READ_MOUSE: u64 = 7;
+// `uptime() -> u64`
+// Milliseconds since the timer started.
+pub const UPTIME: u64 = 8;
+
+// IPC. See `crate::ipc` for messages. A handle names a capability of the calling process.
+//
+// `endpoint_create() -> handle`
+// Creates an endpoint. The handle may send and receive.
+pub const ENDPOINT_CREATE: u64 = 9;
+// `lookup(name: *const u8, len: usize) -> handle`
+// Returns a handle which may send to the kernel service `name`.
+pub const LOOKUP: u64 = 10;
+// `send(handle, message: *const Message) -> 0`
+// Fails with `WouldBlock` if the queue of the endpoint is full.
+pub const SEND: u64 = 11;
+// `receive(handle, message: *mut Message) -> 0`
+// Blocks until a message arrives.
+pub const RECEIVE: u64 = 12;
+// `call(handle, message: *mut Message) -> 0`
+// Sends the message with a reply handle, and blocks until the receiver replies. The reply
+// overwrites the message.
+pub const CALL: u64 = 13;
+// `reply(reply_handle, message: *const Message) -> 0`
+// Consumes the reply handle.
+pub const REPLY: u64 = 14;
+// `close(handle) -> 0`
+pub const CLOSE: u64 = 15;
+
+// Process lifecycle. See `crate::process` for statuses.
+//
+// `wait(pid: u64, status: *mut u64) -> pid`
+// Blocks until the child `pid`, or any child if `pid` is zero, ends. Writes its status and frees
+// it. Fails with `NotFound` if there is no such child.
+pub const WAIT: u64 = 16;
+// `kill(pid: u64) -> 0`
+// Asks a child to terminate. It ends when it next runs in user mode or returns from a system
+// call. A child blocked in `wait`, `receive`, `call` or `sleep` returns at once.
+pub const KILL: u64 = 17;
+// `spawn(image: *const u8, len: usize, args: *const u8, args_len: usize) -> pid`
+// Starts the ELF executable in `image` as a child. `args` holds the arguments, each followed by
+// a NUL.
+pub const SPAWN: u64 = 18;
+
+pub const MAX_WRITE_LEN: usize = 4096;
+pub const MAX_SPAWN_IMAGE_LEN: usize = 4 * 1024 * 1024;
+pub const MAX_SPAWN_ARGS_LEN: usize = 4096;
+
+// Flags of `mmap`. Memory is always readable.
+pub const PROT_WRITE: u64 = 1;
+pub const PROT_EXEC: u64 = 2;
+
+// An error is returned as the negated code in `rax`, so it is at least `1 << 63` as an unsigned
+// value.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Error {
+    NoSuchCall,
+    BadAddress,
+    InvalidArgument,
+    NoMemory,
+    BadHandle,
+    WouldBlock,
+    // The receiver dropped the reply handle without replying.
+    NoReply,
+    NotFound,
+}
+impl Error {
+    const ALL: [Self; 8] = [
+        Self::NoSuchCall,
+        Self::BadAddress,
+        Self::InvalidArgument,
+        Self::NoMemory,
+        Self::BadHandle,
+        Self::WouldBlock,
+        Self::NoReply,
+        Self::NotFound,
+    ];
+
+    #[must_use]
+    pub fn code(self) -> u64 {
+        match self {
+            Self::NoSuchCall => 1,
+            Self::BadAddress => 2,
+            Self::InvalidArgument => 3,
+            Self::NoMemory => 4,
+            Self::BadHandle => 5,
+            Self::WouldBlock => 6,
+            Self::NoReply => 7,
+            Self::NotFound => 8,
+        }
+    }
+}
+
+// Successful values must be below `1 << 63`.
+#[must_use]
+pub fn encode(result: Result<u64, Error>) -> u64 {
+    match result {
+        Ok(value) => value,
+        Err(e) => e.code().wrapping_neg(),
+    }
+}
+
+pub fn decode(value: u64) -> Result<u64, Error> {
+    if value >> 63 == 0 {
+        return Ok(value);
+    }
+
+    let code = value.wrapping_neg();
+    Err(Error::ALL
+        .iter()
+        .copied()
+        .find(|e| e.code() == code)
+        .unwrap_or(Error::InvalidArgument))
+}
@@ -2,10 +2,10 @@
 
 use super::root_dir;
 use common::constant::{KERNEL_ADDR, KERNEL_NAME};
+use common::elf_rs::Elf;
 use core::cmp;
 use core::convert::TryFrom;
 use core::slice;
-use elf_rs::Elf;
 use os_units::Bytes;
 use uefi::proto::media::file;
 use uefi::proto::media::file::File;
@@ -49,7 +49,7 @@ pub fn efi_main(image: Handle, system_table: SystemTable<Boot>) -> ! {
     );
     let mem_map = terminate_boot_services(image, system_table);
 
-    let mut boot_info = kernelboot::Info::new(entry_addr, vram_info, mem_map);
+    let mut boot_info = kernelboot::Info::new(entry_addr, vram_info, mem_map, reserved_regions);
 
     paging::init(&mut boot_info, &reserved_regions);
     exit::bootx64(boot_info);
@@ -55,9 +55,14 @@ pub fn init(boot_info: &mut kernelboot::Info, reserved: &reserved::Map) {
 fn enable_recursive_mapping() {
     let p4: &mut PageTable = unsafe { &mut *(get_pml4_addr().as_u64() as *mut _) };
 
-    p4[511].set_addr(get_pml4_addr(), PageTableFlags::PRESENT);
+    p4[511].set_addr(
+        get_pml4_addr(),
+        PageTableFlags::PRESENT | PageTableFlags::WRITABLE,
+    );
 }
 
+// Some firmware maps its page tables read-only. The kernel enables the protection again once it has
+// tightened the permissions of its own mappings.
 fn remove_table_protection() {
     unsafe {
         Cr0::update(|flags| {
@@ -80,8 +85,8 @@ fn map_virt_to_phys(region: &reserved::Range, allocator: &mut AllocatorWithEfiMe
                 PhysFrame::containing_address(
                     region.phys() + usize::try_from(Size4KiB::SIZE).unwrap() * i,
                 ),
-                PageTableFlags::PRESENT,
-                PageTableFlags::PRESENT,
+                PageTableFlags::PRESENT | PageTableFlags::WRITABLE,
+                PageTableFlags::PRESENT | PageTableFlags::WRITABLE,
                 allocator,
             )
         }
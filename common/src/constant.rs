@@ -20,9 +20,16 @@ pub const STACK_LOWER: VirtAddr =
 pub const INIT_RSP: VirtAddr = VirtAddr::new_truncate(STACK_BASE.as_u64() - Size4KiB::SIZE);
 pub const RECUR_PML4_ADDR: VirtAddr = VirtAddr::new_truncate(0xffff_ffff_ffff_f000);
 
+pub const PHYS_MAP_ADDR: VirtAddr = VirtAddr::new_truncate(0xffff_8000_0000_0000);
+pub const MMIO_ADDR: VirtAddr = VirtAddr::new_truncate(0xffff_c800_0000_0000);
+pub const VMALLOC_ADDR: VirtAddr = VirtAddr::new_truncate(0xffff_d000_0000_0000);
+
 pub const NUM_OF_PAGES_STACK: NumOfPages<Size4KiB> = NumOfPages::new(16);
 pub const BYTES_KERNEL_HEAP: Bytes = Bytes::new(0x1000_0000);
-pub const BYTES_AVAILABLE_RAM: Bytes = Bytes::new(0x1_0000_0000_0000);
+pub const BYTES_KERNEL_HEAP_INIT: Bytes = Bytes::new(0x40_0000);
+pub const BYTES_PHYS_MAP_AREA: Bytes = Bytes::new(0x4000_0000_0000);
+pub const BYTES_MMIO_AREA: Bytes = Bytes::new(0x10_0000_0000);
+pub const BYTES_VMALLOC_AREA: Bytes = Bytes::new(0x10_0000_0000);
 
 pub const PORT_KEY_STATUS: Port<u8> = Port::new(0x0064);
 pub const PORT_KEY_CMD: Port<u8> = Port::new(0x0064);
@@ -1,6 +1,10 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use crate::{constant::INIT_RSP, mem, vram};
+use crate::{
+    constant::INIT_RSP,
+    mem::{self, reserved},
+    vram,
+};
 use core::ptr;
 use uefi::table::boot;
 use x86_64::VirtAddr;
@@ -10,16 +14,23 @@ pub struct Info {
     entry_addr: VirtAddr,
     vram_info: vram::Info,
     mem_map: mem::Map,
+    reserved: reserved::Map,
 }
 
 impl Info {
     #[allow(clippy::too_many_arguments)]
     #[must_use]
-    pub fn new(entry_addr: VirtAddr, vram_info: vram::Info, mem_map: mem::Map) -> Self {
+    pub fn new(
+        entry_addr: VirtAddr,
+        vram_info: vram::Info,
+        mem_map: mem::Map,
+        reserved: reserved::Map,
+    ) -> Self {
         Self {
             entry_addr,
             vram_info,
             mem_map,
+            reserved,
         }
     }
 
@@ -44,6 +55,12 @@ impl Info {
         unsafe { ptr::read(INIT_RSP.as_mut_ptr() as _) }
     }
 
+    // Physical regions bootx64 allocated for the kernel. They must not be reclaimed.
+    #[must_use]
+    pub fn reserved(&self) -> &reserved::Map {
+        &self.reserved
+    }
+
     #[must_use]
     pub fn mem_map_mut(&mut self) -> &mut [boot::MemoryDescriptor] {
         self.mem_map.as_mut_slice()
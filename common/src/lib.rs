@@ -11,4 +11,7 @@ pub mod kernelboot;
 pub mod mem;
 pub mod vram;
 
+// Shared so that bootx64 and the kernel parse ELF files with the same version.
+pub use elf_rs;
+
 extern crate x86_64;
@@ -93,4 +93,9 @@ impl Range {
     pub fn bytes(&self) -> Bytes {
         self.bytes
     }
+
+    #[must_use]
+    pub fn contains(&self, addr: PhysAddr) -> bool {
+        self.phys <= addr && addr < self.phys + self.bytes.as_usize()
+    }
 }
@@ -0,0 +1,21 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+#![no_std]
+#![no_main]
+#![deny(clippy::pedantic)]
+#![deny(clippy::all)]
+
+use ramen::{env, println, process, syscall};
+
+ramen::entry!(main);
+
+fn main() {
+    let name = env::args().next().unwrap_or("init");
+
+    println!(
+        "{} started as process {} at {} ms.",
+        name,
+        process::id(),
+        syscall::uptime()
+    );
+}
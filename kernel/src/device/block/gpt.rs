@@ -0,0 +1,76 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use {
+    super::{Device, BYTES_PER_SECTOR},
+    crate::mem::dma,
+    core::convert::{TryFrom, TryInto},
+};
+
+// 0657FD6D-A4AB-43C4-84E5-0933C84B4F4F. The first three fields are little endian on disk.
+pub const LINUX_SWAP: [u8; 16] = [
+    0x6d, 0xfd, 0x57, 0x06, 0xab, 0xa4, 0xc4, 0x43, 0x84, 0xe5, 0x09, 0x33, 0xc8, 0x4b, 0x4f, 0x4f,
+];
+
+const SIGNATURE: &[u8] = b"EFI PART";
+const LBA_HEADER: u64 = 1;
+
+// The specification requires a power of two of at least 128. An entry larger than a sector is
+// not supported.
+const MIN_BYTES_PER_ENTRY: usize = 128;
+
+#[derive(Copy, Clone, Debug)]
+pub struct Partition {
+    pub first_lba: u64,
+    pub last_lba: u64,
+}
+impl Partition {
+    pub fn num_of_sectors(&self) -> u64 {
+        self.last_lba - self.first_lba + 1
+    }
+}
+
+pub fn find(device: &mut dyn Device, type_guid: [u8; 16]) -> Option<Partition> {
+    let mut sector = dma::Coherent::new_slice(0_u8, BYTES_PER_SECTOR, dma::Constraints::new());
+
+    device.read(LBA_HEADER, &mut sector).ok()?;
+    if &sector[0..8] != SIGNATURE {
+        return None;
+    }
+
+    let entries_lba = u64::from_le_bytes(sector[72..80].try_into().unwrap());
+    let num_of_entries = u32::from_le_bytes(sector[80..84].try_into().unwrap());
+    let bytes_per_entry =
+        usize::try_from(u32::from_le_bytes(sector[84..88].try_into().unwrap())).unwrap();
+    if !bytes_per_entry.is_power_of_two()
+        || !(MIN_BYTES_PER_ENTRY..=BYTES_PER_SECTOR).contains(&bytes_per_entry)
+    {
+        return None;
+    }
+    let entries_per_sector = BYTES_PER_SECTOR / bytes_per_entry;
+
+    for i in 0..usize::try_from(num_of_entries).unwrap() {
+        if i % entries_per_sector == 0 {
+            let lba = entries_lba.checked_add(u64::try_from(i / entries_per_sector).unwrap())?;
+            device.read(lba, &mut sector).ok()?;
+        }
+
+        let offset = i % entries_per_sector * bytes_per_entry;
+        let entry = &sector[offset..offset + bytes_per_entry];
+
+        if entry[0..16] == type_guid {
+            let first_lba = u64::from_le_bytes(entry[32..40].try_into().unwrap());
+            let last_lba = u64::from_le_bytes(entry[40..48].try_into().unwrap());
+
+            return if first_lba <= last_lba {
+                Some(Partition {
+                    first_lba,
+                    last_lba,
+                })
+            } else {
+                None
+            };
+        }
+    }
+
+    None
+}
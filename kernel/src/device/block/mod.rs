@@ -0,0 +1,21 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+pub mod gpt;
+
+use crate::mem::dma::sg;
+
+pub const BYTES_PER_SECTOR: usize = 512;
+
+// Every page of `buf` must be mapped, and its length must be a multiple of `BYTES_PER_SECTOR`. A
+// buffer which is split into too many physical segments is rejected. Transfers are synchronous
+// because the page fault handler reads swapped-out pages through this trait.
+pub trait Device {
+    fn read(&mut self, lba: u64, buf: &mut [u8]) -> Result<(), Error>;
+    fn write(&mut self, lba: u64, buf: &[u8]) -> Result<(), Error>;
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Buffer(sg::Error),
+    DeviceError,
+}
@@ -0,0 +1,186 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use super::{scancode::Key, Modifiers};
+
+// `keyboard::Keymap` reads better than `keyboard::Map`.
+#[allow(clippy::module_name_repetitions)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Keymap {
+    Us,
+    Jis,
+}
+impl Keymap {
+    pub const ALL: [Self; 2] = [Self::Us, Self::Jis];
+
+    // Returns `None` for keys which do not type anything, like modifiers, and for the keypad keys
+    // which move the cursor while Num Lock is off.
+    pub fn char(self, key: Key, modifiers: Modifiers) -> Option<char> {
+        if let Some(c) = letter(key) {
+            return Some(char::from(if modifiers.ctrl() {
+                // Ctrl-A is 0x01 and so on.
+                c - b'a' + 1
+            } else if modifiers.shift() != modifiers.caps_lock {
+                c.to_ascii_uppercase()
+            } else {
+                c
+            }));
+        }
+
+        if let Some(c) = keypad(key, modifiers) {
+            return Some(c);
+        }
+
+        if let Some(c) = control(key) {
+            return Some(c);
+        }
+
+        let (normal, shifted) = match self {
+            Self::Us => us(key)?,
+            Self::Jis => jis(key)?,
+        };
+        if modifiers.shift() {
+            shifted
+        } else {
+            Some(normal)
+        }
+    }
+}
+
+fn letter(key: Key) -> Option<u8> {
+    let c = match key {
+        Key::A => b'a',
+        Key::B => b'b',
+        Key::C => b'c',
+        Key::D => b'd',
+        Key::E => b'e',
+        Key::F => b'f',
+        Key::G => b'g',
+        Key::H => b'h',
+        Key::I => b'i',
+        Key::J => b'j',
+        Key::K => b'k',
+        Key::L => b'l',
+        Key::M => b'm',
+        Key::N => b'n',
+        Key::O => b'o',
+        Key::P => b'p',
+        Key::Q => b'q',
+        Key::R => b'r',
+        Key::S => b's',
+        Key::T => b't',
+        Key::U => b'u',
+        Key::V => b'v',
+        Key::W => b'w',
+        Key::X => b'x',
+        Key::Y => b'y',
+        Key::Z => b'z',
+        _ => return None,
+    };
+
+    Some(c)
+}
+
+// Shift inverts Num Lock, as on other systems.
+fn keypad(key: Key, modifiers: Modifiers) -> Option<char> {
+    let digits = modifiers.num_lock != modifiers.shift();
+
+    let c = match key {
+        Key::KeypadPlus => '+',
+        Key::KeypadMinus => '-',
+        Key::KeypadMultiply => '*',
+        Key::KeypadDivide => '/',
+        Key::KeypadEnter => '\n',
+        _ if !digits => return None,
+        Key::Keypad0 => '0',
+        Key::Keypad1 => '1',
+        Key::Keypad2 => '2',
+        Key::Keypad3 => '3',
+        Key::Keypad4 => '4',
+        Key::Keypad5 => '5',
+        Key::Keypad6 => '6',
+        Key::Keypad7 => '7',
+        Key::Keypad8 => '8',
+        Key::Keypad9 => '9',
+        Key::KeypadPeriod => '.',
+        _ => return None,
+    };
+
+    Some(c)
+}
+
+fn control(key: Key) -> Option<char> {
+    let c = match key {
+        Key::Escape => '\x1b',
+        Key::Backspace => '\x08',
+        Key::Tab => '\t',
+        Key::Enter => '\n',
+        Key::Space => ' ',
+        Key::Delete => '\x7f',
+        _ => return None,
+    };
+
+    Some(c)
+}
+
+// Returns the characters without and with Shift.
+fn us(key: Key) -> Option<(char, Option<char>)> {
+    let (normal, shifted) = match key {
+        Key::Num1 => ('1', '!'),
+        Key::Num2 => ('2', '@'),
+        Key::Num3 => ('3', '#'),
+        Key::Num4 => ('4', '$'),
+        Key::Num5 => ('5', '%'),
+        Key::Num6 => ('6', '^'),
+        Key::Num7 => ('7', '&'),
+        Key::Num8 => ('8', '*'),
+        Key::Num9 => ('9', '('),
+        Key::Num0 => ('0', ')'),
+        Key::Minus => ('-', '_'),
+        Key::Equal => ('=', '+'),
+        Key::LeftBracket => ('[', '{'),
+        Key::RightBracket => (']', '}'),
+        Key::Backslash | Key::NonUsBackslash => ('\\', '|'),
+        Key::Semicolon => (';', ':'),
+        Key::Quote => ('\'', '"'),
+        Key::Backquote => ('`', '~'),
+        Key::Comma => (',', '<'),
+        Key::Period => ('.', '>'),
+        Key::Slash => ('/', '?'),
+        _ => return None,
+    };
+
+    Some((normal, Some(shifted)))
+}
+
+// The keys are named after the US positions. For example, `Key::LeftBracket` is the `@` key.
+fn jis(key: Key) -> Option<(char, Option<char>)> {
+    let (normal, shifted) = match key {
+        Key::Num1 => ('1', Some('!')),
+        Key::Num2 => ('2', Some('"')),
+        Key::Num3 => ('3', Some('#')),
+        Key::Num4 => ('4', Some('$')),
+        Key::Num5 => ('5', Some('%')),
+        Key::Num6 => ('6', Some('&')),
+        Key::Num7 => ('7', Some('\'')),
+        Key::Num8 => ('8', Some('(')),
+        Key::Num9 => ('9', Some(')')),
+        // Shift-0 types nothing.
+        Key::Num0 => ('0', None),
+        Key::Minus => ('-', Some('=')),
+        Key::Equal => ('^', Some('~')),
+        // The Yen key types a backslash, which Japanese fonts draw as a yen sign.
+        Key::Yen => ('\\', Some('|')),
+        Key::LeftBracket => ('@', Some('`')),
+        Key::RightBracket => ('[', Some('{')),
+        Key::Backslash => (']', Some('}')),
+        Key::Semicolon => (';', Some('+')),
+        Key::Quote => (':', Some('*')),
+        Key::Comma => (',', Some('<')),
+        Key::Period => ('.', Some('>')),
+        Key::Slash => ('/', Some('?')),
+        Key::Ro => ('\\', Some('_')),
+        _ => return None,
+    };
+
+    Some((normal, shifted))
+}
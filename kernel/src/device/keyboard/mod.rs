@@ -0,0 +1,317 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// The PS/2 keyboard. Scancodes are decoded into `KeyEvent`s, and typed characters are looked up
+// in the current keymap. Processes still get the raw scancodes through `read_keys`.
+
+mod keymap;
+mod scancode;
+
+pub use {
+    keymap::Keymap,
+    scancode::{Key, Set},
+};
+
+use {
+    crate::{
+        mem::{allocator::heap::debug, meminfo},
+        multitask::{spawner::Spawner, sync::Channel},
+    },
+    common::constant::{
+        KEY_CMD_MODE, KEY_CMD_WRITE_MODE, KEY_STATUS_SEND_NOT_READY, PORT_KEY_CMD, PORT_KEY_DATA,
+        PORT_KEY_STATUS,
+    },
+    conquer_once::spin::Lazy,
+    core::sync::atomic::{AtomicU64, Ordering},
+    crossbeam_queue::ArrayQueue,
+    futures_util::stream::StreamExt,
+    scancode::Decoder,
+    spinning_top::Spinlock,
+};
+
+const SIZE_OF_SCANCODE_QUEUE: usize = 100;
+
+// The controller translates set 2 into set 1 if this bit of the mode is set.
+const KEY_MODE_TRANSLATE: u8 = 0x40;
+
+const KEY_CMD_SET_LEDS: u8 = 0xed;
+const KEY_RESPONSE_ACK: u8 = 0xfa;
+const KEY_RESPONSE_RESEND: u8 = 0xfe;
+
+const LED_SCROLL_LOCK: u8 = 1;
+const LED_NUM_LOCK: u8 = 2;
+const LED_CAPS_LOCK: u8 = 4;
+
+static SCANCODES: Channel<u8> = Channel::new(SIZE_OF_SCANCODE_QUEUE);
+
+// Scancodes for the `read_keys` system call. The oldest ones are dropped when this is full.
+static KEYS_FOR_PROCESSES: Lazy<ArrayQueue<u8>> =
+    Lazy::new(|| ArrayQueue::new(SIZE_OF_SCANCODE_QUEUE));
+
+static KEYMAP: Spinlock<Keymap> = Spinlock::new(Keymap::Us);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct KeyEvent {
+    pub key: Key,
+    pub pressed: bool,
+    // After this event is applied.
+    pub modifiers: Modifiers,
+}
+
+// Each side is tracked, so that releasing one Shift while holding the other keeps Shift on.
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Modifiers {
+    pub left_shift: bool,
+    pub right_shift: bool,
+    pub left_ctrl: bool,
+    pub right_ctrl: bool,
+    pub left_alt: bool,
+    pub right_alt: bool,
+    pub caps_lock: bool,
+    pub num_lock: bool,
+    pub scroll_lock: bool,
+}
+impl Modifiers {
+    pub fn shift(self) -> bool {
+        self.left_shift || self.right_shift
+    }
+
+    pub fn ctrl(self) -> bool {
+        self.left_ctrl || self.right_ctrl
+    }
+
+    pub fn alt(self) -> bool {
+        self.left_alt || self.right_alt
+    }
+
+    // A lock key toggles only when it is pressed, not on each repeat while it is held.
+    fn update(&mut self, key: Key, pressed: bool, repeated: bool) {
+        match key {
+            Key::LeftShift => self.left_shift = pressed,
+            Key::RightShift => self.right_shift = pressed,
+            Key::LeftCtrl => self.left_ctrl = pressed,
+            Key::RightCtrl => self.right_ctrl = pressed,
+            Key::LeftAlt => self.left_alt = pressed,
+            Key::RightAlt => self.right_alt = pressed,
+            Key::CapsLock if pressed && !repeated => self.caps_lock = !self.caps_lock,
+            Key::NumLock if pressed && !repeated => self.num_lock = !self.num_lock,
+            Key::ScrollLock if pressed && !repeated => self.scroll_lock = !self.scroll_lock,
+            _ => {}
+        }
+    }
+
+    fn leds(self) -> u8 {
+        let mut leds = 0;
+        if self.scroll_lock {
+            leds |= LED_SCROLL_LOCK;
+        }
+        if self.num_lock {
+            leds |= LED_NUM_LOCK;
+        }
+        if self.caps_lock {
+            leds |= LED_CAPS_LOCK;
+        }
+        leds
+    }
+}
+
+pub async fn task() {
+    SCANCODES.init();
+
+    enable_keyboard();
+
+    let mut keyboard = Keyboard::new();
+    keyboard.leds.set(keyboard.modifiers.leds());
+
+    let mut scancode_stream = SCANCODES.receiver();
+
+    while let Some(code) = scancode_stream.next().await {
+        match code {
+            KEY_RESPONSE_ACK => keyboard.leds.on_ack(),
+            KEY_RESPONSE_RESEND => keyboard.leds.on_resend(),
+            _ => {
+                push_for_processes(code);
+                if let Some(event) = keyboard.feed(code) {
+                    handle_event(event);
+                }
+            }
+        }
+    }
+}
+
+pub fn enqueue_scancode(code: u8) {
+    if SCANCODES.send(code).is_err() {
+        warn!("The scancode channel is full.");
+    }
+}
+
+pub fn pop_for_process() -> Option<u8> {
+    KEYS_FOR_PROCESSES.pop()
+}
+
+pub fn keymap() -> Keymap {
+    *KEYMAP.lock()
+}
+
+pub fn set_keymap(keymap: Keymap) {
+    *KEYMAP.lock() = keymap;
+}
+
+fn handle_event(event: KeyEvent) {
+    if !event.pressed {
+        return;
+    }
+
+    match event.key {
+        Key::F9 if cfg!(feature = "heap_debug") => log_new_allocations(),
+        Key::F10 => switch_keymap(),
+        Key::F11 => Spawner::current().log_stats(),
+        Key::F12 => meminfo::log(),
+        key => match keymap().char(key, event.modifiers) {
+            Some(c) => info!("{:?} pressed.", c),
+            None => trace!("{:?}", event),
+        },
+    }
+}
+
+fn switch_keymap() {
+    let current = keymap();
+    let position = Keymap::ALL.iter().position(|&k| k == current).unwrap_or(0);
+    let next = Keymap::ALL[(position + 1) % Keymap::ALL.len()];
+
+    set_keymap(next);
+    info!("Keymap: {:?}", next);
+}
+
+// Logs the allocations made since the last press, which helps to find a leak by repeating an
+// operation between presses.
+fn log_new_allocations() {
+    static CHECKPOINT: AtomicU64 = AtomicU64::new(0);
+
+    let since = CHECKPOINT.swap(debug::checkpoint(), Ordering::Relaxed);
+    debug::report_allocations_since(since);
+}
+
+fn push_for_processes(code: u8) {
+    while KEYS_FOR_PROCESSES.push(code).is_err() {
+        KEYS_FOR_PROCESSES.pop();
+    }
+}
+
+struct Keyboard {
+    decoder: Decoder,
+    modifiers: Modifiers,
+    leds: Leds,
+    // To tell a repeat from a new press.
+    last_pressed: Option<Key>,
+}
+impl Keyboard {
+    fn new() -> Self {
+        Self {
+            decoder: Decoder::new(scancode_set()),
+            modifiers: Modifiers::default(),
+            leds: Leds::new(),
+            last_pressed: None,
+        }
+    }
+
+    fn feed(&mut self, code: u8) -> Option<KeyEvent> {
+        let (key, pressed) = self.decoder.feed(code)?;
+
+        let repeated = pressed && self.last_pressed == Some(key);
+        self.last_pressed = if pressed { Some(key) } else { None };
+
+        self.modifiers.update(key, pressed, repeated);
+        self.leds.set(self.modifiers.leds());
+
+        Some(KeyEvent {
+            key,
+            pressed,
+            modifiers: self.modifiers,
+        })
+    }
+}
+
+// The keyboard acknowledges each byte sent to it, and the next one is sent only after that. The
+// acknowledgement arrives as a scancode.
+struct Leds {
+    // Not acknowledged yet.
+    sent: Option<u8>,
+    wanted: u8,
+    // `None` until the first update, as the state the firmware left is unknown.
+    applied: Option<u8>,
+}
+impl Leds {
+    fn new() -> Self {
+        Self {
+            sent: None,
+            wanted: 0,
+            applied: None,
+        }
+    }
+
+    fn set(&mut self, leds: u8) {
+        self.wanted = leds;
+        if self.sent.is_none() && self.applied != Some(leds) {
+            self.send(KEY_CMD_SET_LEDS);
+        }
+    }
+
+    fn on_ack(&mut self) {
+        match self.sent.take() {
+            Some(KEY_CMD_SET_LEDS) => self.send(self.wanted),
+            Some(leds) => {
+                self.applied = Some(leds);
+                self.set(self.wanted);
+            }
+            None => {}
+        }
+    }
+
+    fn on_resend(&mut self) {
+        if let Some(byte) = self.sent {
+            write_data(byte);
+        }
+    }
+
+    fn send(&mut self, byte: u8) {
+        write_data(byte);
+        self.sent = Some(byte);
+    }
+}
+
+fn scancode_set() -> Set {
+    if KEY_CMD_MODE & KEY_MODE_TRANSLATE == 0 {
+        Set::Two
+    } else {
+        Set::One
+    }
+}
+
+fn enable_keyboard() {
+    wait_kbc_sendready();
+
+    let mut port_key_cmd = PORT_KEY_CMD;
+    unsafe { port_key_cmd.write(KEY_CMD_WRITE_MODE as u8) };
+
+    wait_kbc_sendready();
+
+    let mut port_key_data = PORT_KEY_DATA;
+    unsafe { port_key_data.write(KEY_CMD_MODE as u8) };
+}
+
+fn write_data(byte: u8) {
+    wait_kbc_sendready();
+
+    let mut port_key_data = PORT_KEY_DATA;
+    unsafe { port_key_data.write(byte) };
+}
+
+pub(super) fn wait_kbc_sendready() {
+    loop {
+        let mut port_key_status = PORT_KEY_STATUS;
+        if unsafe { port_key_status.read() } & KEY_STATUS_SEND_NOT_READY == 0 {
+            break;
+        }
+    }
+}
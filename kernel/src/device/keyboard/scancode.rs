@@ -0,0 +1,500 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Decodes scancode set 1 and set 2 into keys. A key is named after its position on a US
+// keyboard, except the ones only Japanese keyboards have.
+
+const PREFIX_EXTENDED: u8 = 0xe0;
+const PREFIX_PAUSE: u8 = 0xe1;
+// Set 2 only. Set 1 sets the top bit of the code instead.
+const PREFIX_BREAK: u8 = 0xf0;
+const SET_1_BREAK: u8 = 0x80;
+
+// The PrintScreen key sends a Shift code before its own one. Other keys which have the same code
+// with and without Num Lock do the same depending on the state of Shift.
+const SET_1_FAKE_SHIFTS: [u8; 2] = [0x2a, 0x36];
+const SET_2_FAKE_SHIFTS: [u8; 2] = [0x12, 0x59];
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Set {
+    One,
+    Two,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Key {
+    Escape,
+    Num1,
+    Num2,
+    Num3,
+    Num4,
+    Num5,
+    Num6,
+    Num7,
+    Num8,
+    Num9,
+    Num0,
+    Minus,
+    Equal,
+    Backspace,
+    Tab,
+    Q,
+    W,
+    E,
+    R,
+    T,
+    Y,
+    U,
+    I,
+    O,
+    P,
+    LeftBracket,
+    RightBracket,
+    Enter,
+    LeftCtrl,
+    A,
+    S,
+    D,
+    F,
+    G,
+    H,
+    J,
+    K,
+    L,
+    Semicolon,
+    Quote,
+    Backquote,
+    LeftShift,
+    Backslash,
+    Z,
+    X,
+    C,
+    V,
+    B,
+    N,
+    M,
+    Comma,
+    Period,
+    Slash,
+    RightShift,
+    LeftAlt,
+    Space,
+    CapsLock,
+    F1,
+    F2,
+    F3,
+    F4,
+    F5,
+    F6,
+    F7,
+    F8,
+    F9,
+    F10,
+    F11,
+    F12,
+    NumLock,
+    ScrollLock,
+    Keypad0,
+    Keypad1,
+    Keypad2,
+    Keypad3,
+    Keypad4,
+    Keypad5,
+    Keypad6,
+    Keypad7,
+    Keypad8,
+    Keypad9,
+    KeypadPeriod,
+    KeypadPlus,
+    KeypadMinus,
+    KeypadMultiply,
+    KeypadDivide,
+    KeypadEnter,
+    // The key between the left Shift and Z on ISO keyboards.
+    NonUsBackslash,
+    RightCtrl,
+    RightAlt,
+    LeftMeta,
+    RightMeta,
+    Menu,
+    Insert,
+    Delete,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Up,
+    Down,
+    Left,
+    Right,
+    PrintScreen,
+    Pause,
+    // Japanese keyboards.
+    Yen,
+    Ro,
+    Henkan,
+    Muhenkan,
+    KatakanaHiragana,
+}
+
+enum State {
+    Normal,
+    Extended,
+    // Set 2 only.
+    Break { extended: bool },
+    // Pause sends the sequences for pressing and releasing at once, both after `PREFIX_PAUSE`.
+    Pause { left: u8, released: bool },
+}
+
+pub struct Decoder {
+    set: Set,
+    state: State,
+}
+impl Decoder {
+    pub const fn new(set: Set) -> Self {
+        Self {
+            set,
+            state: State::Normal,
+        }
+    }
+
+    // Returns the key and whether it is pressed once a sequence ends. An unknown sequence is
+    // dropped.
+    pub fn feed(&mut self, byte: u8) -> Option<(Key, bool)> {
+        match self.set {
+            Set::One => self.feed_set_1(byte),
+            Set::Two => self.feed_set_2(byte),
+        }
+    }
+
+    fn feed_set_1(&mut self, byte: u8) -> Option<(Key, bool)> {
+        match self.state {
+            State::Normal => match byte {
+                PREFIX_EXTENDED => self.enter(State::Extended),
+                PREFIX_PAUSE => self.enter(State::Pause {
+                    left: 2,
+                    released: false,
+                }),
+                _ => set_1(byte & !SET_1_BREAK).map(|key| (key, byte & SET_1_BREAK == 0)),
+            },
+            State::Extended => {
+                self.state = State::Normal;
+
+                let code = byte & !SET_1_BREAK;
+                if SET_1_FAKE_SHIFTS.contains(&code) {
+                    None
+                } else {
+                    set_1_extended(code).map(|key| (key, byte & SET_1_BREAK == 0))
+                }
+            }
+            // `E1 1D 45` when pressed, and `E1 9D C5` when released.
+            State::Pause { left, released } => {
+                self.feed_pause(left, released || byte & SET_1_BREAK != 0)
+            }
+            State::Break { .. } => unreachable!("Set 1 has no break prefix."),
+        }
+    }
+
+    fn feed_set_2(&mut self, byte: u8) -> Option<(Key, bool)> {
+        match self.state {
+            State::Normal => match byte {
+                PREFIX_EXTENDED => self.enter(State::Extended),
+                PREFIX_PAUSE => self.enter(State::Pause {
+                    left: 2,
+                    released: false,
+                }),
+                PREFIX_BREAK => self.enter(State::Break { extended: false }),
+                _ => set_2(byte).map(|key| (key, true)),
+            },
+            State::Extended if byte == PREFIX_BREAK => self.enter(State::Break { extended: true }),
+            State::Extended => {
+                self.state = State::Normal;
+                set_2_extended(byte).map(|key| (key, true))
+            }
+            State::Break { extended } => {
+                self.state = State::Normal;
+
+                if !extended {
+                    set_2(byte).map(|key| (key, false))
+                } else if SET_2_FAKE_SHIFTS.contains(&byte) {
+                    None
+                } else {
+                    set_2_extended(byte).map(|key| (key, false))
+                }
+            }
+            // `E1 14 77` when pressed, and `E1 F0 14 F0 77` when released. The break prefixes
+            // are not counted.
+            State::Pause { left, .. } if byte == PREFIX_BREAK => self.enter(State::Pause {
+                left,
+                released: true,
+            }),
+            State::Pause { left, released } => self.feed_pause(left, released),
+        }
+    }
+
+    fn feed_pause(&mut self, left: u8, released: bool) -> Option<(Key, bool)> {
+        if left > 1 {
+            self.enter(State::Pause {
+                left: left - 1,
+                released,
+            })
+        } else {
+            self.state = State::Normal;
+            Some((Key::Pause, !released))
+        }
+    }
+
+    fn enter(&mut self, state: State) -> Option<(Key, bool)> {
+        self.state = state;
+        None
+    }
+}
+
+fn set_1(code: u8) -> Option<Key> {
+    let key = match code {
+        0x01 => Key::Escape,
+        0x02 => Key::Num1,
+        0x03 => Key::Num2,
+        0x04 => Key::Num3,
+        0x05 => Key::Num4,
+        0x06 => Key::Num5,
+        0x07 => Key::Num6,
+        0x08 => Key::Num7,
+        0x09 => Key::Num8,
+        0x0a => Key::Num9,
+        0x0b => Key::Num0,
+        0x0c => Key::Minus,
+        0x0d => Key::Equal,
+        0x0e => Key::Backspace,
+        0x0f => Key::Tab,
+        0x10 => Key::Q,
+        0x11 => Key::W,
+        0x12 => Key::E,
+        0x13 => Key::R,
+        0x14 => Key::T,
+        0x15 => Key::Y,
+        0x16 => Key::U,
+        0x17 => Key::I,
+        0x18 => Key::O,
+        0x19 => Key::P,
+        0x1a => Key::LeftBracket,
+        0x1b => Key::RightBracket,
+        0x1c => Key::Enter,
+        0x1d => Key::LeftCtrl,
+        0x1e => Key::A,
+        0x1f => Key::S,
+        0x20 => Key::D,
+        0x21 => Key::F,
+        0x22 => Key::G,
+        0x23 => Key::H,
+        0x24 => Key::J,
+        0x25 => Key::K,
+        0x26 => Key::L,
+        0x27 => Key::Semicolon,
+        0x28 => Key::Quote,
+        0x29 => Key::Backquote,
+        0x2a => Key::LeftShift,
+        0x2b => Key::Backslash,
+        0x2c => Key::Z,
+        0x2d => Key::X,
+        0x2e => Key::C,
+        0x2f => Key::V,
+        0x30 => Key::B,
+        0x31 => Key::N,
+        0x32 => Key::M,
+        0x33 => Key::Comma,
+        0x34 => Key::Period,
+        0x35 => Key::Slash,
+        0x36 => Key::RightShift,
+        0x37 => Key::KeypadMultiply,
+        0x38 => Key::LeftAlt,
+        0x39 => Key::Space,
+        0x3a => Key::CapsLock,
+        0x3b => Key::F1,
+        0x3c => Key::F2,
+        0x3d => Key::F3,
+        0x3e => Key::F4,
+        0x3f => Key::F5,
+        0x40 => Key::F6,
+        0x41 => Key::F7,
+        0x42 => Key::F8,
+        0x43 => Key::F9,
+        0x44 => Key::F10,
+        0x45 => Key::NumLock,
+        0x46 => Key::ScrollLock,
+        0x47 => Key::Keypad7,
+        0x48 => Key::Keypad8,
+        0x49 => Key::Keypad9,
+        0x4a => Key::KeypadMinus,
+        0x4b => Key::Keypad4,
+        0x4c => Key::Keypad5,
+        0x4d => Key::Keypad6,
+        0x4e => Key::KeypadPlus,
+        0x4f => Key::Keypad1,
+        0x50 => Key::Keypad2,
+        0x51 => Key::Keypad3,
+        0x52 => Key::Keypad0,
+        0x53 => Key::KeypadPeriod,
+        0x56 => Key::NonUsBackslash,
+        0x57 => Key::F11,
+        0x58 => Key::F12,
+        0x70 => Key::KatakanaHiragana,
+        0x73 => Key::Ro,
+        0x79 => Key::Henkan,
+        0x7b => Key::Muhenkan,
+        0x7d => Key::Yen,
+        _ => return None,
+    };
+
+    Some(key)
+}
+
+fn set_1_extended(code: u8) -> Option<Key> {
+    let key = match code {
+        0x1c => Key::KeypadEnter,
+        0x1d => Key::RightCtrl,
+        0x35 => Key::KeypadDivide,
+        0x37 => Key::PrintScreen,
+        0x38 => Key::RightAlt,
+        0x47 => Key::Home,
+        0x48 => Key::Up,
+        0x49 => Key::PageUp,
+        0x4b => Key::Left,
+        0x4d => Key::Right,
+        0x4f => Key::End,
+        0x50 => Key::Down,
+        0x51 => Key::PageDown,
+        0x52 => Key::Insert,
+        0x53 => Key::Delete,
+        0x5b => Key::LeftMeta,
+        0x5c => Key::RightMeta,
+        0x5d => Key::Menu,
+        _ => return None,
+    };
+
+    Some(key)
+}
+
+fn set_2(code: u8) -> Option<Key> {
+    let key = match code {
+        0x01 => Key::F9,
+        0x03 => Key::F5,
+        0x04 => Key::F3,
+        0x05 => Key::F1,
+        0x06 => Key::F2,
+        0x07 => Key::F12,
+        0x09 => Key::F10,
+        0x0a => Key::F8,
+        0x0b => Key::F6,
+        0x0c => Key::F4,
+        0x0d => Key::Tab,
+        0x0e => Key::Backquote,
+        0x11 => Key::LeftAlt,
+        0x12 => Key::LeftShift,
+        0x13 => Key::KatakanaHiragana,
+        0x14 => Key::LeftCtrl,
+        0x15 => Key::Q,
+        0x16 => Key::Num1,
+        0x1a => Key::Z,
+        0x1b => Key::S,
+        0x1c => Key::A,
+        0x1d => Key::W,
+        0x1e => Key::Num2,
+        0x21 => Key::C,
+        0x22 => Key::X,
+        0x23 => Key::D,
+        0x24 => Key::E,
+        0x25 => Key::Num4,
+        0x26 => Key::Num3,
+        0x29 => Key::Space,
+        0x2a => Key::V,
+        0x2b => Key::F,
+        0x2c => Key::T,
+        0x2d => Key::R,
+        0x2e => Key::Num5,
+        0x31 => Key::N,
+        0x32 => Key::B,
+        0x33 => Key::H,
+        0x34 => Key::G,
+        0x35 => Key::Y,
+        0x36 => Key::Num6,
+        0x3a => Key::M,
+        0x3b => Key::J,
+        0x3c => Key::U,
+        0x3d => Key::Num7,
+        0x3e => Key::Num8,
+        0x41 => Key::Comma,
+        0x42 => Key::K,
+        0x43 => Key::I,
+        0x44 => Key::O,
+        0x45 => Key::Num0,
+        0x46 => Key::Num9,
+        0x49 => Key::Period,
+        0x4a => Key::Slash,
+        0x4b => Key::L,
+        0x4c => Key::Semicolon,
+        0x4d => Key::P,
+        0x4e => Key::Minus,
+        0x51 => Key::Ro,
+        0x52 => Key::Quote,
+        0x54 => Key::LeftBracket,
+        0x55 => Key::Equal,
+        0x58 => Key::CapsLock,
+        0x59 => Key::RightShift,
+        0x5a => Key::Enter,
+        0x5b => Key::RightBracket,
+        0x5d => Key::Backslash,
+        0x61 => Key::NonUsBackslash,
+        0x64 => Key::Henkan,
+        0x66 => Key::Backspace,
+        0x67 => Key::Muhenkan,
+        0x69 => Key::Keypad1,
+        0x6a => Key::Yen,
+        0x6b => Key::Keypad4,
+        0x6c => Key::Keypad7,
+        0x70 => Key::Keypad0,
+        0x71 => Key::KeypadPeriod,
+        0x72 => Key::Keypad2,
+        0x73 => Key::Keypad5,
+        0x74 => Key::Keypad6,
+        0x75 => Key::Keypad8,
+        0x76 => Key::Escape,
+        0x77 => Key::NumLock,
+        0x78 => Key::F11,
+        0x79 => Key::KeypadPlus,
+        0x7a => Key::Keypad3,
+        0x7b => Key::KeypadMinus,
+        0x7c => Key::KeypadMultiply,
+        0x7d => Key::Keypad9,
+        0x7e => Key::ScrollLock,
+        0x83 => Key::F7,
+        _ => return None,
+    };
+
+    Some(key)
+}
+
+fn set_2_extended(code: u8) -> Option<Key> {
+    let key = match code {
+        0x11 => Key::RightAlt,
+        0x14 => Key::RightCtrl,
+        0x1f => Key::LeftMeta,
+        0x27 => Key::RightMeta,
+        0x2f => Key::Menu,
+        0x4a => Key::KeypadDivide,
+        0x5a => Key::KeypadEnter,
+        0x69 => Key::End,
+        0x6b => Key::Left,
+        0x6c => Key::Home,
+        0x70 => Key::Insert,
+        0x71 => Key::Delete,
+        0x72 => Key::Down,
+        0x74 => Key::Right,
+        0x75 => Key::Up,
+        0x7a => Key::PageDown,
+        0x7c => Key::PrintScreen,
+        0x7d => Key::PageUp,
+        _ => return None,
+    };
+
+    Some(key)
+}
@@ -1,5 +1,7 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+pub mod block;
 pub mod keyboard;
 pub mod mouse;
 pub mod pci;
+pub mod timer;
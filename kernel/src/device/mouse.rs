@@ -1,31 +1,27 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use {
-    crate::graphics::screen::cursor::Cursor,
+    crate::{graphics::screen::cursor::Cursor, multitask::sync::Channel},
+    abi::MouseEvent,
     common::constant::{PORT_KEY_CMD, PORT_KEY_DATA},
-    conquer_once::spin::OnceCell,
-    core::{
-        pin::Pin,
-        task::{Context, Poll},
-    },
+    conquer_once::spin::Lazy,
     crossbeam_queue::ArrayQueue,
-    futures_util::{
-        stream::{Stream, StreamExt},
-        task::AtomicWaker,
-    },
+    futures_util::stream::StreamExt,
     vek::Vec2,
 };
 
-static MOUSE_PACKET_QUEUE: OnceCell<ArrayQueue<u8>> = OnceCell::uninit();
-static WAKER: AtomicWaker = AtomicWaker::new();
+static PACKETS: Channel<u8> = Channel::new(100);
+
+// Events for the `read_mouse` system call. The oldest ones are dropped when this is full.
+static EVENTS_FOR_PROCESSES: Lazy<ArrayQueue<MouseEvent>> = Lazy::new(|| ArrayQueue::new(100));
 
 const KEY_CMD_SEND_TO_MOUSE: u8 = 0xD4;
 const MOUSE_CMD_ENABLE: u8 = 0xF4;
 
 pub async fn task() {
-    PacketStream::init_queue();
+    PACKETS.init();
     Device::enable();
-    let mut packet_stream = PacketStream;
+    let mut packet_stream = PACKETS.receiver();
 
     let mut device = Device::new();
     let mut cursor = Cursor::new();
@@ -46,20 +42,23 @@ fn parse_packets(device: &mut Device, cursor: &mut Cursor) {
     device.parse_packets();
     device.print_click_info();
     cursor.move_offset(device.speed());
+    push_for_processes(device.event());
 }
 
-pub fn enqueue_packet(packet: u8) {
-    if queue().push(packet).is_ok() {
-        WAKER.wake();
-    } else {
-        warn!("MOUSE_PACKET_QUEUE is full.")
+pub fn pop_for_process() -> Option<MouseEvent> {
+    EVENTS_FOR_PROCESSES.pop()
+}
+
+fn push_for_processes(event: MouseEvent) {
+    while EVENTS_FOR_PROCESSES.push(event).is_err() {
+        EVENTS_FOR_PROCESSES.pop();
     }
 }
 
-fn queue() -> &'static ArrayQueue<u8> {
-    MOUSE_PACKET_QUEUE
-        .try_get()
-        .expect("MOUSE_PACKET_QUEUE is not initialized.")
+pub fn enqueue_packet(packet: u8) {
+    if PACKETS.send(packet).is_err() {
+        warn!("The channel of mouse packets is full.")
+    }
 }
 
 struct Device {
@@ -100,6 +99,16 @@ impl Device {
         self.speed
     }
 
+    fn event(&self) -> MouseEvent {
+        MouseEvent {
+            dx: self.speed.x,
+            dy: self.speed.y,
+            left: self.buttons.left,
+            right: self.buttons.right,
+            center: self.buttons.center,
+        }
+    }
+
     fn parse_packets(&mut self) {
         self.buttons = self.buf.buttons_info();
         self.speed = self.buf.speed();
@@ -201,29 +210,6 @@ impl Buf {
     }
 }
 
-struct PacketStream;
-impl PacketStream {
-    fn init_queue() {
-        MOUSE_PACKET_QUEUE
-            .try_init_once(|| ArrayQueue::new(100))
-            .expect("MOUSE_PACKET_QUEUE is already initialized.")
-    }
-}
-impl Stream for PacketStream {
-    type Item = u8;
-
-    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        WAKER.register(&cx.waker());
-        match queue().pop() {
-            Some(packet) => {
-                WAKER.take();
-                Poll::Ready(Some(packet))
-            }
-            None => Poll::Pending,
-        }
-    }
-}
-
 #[derive(PartialEq, Eq, Copy, Clone)]
 enum DevicePhase {
     Init,
@@ -5,12 +5,16 @@ mod port;
 mod registers;
 
 use {
-    crate::device::pci::{self, config::bar},
+    crate::{
+        device::{
+            block::gpt,
+            pci::{self, config::bar},
+        },
+        mem::swap,
+    },
     ahc::Ahc,
-    alloc::rc::Rc,
-    core::cell::RefCell,
+    alloc::boxed::Box,
     registers::Registers,
-    x86_64::PhysAddr,
 };
 
 pub async fn task() {
@@ -21,19 +25,42 @@ pub async fn task() {
 
     place_into_minimally_initialized_state(&mut ahc, &mut ports);
     ahc.get_ownership_from_bios();
+
+    enable_swap(ports);
+}
+
+// The first port with a Linux swap partition is handed to the swap subsystem. The other ports are
+// not used yet.
+fn enable_swap(ports: port::Collection) {
+    for mut port in ports.into_vec() {
+        if !port.start() {
+            continue;
+        }
+
+        if let Some(partition) = gpt::find(&mut port, gpt::LINUX_SWAP) {
+            swap::init(Box::new(port), partition);
+
+            #[cfg(feature = "swap_test")]
+            swap::exercise::run();
+
+            return;
+        }
+    }
 }
 
 fn init() -> Option<(Ahc, port::Collection)> {
-    let registers = Rc::new(RefCell::new(fetch_registers()?));
-    let ahc = Ahc::new(registers.clone());
-    let port_collection = port::Collection::new(&registers);
+    // Each port takes its own registers so that the swap subsystem can own the port.
+    let mut registers = fetch_registers()?;
+    let port_collection = port::Collection::new(&mut registers);
+    let ahc = Ahc::new(registers);
 
     Some((ahc, port_collection))
 }
 
 fn fetch_registers() -> Option<Registers> {
-    let abar = AchiBaseAddr::new()?;
-    Some(Registers::new(abar))
+    let device = pci::iter_devices().find(pci::config::Space::is_ahci)?;
+    let abar = device.map_bar(bar::Index::new(5));
+    Some(Registers::new(&abar))
 }
 
 fn place_into_minimally_initialized_state(ahc: &mut Ahc, ports: &mut port::Collection) {
@@ -43,23 +70,3 @@ fn place_into_minimally_initialized_state(ahc: &mut Ahc, ports: &mut port::Colle
     ports.register_command_lists_and_fis();
     ports.clear_error_bits();
 }
-
-#[derive(Copy, Clone)]
-pub struct AchiBaseAddr(PhysAddr);
-impl AchiBaseAddr {
-    fn new() -> Option<Self> {
-        for device in pci::iter_devices() {
-            if device.is_ahci() {
-                let addr = device.base_address(bar::Index::new(5));
-                return Some(Self(addr));
-            }
-        }
-
-        None
-    }
-}
-impl From<AchiBaseAddr> for PhysAddr {
-    fn from(abar: AchiBaseAddr) -> Self {
-        abar.0
-    }
-}
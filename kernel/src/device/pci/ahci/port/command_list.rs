@@ -1,18 +1,24 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use {
-    super::Registers, crate::mem::allocator::page_box::PageBox, bitfield::bitfield,
-    core::convert::TryInto, x86_64::PhysAddr,
+    super::{command_table::RegisterH2d, Registers},
+    crate::mem::dma::{self, AddrWidth},
+    bitfield::bitfield,
+    core::convert::TryInto,
+    x86_64::PhysAddr,
 };
 
-pub struct CommandList(PageBox<[CommandHeader]>);
+pub struct CommandList(dma::Coherent<[CommandHeader]>);
 impl CommandList {
-    pub fn new(registers: &Registers) -> Self {
-        Self(PageBox::new_slice(
+    const ALIGNMENT: usize = 1024;
+
+    pub fn new(registers: &Registers, width: AddrWidth) -> Self {
+        Self(dma::Coherent::new_slice(
             CommandHeader::null(),
             Self::num_of_command_slots_supported(registers)
                 .try_into()
                 .unwrap(),
+            dma::Constraints::new().align(Self::ALIGNMENT).width(width),
         ))
     }
 
@@ -20,6 +26,10 @@ impl CommandList {
         self.0.phys_addr()
     }
 
+    pub fn header_mut(&mut self, slot: usize) -> &mut CommandHeader {
+        &mut self.0[slot]
+    }
+
     fn num_of_command_slots_supported(registers: &Registers) -> u32 {
         registers.generic.cap.read().num_of_command_slots()
     }
@@ -28,6 +38,21 @@ impl CommandList {
 #[derive(Copy, Clone)]
 pub struct CommandHeader(CommandHeaderStructure<[u32; 8]>);
 impl CommandHeader {
+    // The table has a Register H2D FIS and `prdt_length` PRD entries.
+    pub fn set(&mut self, table: PhysAddr, write: bool, prdt_length: usize) {
+        let table = table.as_u64();
+        assert!(table.trailing_zeros() >= 7);
+
+        self.0 = CommandHeaderStructure::null();
+        self.0
+            .set_command_fis_length((RegisterH2d::BYTES / 4).try_into().unwrap());
+        self.0.set_write(write);
+        self.0.set_prdt_length(prdt_length.try_into().unwrap());
+        self.0
+            .set_ctba_lower((table & 0xffff_ffff).try_into().unwrap());
+        self.0.set_ctba_upper((table >> 32).try_into().unwrap());
+    }
+
     fn null() -> Self {
         Self(CommandHeaderStructure::null())
     }
@@ -37,6 +62,11 @@ bitfield! {
     #[derive(Copy,Clone)]
     pub struct CommandHeaderStructure([u32]);
     impl Debug;
+    u32, _, set_command_fis_length: 4, 0;
+    _, set_write: 6;
+    u32, _, set_prdt_length: 31, 16;
+    u32, _, set_ctba_lower: 95, 64;
+    u32, _, set_ctba_upper: 127, 96;
 }
 impl CommandHeaderStructure<[u32; 8]> {
     fn null() -> Self {
@@ -0,0 +1,106 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use {
+    crate::mem::dma::{self, sg, AddrWidth},
+    core::convert::TryFrom,
+    x86_64::PhysAddr,
+};
+
+// Only one command is issued at a time, so a port has a single table. Each segment of the buffer
+// takes a PRD entry.
+pub struct CommandTable(dma::Coherent<Structure>);
+impl CommandTable {
+    const ALIGNMENT: usize = 128;
+
+    pub fn new(width: AddrWidth) -> Self {
+        Self(dma::Coherent::new(
+            Structure::null(),
+            dma::Constraints::new().align(Self::ALIGNMENT).width(width),
+        ))
+    }
+
+    pub fn phys_addr(&self) -> PhysAddr {
+        self.0.phys_addr()
+    }
+
+    pub fn set_fis(&mut self, fis: &RegisterH2d) {
+        self.0.command_fis = [0; 64];
+        self.0.command_fis[..RegisterH2d::BYTES].copy_from_slice(&fis.0);
+    }
+
+    // Returns the number of the PRD entries.
+    pub fn set_buffer(&mut self, buf: &sg::List<'_>) -> usize {
+        for (entry, segment) in self.0.prdt.iter_mut().zip(buf.segments()) {
+            *entry = PrdEntry {
+                data_base: segment.addr.as_u64(),
+                reserved: 0,
+                byte_count: u32::try_from(segment.bytes.as_usize() - 1).unwrap(),
+            };
+        }
+
+        buf.segments().len()
+    }
+}
+
+#[repr(C, align(128))]
+struct Structure {
+    command_fis: [u8; 64],
+    atapi_command: [u8; 16],
+    reserved: [u8; 48],
+    prdt: [PrdEntry; sg::MAX_SEGMENTS],
+}
+impl Structure {
+    fn null() -> Self {
+        Self {
+            command_fis: [0; 64],
+            atapi_command: [0; 16],
+            reserved: [0; 48],
+            prdt: [PrdEntry::null(); sg::MAX_SEGMENTS],
+        }
+    }
+}
+
+// The buffer must be word aligned, and the byte count is the length minus one.
+#[repr(C)]
+#[derive(Copy, Clone)]
+struct PrdEntry {
+    data_base: u64,
+    reserved: u32,
+    byte_count: u32,
+}
+impl PrdEntry {
+    fn null() -> Self {
+        Self {
+            data_base: 0,
+            reserved: 0,
+            byte_count: 0,
+        }
+    }
+}
+
+pub struct RegisterH2d([u8; RegisterH2d::BYTES]);
+impl RegisterH2d {
+    pub const BYTES: usize = 20;
+    const TYPE: u8 = 0x27;
+    const COMMAND_BIT: u8 = 0x80;
+    const LBA_MODE: u8 = 1 << 6;
+
+    pub const READ_DMA_EXT: u8 = 0x25;
+    pub const WRITE_DMA_EXT: u8 = 0x35;
+
+    pub fn new(command: u8, lba: u64, num_of_sectors: u16) -> Self {
+        let lba = lba.to_le_bytes();
+        let count = num_of_sectors.to_le_bytes();
+
+        let mut fis = [0; Self::BYTES];
+        fis[0] = Self::TYPE;
+        fis[1] = Self::COMMAND_BIT;
+        fis[2] = command;
+        fis[4..7].copy_from_slice(&lba[0..3]);
+        fis[7] = Self::LBA_MODE;
+        fis[8..11].copy_from_slice(&lba[3..6]);
+        fis[12..14].copy_from_slice(&count);
+
+        Self(fis)
+    }
+}
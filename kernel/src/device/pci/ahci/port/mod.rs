@@ -1,13 +1,19 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 mod command_list;
+mod command_table;
 mod received_fis;
 
 use {
     super::registers::{port, Registers},
-    alloc::{rc::Rc, vec::Vec},
+    crate::{
+        device::block::{self, BYTES_PER_SECTOR},
+        mem::dma::{self, sg, AddrWidth},
+    },
+    alloc::vec::Vec,
     command_list::CommandList,
-    core::{cell::RefCell, convert::TryInto},
+    command_table::{CommandTable, RegisterH2d},
+    core::convert::TryFrom,
     received_fis::ReceivedFis,
 };
 
@@ -15,10 +21,10 @@ pub struct Collection(Vec<Port>);
 impl Collection {
     const MAX_PORTS: usize = 32;
 
-    pub fn new(registers: &Rc<RefCell<Registers>>) -> Self {
+    pub fn new(registers: &mut Registers) -> Self {
         Self(
             (0..Self::MAX_PORTS)
-                .filter_map(|i| Port::new(registers.clone(), i))
+                .filter_map(|i| Port::new(registers, i))
                 .collect(),
         )
     }
@@ -41,18 +47,48 @@ impl Collection {
         }
     }
 
+    pub fn into_vec(self) -> Vec<Port> {
+        self.0
+    }
+
     fn iter_mut(&mut self) -> impl Iterator<Item = &mut Port> {
         self.0.iter_mut()
     }
 }
 
+// The port owns its registers, so it can be moved to another thread without the others.
 pub struct Port {
-    registers: Rc<RefCell<Registers>>,
+    registers: port::Registers,
     command_list: CommandList,
+    command_table: CommandTable,
     received_fis: ReceivedFis,
-    index: usize,
+    width: AddrWidth,
 }
 impl Port {
+    // Returns false if no device is attached to the port.
+    pub fn start(&mut self) -> bool {
+        // Refer to P.34 and P.113 of Serial ATA AHCI 1.3.1 Specification
+        const DEVICE_PRESENT_AND_PHY_ESTABLISHED: u32 = 3;
+
+        if self.parse_port_rg(|rg| rg.ssts.read().device_detection())
+            != DEVICE_PRESENT_AND_PHY_ESTABLISHED
+        {
+            return false;
+        }
+
+        while {
+            self.parse_port_rg(|rg| {
+                let tfd = rg.tfd.read();
+                tfd.busy() || tfd.data_transfer_requested()
+            })
+        } {}
+
+        self.edit_port_rg(|rg| rg.cmd.update(|cmd| cmd.set_fis_receive_enable(true)));
+        self.edit_port_rg(|rg| rg.cmd.update(|cmd| cmd.set_start_bit(true)));
+
+        true
+    }
+
     pub fn idle(&mut self) {
         self.edit_port_rg(|rg| {
             rg.cmd.update(|cmd| {
@@ -69,42 +105,41 @@ impl Port {
         } {}
     }
 
-    fn new(registers: Rc<RefCell<Registers>>, index: usize) -> Option<Self> {
-        if Self::exists(&registers, index) {
-            Some(Self::generate(registers, index))
-        } else {
-            None
-        }
-    }
-
-    fn exists(registers: &Rc<RefCell<Registers>>, index: usize) -> bool {
-        let registers = &registers.borrow();
-        let pi: usize = registers.generic.pi.read().0.try_into().unwrap();
-        pi & (1 << index) != 0
+    // Takes the registers of the port if it is implemented.
+    fn new(registers: &mut Registers, index: usize) -> Option<Self> {
+        let port_rg = registers.port_regs[index].take()?;
+        Some(Self::generate(registers, port_rg))
     }
 
-    fn generate(registers: Rc<RefCell<Registers>>, index: usize) -> Self {
-        let command_list = CommandList::new(&*registers.borrow());
-        let received_fis = ReceivedFis::new();
+    fn generate(registers: &Registers, port_rg: port::Registers) -> Self {
+        let width = Self::addr_width(registers);
+        let command_list = CommandList::new(registers, width);
+        let command_table = CommandTable::new(width);
+        let received_fis = ReceivedFis::new(width);
         Self {
-            registers,
+            registers: port_rg,
             received_fis,
             command_list,
-            index,
+            command_table,
+            width,
+        }
+    }
+
+    // HBAs which do not support 64-bit addressing ignore the upper 32 bits of addresses.
+    fn addr_width(registers: &Registers) -> AddrWidth {
+        let cap = registers.generic.cap.read();
+        if cap.supports_64bit_addressing() {
+            AddrWidth::Bits64
+        } else {
+            AddrWidth::Bits32
         }
     }
 
     fn register_command_list_and_received_fis(&mut self) {
-        self.assert_64bit_accessing_is_supported();
         self.register_command_list();
         self.register_received_fis();
     }
 
-    fn assert_64bit_accessing_is_supported(&self) {
-        let registers = &self.registers.borrow();
-        assert!(registers.generic.cap.read().supports_64bit_addressing());
-    }
-
     fn register_command_list(&mut self) {
         let addr = self.command_list.phys_addr();
         self.edit_port_rg(|rg| rg.clb.update(|b| b.set(addr)));
@@ -121,21 +156,100 @@ impl Port {
         self.edit_port_rg(|rg| rg.serr.update(|serr| serr.0 = BIT_MASK));
     }
 
+    // PRD entries must start at word boundaries, and each covers up to 4 MiB.
+    fn buffer_constraints(&self) -> dma::Constraints {
+        dma::Constraints::new()
+            .align(2)
+            .boundary(0x40_0000)
+            .width(self.width)
+    }
+
+    // Only slot 0 is used, and the completion is polled. The page fault handler reads swapped-out
+    // pages through this, so it must not wait for an interrupt.
+    fn issue(
+        &mut self,
+        fis: &RegisterH2d,
+        buf: &sg::List<'_>,
+        write: bool,
+    ) -> Result<(), block::Error> {
+        self.command_table.set_fis(fis);
+        let prdt_length = self.command_table.set_buffer(buf);
+
+        let table = self.command_table.phys_addr();
+        self.command_list
+            .header_mut(0)
+            .set(table, write, prdt_length);
+
+        self.clear_interrupt_status();
+        self.edit_port_rg(|rg| rg.ci.write(port::PortxCommandIssue(1)));
+
+        loop {
+            let (issued, error) = self.parse_port_rg(|rg| {
+                (
+                    rg.ci.read().0 & 1 != 0,
+                    rg.is.read().task_file_error_status(),
+                )
+            });
+
+            if error {
+                self.clear_interrupt_status();
+                return Err(block::Error::DeviceError);
+            }
+            if !issued {
+                break;
+            }
+        }
+
+        if self.parse_port_rg(|rg| rg.tfd.read().error()) {
+            Err(block::Error::DeviceError)
+        } else {
+            Ok(())
+        }
+    }
+
+    // Bits of PxIS are cleared by writing 1 to them.
+    fn clear_interrupt_status(&mut self) {
+        self.edit_port_rg(|rg| {
+            let is = rg.is.read();
+            rg.is.write(is);
+        });
+    }
+
+    fn num_of_sectors(buf: &[u8]) -> u16 {
+        assert_eq!(buf.len() % BYTES_PER_SECTOR, 0);
+        u16::try_from(buf.len() / BYTES_PER_SECTOR).unwrap()
+    }
+
     fn parse_port_rg<T, U>(&self, f: T) -> U
     where
         T: Fn(&port::Registers) -> U,
     {
-        let registers = &self.registers.borrow_mut();
-        let port_rg = registers.port_regs[self.index].as_ref().unwrap();
-        f(port_rg)
+        f(&self.registers)
     }
 
     fn edit_port_rg<T>(&mut self, f: T)
     where
         T: Fn(&mut port::Registers),
     {
-        let registers = &mut self.registers.borrow_mut();
-        let port_rg = registers.port_regs[self.index].as_mut().unwrap();
-        f(port_rg);
+        f(&mut self.registers);
+    }
+}
+impl block::Device for Port {
+    fn read(&mut self, lba: u64, buf: &mut [u8]) -> Result<(), block::Error> {
+        let num_of_sectors = Self::num_of_sectors(buf);
+        let fis = RegisterH2d::new(RegisterH2d::READ_DMA_EXT, lba, num_of_sectors);
+
+        let buf =
+            sg::List::from_device(buf, self.buffer_constraints()).map_err(block::Error::Buffer)?;
+        self.issue(&fis, &buf, false)
+    }
+
+    fn write(&mut self, lba: u64, buf: &[u8]) -> Result<(), block::Error> {
+        let num_of_sectors = Self::num_of_sectors(buf);
+        let fis = RegisterH2d::new(RegisterH2d::WRITE_DMA_EXT, lba, num_of_sectors);
+
+        let buf =
+            sg::List::to_device(buf, self.buffer_constraints()).map_err(block::Error::Buffer)?;
+        self.issue(&fis, &buf, true)
     }
 }
@@ -1,12 +1,22 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use {crate::mem::allocator::page_box::PageBox, x86_64::PhysAddr};
+use {
+    crate::mem::dma::{self, AddrWidth},
+    x86_64::PhysAddr,
+};
 
 // This is a temporary implementation.
-pub struct ReceivedFis(PageBox<u32>);
+pub struct ReceivedFis(dma::Coherent<[u8]>);
 impl ReceivedFis {
-    pub fn new() -> Self {
-        Self(PageBox::new(0))
+    const BYTES: usize = 256;
+    const ALIGNMENT: usize = 256;
+
+    pub fn new(width: AddrWidth) -> Self {
+        Self(dma::Coherent::new_slice(
+            0,
+            Self::BYTES,
+            dma::Constraints::new().align(Self::ALIGNMENT).width(width),
+        ))
     }
 
     pub fn phys_addr(&self) -> PhysAddr {
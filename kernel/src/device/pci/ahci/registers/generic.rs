@@ -1,21 +1,24 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use {
-    super::super::AchiBaseAddr, crate::mem::accessor::Accessor, bitfield::bitfield, os_units::Bytes,
+    crate::mem::mmio::{ReadOnly, ReadWrite, Region, Register},
+    alloc::sync::Arc,
+    bitfield::bitfield,
+    os_units::Bytes,
 };
 
 pub struct Generic {
-    pub cap: Accessor<HbaCapability>,
-    pub ghc: Accessor<GlobalHbaControl>,
-    pub pi: Accessor<PortsImplemented>,
-    pub bohc: Accessor<BiosOsHandoffControlAndStatus>,
+    pub cap: Register<HbaCapability, ReadOnly>,
+    pub ghc: Register<GlobalHbaControl, ReadWrite>,
+    pub pi: Register<PortsImplemented, ReadOnly>,
+    pub bohc: Register<BiosOsHandoffControlAndStatus, ReadWrite>,
 }
 impl Generic {
-    pub fn new(abar: AchiBaseAddr) -> Self {
-        let cap = Accessor::new(abar.into(), Bytes::new(0x00));
-        let ghc = Accessor::new(abar.into(), Bytes::new(0x04));
-        let pi = Accessor::new(abar.into(), Bytes::new(0x0c));
-        let bohc = Accessor::new(abar.into(), Bytes::new(0x28));
+    pub fn new(abar: &Arc<Region>) -> Self {
+        let cap = abar.register(Bytes::new(0x00));
+        let ghc = abar.register(Bytes::new(0x04));
+        let pi = abar.register(Bytes::new(0x0c));
+        let bohc = abar.register(Bytes::new(0x28));
 
         Self { cap, ghc, pi, bohc }
     }
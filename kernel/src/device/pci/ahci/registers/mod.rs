@@ -3,21 +3,25 @@
 pub mod generic;
 pub mod port;
 
-use {super::AchiBaseAddr, alloc::vec::Vec, generic::Generic};
+use {
+    crate::mem::mmio::Region,
+    alloc::{sync::Arc, vec::Vec},
+    generic::Generic,
+};
 
 pub struct Registers {
     pub generic: Generic,
     pub port_regs: Vec<Option<port::Registers>>,
 }
 impl Registers {
-    pub fn new(abar: AchiBaseAddr) -> Self {
+    pub fn new(abar: &Arc<Region>) -> Self {
         let generic = Generic::new(abar);
         let port_regs = Self::collect_port_regs(abar, &generic);
 
         Self { generic, port_regs }
     }
 
-    fn collect_port_regs(abar: AchiBaseAddr, generic: &Generic) -> Vec<Option<port::Registers>> {
+    fn collect_port_regs(abar: &Arc<Region>, generic: &Generic) -> Vec<Option<port::Registers>> {
         (0..32)
             .map(|i| port::Registers::new(abar, i, generic))
             .collect()
@@ -1,21 +1,26 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use {
-    super::{generic::Generic, AchiBaseAddr},
-    crate::mem::accessor::Accessor,
+    super::generic::Generic,
+    crate::mem::mmio::{ReadOnly, ReadWrite, Region, Register},
+    alloc::sync::Arc,
     bitfield::bitfield,
     os_units::Bytes,
     x86_64::PhysAddr,
 };
 
 pub struct Registers {
-    pub clb: Accessor<PortxCommandListBaseAddress>,
-    pub fb: Accessor<PortxFisBaseAddress>,
-    pub cmd: Accessor<PortxCommandAndStatus>,
-    pub serr: Accessor<PortxSerialAtaError>,
+    pub clb: Register<PortxCommandListBaseAddress, ReadWrite>,
+    pub fb: Register<PortxFisBaseAddress, ReadWrite>,
+    pub is: Register<PortxInterruptStatus, ReadWrite>,
+    pub cmd: Register<PortxCommandAndStatus, ReadWrite>,
+    pub tfd: Register<PortxTaskFileData, ReadOnly>,
+    pub ssts: Register<PortxSerialAtaStatus, ReadOnly>,
+    pub serr: Register<PortxSerialAtaError, ReadWrite>,
+    pub ci: Register<PortxCommandIssue, ReadWrite>,
 }
 impl Registers {
-    pub fn new(abar: AchiBaseAddr, port_index: usize, generic: &Generic) -> Option<Self> {
+    pub fn new(abar: &Arc<Region>, port_index: usize, generic: &Generic) -> Option<Self> {
         if Self::exist(port_index, generic) {
             Some(Self::fetch(abar, port_index))
         } else {
@@ -27,24 +32,32 @@ impl Registers {
         generic.pi.read().0 & (1 << port_index) != 0
     }
 
-    fn fetch(abar: AchiBaseAddr, port_index: usize) -> Self {
-        let base_addr = Self::base_addr_to_registers(abar, port_index);
+    fn fetch(abar: &Arc<Region>, port_index: usize) -> Self {
+        let offset = |offset: usize| Self::offset_to_registers(port_index) + offset;
 
-        let px_clb = Accessor::new(base_addr, Bytes::new(0x00));
-        let px_fb = Accessor::new(base_addr, Bytes::new(0x08));
-        let px_cmd = Accessor::new(base_addr, Bytes::new(0x18));
-        let px_serr = Accessor::new(base_addr, Bytes::new(0x30));
+        let px_clb = abar.register(Bytes::new(offset(0x00)));
+        let px_fb = abar.register(Bytes::new(offset(0x08)));
+        let px_is = abar.register(Bytes::new(offset(0x10)));
+        let px_cmd = abar.register(Bytes::new(offset(0x18)));
+        let px_tfd = abar.register(Bytes::new(offset(0x20)));
+        let px_ssts = abar.register(Bytes::new(offset(0x28)));
+        let px_serr = abar.register(Bytes::new(offset(0x30)));
+        let px_ci = abar.register(Bytes::new(offset(0x38)));
 
         Self {
             clb: px_clb,
             fb: px_fb,
+            is: px_is,
             cmd: px_cmd,
+            tfd: px_tfd,
+            ssts: px_ssts,
             serr: px_serr,
+            ci: px_ci,
         }
     }
 
-    fn base_addr_to_registers(abar: AchiBaseAddr, port_index: usize) -> PhysAddr {
-        PhysAddr::from(abar) + 0x100_usize + port_index * 0x80
+    fn offset_to_registers(port_index: usize) -> usize {
+        0x100 + port_index * 0x80
     }
 }
 
@@ -58,9 +71,35 @@ bitfield! {
     pub command_list_running, _: 15;
 }
 
+bitfield! {
+    #[repr(transparent)]
+    pub struct PortxInterruptStatus(u32);
+    impl Debug;
+    pub task_file_error_status, _: 30;
+}
+
+bitfield! {
+    #[repr(transparent)]
+    pub struct PortxTaskFileData(u32);
+    impl Debug;
+    pub error, _: 0;
+    pub data_transfer_requested, _: 3;
+    pub busy, _: 7;
+}
+
+bitfield! {
+    #[repr(transparent)]
+    pub struct PortxSerialAtaStatus(u32);
+    impl Debug;
+    pub device_detection, _: 3, 0;
+}
+
 #[repr(transparent)]
 pub struct PortxSerialAtaError(pub u32);
 
+#[repr(transparent)]
+pub struct PortxCommandIssue(pub u32);
+
 #[repr(transparent)]
 pub struct PortxCommandListBaseAddress(u64);
 impl PortxCommandListBaseAddress {
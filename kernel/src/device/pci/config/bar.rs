@@ -27,6 +27,10 @@ impl Bar {
         }
     }
 
+    pub fn is_64bit(self) -> bool {
+        self.ty() == BarType::Bar64Bit
+    }
+
     fn ty(self) -> BarType {
         let ty_raw = (self.0 >> 1) & 0b11;
         if ty_raw == 0 {
@@ -6,6 +6,8 @@ pub mod type_spec;
 
 use {
     self::common::Common,
+    crate::mem::mmio,
+    alloc::sync::Arc,
     bar::Bar,
     core::{convert::TryFrom, ops::Add},
     type_spec::TypeSpec,
@@ -39,6 +41,11 @@ impl Space {
         self.type_spec().base_address(index)
     }
 
+    pub fn map_bar(&self, index: bar::Index) -> Arc<mmio::Region> {
+        let type_spec = self.type_spec();
+        mmio::Region::new(type_spec.base_address(index), type_spec.bar_size(index))
+    }
+
     fn type_spec(&self) -> TypeSpec {
         TypeSpec::new(&self.registers, &self.common())
     }
@@ -73,6 +80,11 @@ impl Registers {
         let accessor = ConfigAddress::new(self.bus, self.device, Function::zero(), index);
         unsafe { accessor.read() }
     }
+
+    fn set(&self, index: RegisterIndex, value: u32) {
+        let accessor = ConfigAddress::new(self.bus, self.device, Function::zero(), index);
+        unsafe { accessor.write(value) }
+    }
 }
 
 struct ConfigAddress {
@@ -114,6 +126,15 @@ impl ConfigAddress {
         let mut data = Self::PORT_CONFIG_DATA;
         data.read()
     }
+
+    /// Safety: `self` must contain the valid config address.
+    unsafe fn write(&self, value: u32) {
+        let mut addr = Self::PORT_CONFIG_ADDR;
+        addr.write(self.as_u32());
+
+        let mut data = Self::PORT_CONFIG_DATA;
+        data.write(value);
+    }
 }
 
 #[derive(Copy, Clone, Debug)]
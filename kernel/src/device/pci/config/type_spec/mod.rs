@@ -8,6 +8,7 @@ use {
         common::{BridgeType, Common},
         Bar, RegisterIndex, Registers,
     },
+    os_units::Bytes,
     x86_64::PhysAddr,
 };
 
@@ -28,4 +29,9 @@ impl<'a> TypeSpec<'a> {
         let TypeSpec::NonBridge(non_bridge) = self;
         non_bridge.base_addr(index)
     }
+
+    pub fn bar_size(&self, index: bar::Index) -> Bytes {
+        let TypeSpec::NonBridge(non_bridge) = self;
+        non_bridge.bar_size(index)
+    }
 }
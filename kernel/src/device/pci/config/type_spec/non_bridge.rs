@@ -2,6 +2,8 @@
 
 use {
     super::{bar, Bar, RegisterIndex, Registers},
+    core::convert::TryFrom,
+    os_units::Bytes,
     x86_64::PhysAddr,
 };
 
@@ -31,6 +33,44 @@ impl<'a> TypeSpec<'a> {
             .expect("Could not calculate Base Address.")
     }
 
+    // Write all 1s to the BAR and read back which address bits are hardwired to zero. Memory
+    // decoding is disabled meanwhile so that the device does not respond to the bogus address.
+    pub fn bar_size(&self, index: bar::Index) -> Bytes {
+        const MEMORY_SPACE_ENABLE: u32 = 1 << 1;
+        const COMMAND_MASK: u32 = 0xffff;
+
+        let command_index = RegisterIndex::new(1);
+        let command = self.registers.get(command_index) & COMMAND_MASK;
+        self.registers
+            .set(command_index, command & !MEMORY_SPACE_ENABLE);
+
+        let lower = self.probe(RegisterIndex::from(index)) & !0xf;
+        let upper = if self.bar(index).is_64bit() {
+            Some(self.probe(RegisterIndex::from(index + 1)))
+        } else {
+            None
+        };
+
+        self.registers.set(command_index, command);
+
+        // An unimplemented BAR reads back as zero.
+        if lower == 0 && upper.unwrap_or(0) == 0 {
+            return Bytes::new(0);
+        }
+
+        let mask = u64::from(upper.unwrap_or(!0)) << 32 | u64::from(lower);
+        Bytes::new(usize::try_from(!mask + 1).unwrap())
+    }
+
+    fn probe(&self, index: RegisterIndex) -> u32 {
+        let original = self.registers.get(index);
+        self.registers.set(index, !0);
+        let mask = self.registers.get(index);
+        self.registers.set(index, original);
+
+        mask
+    }
+
     fn bar(&self, index: bar::Index) -> Bar {
         Bar::new(self.registers.get(RegisterIndex::from(index)))
     }
@@ -1,10 +1,16 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use {
+    crate::mem::dma,
     bitfield::bitfield,
     core::ops::{Deref, DerefMut},
 };
 
+// Contexts are aligned to 64 bytes and must not cross a page boundary.
+pub fn constraints() -> dma::Constraints {
+    dma::Constraints::new().align(64).boundary(4096)
+}
+
 pub struct Input {
     input_control_context: InputControl,
     endpoint_context: [Endpoint; 32],
@@ -1,17 +1,23 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use {
-    super::Registers, crate::mem::allocator::page_box::PageBox, alloc::rc::Rc, core::cell::RefCell,
-    x86_64::PhysAddr,
-};
+use {super::Registers, crate::mem::dma, alloc::rc::Rc, core::cell::RefCell, x86_64::PhysAddr};
 
 pub struct DeviceContextBaseAddressArray {
-    arr: PageBox<[usize]>,
+    arr: dma::Coherent<[usize]>,
     registers: Rc<RefCell<Registers>>,
 }
 impl<'a> DeviceContextBaseAddressArray {
+    const ALIGNMENT: usize = 64;
+    const BOUNDARY: usize = 4096;
+
     pub fn new(registers: Rc<RefCell<Registers>>) -> Self {
-        let arr = PageBox::new_slice(0, Self::num_of_slots(&registers));
+        let arr = dma::Coherent::new_slice(
+            0,
+            Self::num_of_slots(&registers),
+            dma::Constraints::new()
+                .align(Self::ALIGNMENT)
+                .boundary(Self::BOUNDARY),
+        );
         Self { arr, registers }
     }
 
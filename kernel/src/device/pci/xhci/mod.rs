@@ -10,7 +10,7 @@ mod xhc;
 
 use {
     super::config::bar,
-    crate::multitask::task::{self, Task},
+    crate::multitask::group::TaskGroup,
     alloc::rc::Rc,
     command_runner::{CommandCompletionReceiver, Runner},
     core::cell::RefCell,
@@ -21,24 +21,26 @@ use {
     xhc::Xhc,
 };
 
-pub async fn task(task_collection: Rc<RefCell<task::Collection>>) {
+// Tasks of ports and the event ring are in a group. They are cancelled if this task is.
+pub async fn task() {
     let registers = Rc::new(RefCell::new(iter_devices().next().unwrap()));
     let (_xhc, event_ring, _dcbaa, port_task_spawner, command_completion_receiver) =
-        init(&registers, task_collection.clone());
+        init(&registers);
 
-    port_task_spawner.spawn_tasks();
+    let mut group = TaskGroup::new();
 
-    task_collection
-        .borrow_mut()
-        .add_task_as_woken(Task::new(event::task(
-            event_ring,
-            command_completion_receiver,
-        )));
+    port_task_spawner.spawn_tasks(&mut group);
+
+    group.spawn_named(
+        "xhci event ring",
+        event::task(event_ring, command_completion_receiver),
+    );
+
+    group.join_all().await;
 }
 
 fn init(
     registers: &Rc<RefCell<Registers>>,
-    task_collection: Rc<RefCell<task::Collection>>,
 ) -> (
     Xhc,
     event::Ring,
@@ -47,7 +49,7 @@ fn init(
     Rc<RefCell<CommandCompletionReceiver>>,
 ) {
     let mut xhc = Xhc::new(registers.clone());
-    let mut event_ring = event::Ring::new(registers.clone(), task_collection.clone());
+    let mut event_ring = event::Ring::new(registers.clone());
     let command_ring = Rc::new(RefCell::new(command::Ring::new(registers.clone())));
     let dcbaa = DeviceContextBaseAddressArray::new(registers.clone());
     let command_completion_receiver = Rc::new(RefCell::new(CommandCompletionReceiver::new()));
@@ -55,7 +57,7 @@ fn init(
         Runner::new(command_ring.clone(), command_completion_receiver.clone()),
         false,
     ));
-    let ports = port::TaskSpawner::new(command_runner, registers.clone(), task_collection);
+    let ports = port::TaskSpawner::new(command_runner, registers.clone());
 
     xhc.init();
 
@@ -71,7 +73,7 @@ fn init(
 pub fn iter_devices() -> impl Iterator<Item = Registers> {
     super::iter_devices().filter_map(|device| {
         if device.is_xhci() {
-            Some(Registers::new(device.base_address(bar::Index::new(0))))
+            Some(Registers::new(&device.map_bar(bar::Index::new(0))))
         } else {
             None
         }
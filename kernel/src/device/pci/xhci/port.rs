@@ -7,10 +7,7 @@ use {
         register::{hc_operational::PortRegisters, Registers},
         ring::transfer,
     },
-    crate::{
-        mem::allocator::page_box::PageBox,
-        multitask::task::{self, Task},
-    },
+    crate::{mem::dma, multitask::group::TaskGroup},
     alloc::rc::Rc,
     core::cell::RefCell,
     futures_intrusive::sync::LocalMutex,
@@ -34,28 +31,20 @@ async fn task(mut port: Port, command_runner: Rc<LocalMutex<Runner>>) {
 pub struct TaskSpawner {
     command_runner: Rc<LocalMutex<Runner>>,
     registers: Rc<RefCell<Registers>>,
-    task_collection: Rc<RefCell<task::Collection>>,
 }
 impl<'a> TaskSpawner {
-    pub fn new(
-        command_runner: Rc<LocalMutex<Runner>>,
-        registers: Rc<RefCell<Registers>>,
-        task_collection: Rc<RefCell<task::Collection>>,
-    ) -> Self {
+    pub fn new(command_runner: Rc<LocalMutex<Runner>>, registers: Rc<RefCell<Registers>>) -> Self {
         Self {
             command_runner,
             registers,
-            task_collection,
         }
     }
 
-    pub fn spawn_tasks(&self) {
+    pub fn spawn_tasks(&self, group: &mut TaskGroup) {
         for i in 0..self.num_of_ports() {
             let port = Port::new(self.registers.clone(), i);
             if port.connected() {
-                self.task_collection
-                    .borrow_mut()
-                    .add_task_as_woken(Task::new(task(port, self.command_runner.clone())));
+                group.spawn_named("xhci port", task(port, self.command_runner.clone()));
             }
         }
     }
@@ -69,8 +58,8 @@ impl<'a> TaskSpawner {
 pub struct Port {
     registers: Rc<RefCell<Registers>>,
     index: usize,
-    input_context: PageBox<context::Input>,
-    input_slot_context: PageBox<context::Slot>,
+    input_context: dma::Coherent<context::Input>,
+    input_slot_context: dma::Coherent<context::Slot>,
     transfer_ring: transfer::Ring,
 }
 impl Port {
@@ -84,8 +73,8 @@ impl Port {
         Self {
             registers: registers.clone(),
             index,
-            input_context: PageBox::new(context::Input::null()),
-            input_slot_context: PageBox::new(context::Slot::null()),
+            input_context: dma::Coherent::new(context::Input::null(), context::constraints()),
+            input_slot_context: dma::Coherent::new(context::Slot::null(), context::constraints()),
             transfer_ring: transfer::Ring::new(registers),
         }
     }
@@ -1,23 +1,22 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use {crate::mem::accessor::Accessor, core::convert::TryInto, os_units::Bytes, x86_64::PhysAddr};
+use {
+    crate::mem::mmio::{self, Region, WriteOnly},
+    alloc::sync::Arc,
+    core::convert::TryInto,
+    os_units::Bytes,
+};
 
 const NUM_OF_REGISTERS: usize = 256;
 
-pub struct Array(Accessor<[u32]>);
+// Reading doorbells always returns 0. Only writing is meaningful.
+pub struct Array(mmio::Array<u32, WriteOnly>);
 impl Array {
-    pub fn new(mmio_base: PhysAddr, db_off: u32) -> Self {
-        Self(Accessor::new_slice(
-            mmio_base,
-            Bytes::new(db_off.try_into().unwrap()),
-            NUM_OF_REGISTERS,
-        ))
+    pub fn new(region: &Arc<Region>, db_off: u32) -> Self {
+        Self(region.array(Bytes::new(db_off.try_into().unwrap()), NUM_OF_REGISTERS))
     }
 
-    pub fn update<T>(&mut self, index: usize, f: T)
-    where
-        T: Fn(&mut u32),
-    {
-        self.0.update(index, f)
+    pub fn ring(&mut self, index: usize, value: u32) {
+        self.0.write(index, value)
     }
 }
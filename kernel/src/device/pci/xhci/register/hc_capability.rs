@@ -1,24 +1,29 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use {crate::mem::accessor::Accessor, bitfield::bitfield, os_units::Bytes, x86_64::PhysAddr};
+use {
+    crate::mem::mmio::{ReadOnly, Region, Register},
+    alloc::sync::Arc,
+    bitfield::bitfield,
+    os_units::Bytes,
+};
 
 pub struct HCCapabilityRegisters {
-    pub cap_length: Accessor<CapabilityRegistersLength>,
-    pub hcs_params_1: Accessor<StructuralParameters1>,
-    pub hcs_params_2: Accessor<StructuralParameters2>,
-    pub hc_cp_params_1: Accessor<HCCapabilityParameters1>,
-    pub db_off: Accessor<DoorbellOffset>,
-    pub rts_off: Accessor<RuntimeRegisterSpaceOffset>,
+    pub cap_length: Register<CapabilityRegistersLength, ReadOnly>,
+    pub hcs_params_1: Register<StructuralParameters1, ReadOnly>,
+    pub hcs_params_2: Register<StructuralParameters2, ReadOnly>,
+    pub hc_cp_params_1: Register<HCCapabilityParameters1, ReadOnly>,
+    pub db_off: Register<DoorbellOffset, ReadOnly>,
+    pub rts_off: Register<RuntimeRegisterSpaceOffset, ReadOnly>,
 }
 
 impl HCCapabilityRegisters {
-    pub fn new(mmio_base: PhysAddr) -> Self {
-        let cap_length = Accessor::new(mmio_base, Bytes::new(0));
-        let hcs_params_1 = Accessor::new(mmio_base, Bytes::new(0x04));
-        let hcs_params_2 = Accessor::new(mmio_base, Bytes::new(0x08));
-        let hc_cp_params_1 = Accessor::new(mmio_base, Bytes::new(0x10));
-        let db_off = Accessor::new(mmio_base, Bytes::new(0x14));
-        let rts_off = Accessor::new(mmio_base, Bytes::new(0x18));
+    pub fn new(region: &Arc<Region>) -> Self {
+        let cap_length = region.register(Bytes::new(0));
+        let hcs_params_1 = region.register(Bytes::new(0x04));
+        let hcs_params_2 = region.register(Bytes::new(0x08));
+        let hc_cp_params_1 = region.register(Bytes::new(0x10));
+        let db_off = region.register(Bytes::new(0x14));
+        let rts_off = region.register(Bytes::new(0x18));
 
         Self {
             cap_length,
@@ -1,31 +1,35 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use {
-    super::hc_capability::HCCapabilityRegisters, crate::mem::accessor::Accessor,
-    bitfield::bitfield, os_units::Bytes, x86_64::PhysAddr,
+    super::hc_capability::HCCapabilityRegisters,
+    crate::mem::mmio::{Array, ReadWrite, Region, Register},
+    alloc::sync::Arc,
+    bitfield::bitfield,
+    os_units::Bytes,
+    x86_64::PhysAddr,
 };
 
 pub struct HCOperational {
-    pub usb_cmd: Accessor<UsbCommandRegister>,
-    pub usb_sts: Accessor<UsbStatusRegister>,
-    pub crcr: Accessor<CommandRingControlRegister>,
-    pub dcbaap: Accessor<DeviceContextBaseAddressArrayPointer>,
-    pub config: Accessor<ConfigureRegister>,
-    pub port_registers: Accessor<[PortRegisters]>,
+    pub usb_cmd: Register<UsbCommandRegister, ReadWrite>,
+    pub usb_sts: Register<UsbStatusRegister, ReadWrite>,
+    pub crcr: Register<CommandRingControlRegister, ReadWrite>,
+    pub dcbaap: Register<DeviceContextBaseAddressArrayPointer, ReadWrite>,
+    pub config: Register<ConfigureRegister, ReadWrite>,
+    pub port_registers: Array<PortRegisters, ReadWrite>,
 }
 
 impl HCOperational {
-    pub fn new(mmio_base: PhysAddr, capabilities: &HCCapabilityRegisters) -> Self {
-        let operational_base = mmio_base + capabilities.cap_length.read().get();
-
-        let usb_cmd = Accessor::new(operational_base, Bytes::new(0x00));
-        let usb_sts = Accessor::new(operational_base, Bytes::new(0x04));
-        let crcr = Accessor::new(operational_base, Bytes::new(0x18));
-        let dcbaap = Accessor::new(operational_base, Bytes::new(0x30));
-        let config = Accessor::new(operational_base, Bytes::new(0x38));
-        let port_registers = Accessor::new_slice(
-            operational_base,
-            Bytes::new(0x400),
+    pub fn new(region: &Arc<Region>, capabilities: &HCCapabilityRegisters) -> Self {
+        let operational_base = capabilities.cap_length.read().get();
+        let offset = |offset: usize| Bytes::new(operational_base + offset);
+
+        let usb_cmd = region.register(offset(0x00));
+        let usb_sts = region.register(offset(0x04));
+        let crcr = region.register(offset(0x18));
+        let dcbaap = region.register(offset(0x30));
+        let config = region.register(offset(0x38));
+        let port_registers = region.array(
+            offset(0x400),
             capabilities.hcs_params_1.read().max_ports().into(),
         );
 
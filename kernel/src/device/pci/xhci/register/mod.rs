@@ -7,9 +7,9 @@ pub mod runtime_base_registers;
 pub mod usb_legacy_support_capability;
 
 use {
-    hc_capability::HCCapabilityRegisters, hc_operational::HCOperational,
-    runtime_base_registers::RuntimeBaseRegisters,
-    usb_legacy_support_capability::UsbLegacySupportCapability, x86_64::PhysAddr,
+    crate::mem::mmio::Region, alloc::sync::Arc, hc_capability::HCCapabilityRegisters,
+    hc_operational::HCOperational, runtime_base_registers::RuntimeBaseRegisters,
+    usb_legacy_support_capability::UsbLegacySupportCapability,
 };
 
 pub struct Registers {
@@ -20,17 +20,17 @@ pub struct Registers {
     pub doorbell_array: doorbell::Array,
 }
 impl Registers {
-    pub fn new(mmio_base: PhysAddr) -> Self {
-        let hc_capability_registers = HCCapabilityRegisters::new(mmio_base);
+    pub fn new(region: &Arc<Region>) -> Self {
+        let hc_capability_registers = HCCapabilityRegisters::new(region);
         let usb_legacy_support_capability =
-            UsbLegacySupportCapability::new(mmio_base, &hc_capability_registers);
-        let hc_operational = HCOperational::new(mmio_base, &hc_capability_registers);
+            UsbLegacySupportCapability::new(region, &hc_capability_registers);
+        let hc_operational = HCOperational::new(region, &hc_capability_registers);
         let runtime_base_registers = RuntimeBaseRegisters::new(
-            mmio_base,
+            region,
             hc_capability_registers.rts_off.read().get() as usize,
         );
         let doorbell_array =
-            doorbell::Array::new(mmio_base, hc_capability_registers.db_off.read().get());
+            doorbell::Array::new(region, hc_capability_registers.db_off.read().get());
 
         Self {
             usb_legacy_support_capability,
@@ -1,18 +1,25 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use {crate::mem::accessor::Accessor, bitfield::bitfield, os_units::Bytes, x86_64::PhysAddr};
+use {
+    crate::mem::mmio::{ReadWrite, Region, Register},
+    alloc::sync::Arc,
+    bitfield::bitfield,
+    os_units::Bytes,
+    x86_64::PhysAddr,
+};
 
 pub struct RuntimeBaseRegisters {
-    pub erst_sz: Accessor<EventRingSegmentTableSizeRegister>,
-    pub erst_ba: Accessor<EventRingSegmentTableBaseAddressRegister>,
-    pub erd_p: Accessor<EventRingDequeuePointerRegister>,
+    pub erst_sz: Register<EventRingSegmentTableSizeRegister, ReadWrite>,
+    pub erst_ba: Register<EventRingSegmentTableBaseAddressRegister, ReadWrite>,
+    pub erd_p: Register<EventRingDequeuePointerRegister, ReadWrite>,
 }
 impl<'a> RuntimeBaseRegisters {
-    pub fn new(mmio_base: PhysAddr, runtime_register_space_offset: usize) -> Self {
-        let runtime_base = mmio_base + runtime_register_space_offset;
-        let erst_sz = Accessor::new(runtime_base, Bytes::new(0x28));
-        let erst_ba = Accessor::new(runtime_base, Bytes::new(0x30));
-        let erd_p = Accessor::new(runtime_base, Bytes::new(0x38));
+    pub fn new(region: &Arc<Region>, runtime_register_space_offset: usize) -> Self {
+        let offset = |offset: usize| Bytes::new(runtime_register_space_offset + offset);
+
+        let erst_sz = region.register(offset(0x28));
+        let erst_ba = region.register(offset(0x30));
+        let erd_p = region.register(offset(0x38));
 
         Self {
             erst_sz,
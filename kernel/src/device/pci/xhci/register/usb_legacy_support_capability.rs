@@ -2,20 +2,21 @@
 
 use {
     crate::{
-        device::pci::xhci::register::hc_capability::HCCapabilityRegisters, mem::accessor::Accessor,
+        device::pci::xhci::register::hc_capability::HCCapabilityRegisters,
+        mem::mmio::{ReadWrite, Region, Register},
     },
+    alloc::sync::Arc,
     bitfield::bitfield,
     os_units::Bytes,
-    x86_64::PhysAddr,
 };
 
 pub struct UsbLegacySupportCapability {
-    pub usb_leg_sup: Accessor<UsbLegacySupportCapabilityRegister>,
+    pub usb_leg_sup: Register<UsbLegacySupportCapabilityRegister, ReadWrite>,
 }
 
 impl UsbLegacySupportCapability {
     pub fn new(
-        mmio_base: PhysAddr,
+        region: &Arc<Region>,
         hc_capability_registers: &HCCapabilityRegisters,
     ) -> Option<Self> {
         let xecp = hc_capability_registers
@@ -23,8 +24,8 @@ impl UsbLegacySupportCapability {
             .read()
             .xhci_extended_capabilities_pointer();
         info!("xECP: {}", xecp);
-        let base = mmio_base + ((xecp as usize) << 2);
-        let usb_leg_sup = Accessor::<UsbLegacySupportCapabilityRegister>::new(base, Bytes::new(0));
+        let usb_leg_sup: Register<UsbLegacySupportCapabilityRegister, _> =
+            region.register(Bytes::new((xecp as usize) << 2));
 
         if usb_leg_sup.read().id() == 1 {
             Some(Self { usb_leg_sup })
@@ -40,7 +40,7 @@ impl<'a> Ring {
 
     fn notify_command_is_sent(&mut self) {
         let doorbell_array = &mut self.registers.borrow_mut().doorbell_array;
-        doorbell_array.update(0, |reg| *reg = 0)
+        doorbell_array.ring(0, 0)
     }
 
     pub fn init(&mut self) {
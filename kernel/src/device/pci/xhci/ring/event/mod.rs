@@ -7,7 +7,7 @@ use {
         trb::Trb,
         CycleBit,
     },
-    crate::multitask::task::{self, Task},
+    crate::multitask::spawner::Spawner,
     alloc::{rc::Rc, vec::Vec},
     core::{
         cell::RefCell,
@@ -47,16 +47,12 @@ pub struct Ring {
     current_cycle_bit: CycleBit,
     dequeue_ptr_trb: usize,
     dequeue_ptr_segment: usize,
-    task_collection: Rc<RefCell<task::Collection>>,
     registers: Rc<RefCell<Registers>>,
 }
 impl<'a> Ring {
     const MAX_NUM_OF_TRB_IN_QUEUE: u16 = 4096;
 
-    pub fn new(
-        registers: Rc<RefCell<Registers>>,
-        task_collection: Rc<RefCell<task::Collection>>,
-    ) -> Self {
+    pub fn new(registers: Rc<RefCell<Registers>>) -> Self {
         let max_num_of_erst = registers
             .borrow()
             .hc_capability
@@ -70,7 +66,6 @@ impl<'a> Ring {
             current_cycle_bit: CycleBit::new(true),
             dequeue_ptr_trb: 0,
             dequeue_ptr_segment: 0,
-            task_collection,
             registers,
         }
     }
@@ -168,12 +163,9 @@ impl<'a> Stream for Ring {
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         WAKER.register(&cx.waker());
-        let task_collection = self.task_collection.clone();
         Pin::into_inner(self).dequeue().map_or_else(
             || {
-                task_collection
-                    .borrow_mut()
-                    .add_task_as_woken(Task::new(task_to_check_event_ring()));
+                Spawner::current().spawn(task_to_check_event_ring());
                 Poll::Pending
             },
             |trb| {
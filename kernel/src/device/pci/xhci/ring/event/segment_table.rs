@@ -1,15 +1,21 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use {
-    crate::mem::allocator::page_box::PageBox,
+    crate::mem::dma,
     core::ops::{Index, IndexMut},
     x86_64::PhysAddr,
 };
 
-pub struct SegmentTable(PageBox<[Entry]>);
+pub struct SegmentTable(dma::Coherent<[Entry]>);
 impl SegmentTable {
+    const ALIGNMENT: usize = 64;
+
     pub fn new(len: usize) -> Self {
-        Self(PageBox::new_slice(Entry::null(), len))
+        Self(dma::Coherent::new_slice(
+            Entry::null(),
+            len,
+            dma::Constraints::new().align(Self::ALIGNMENT),
+        ))
     }
 
     pub fn phys_addr(&self) -> PhysAddr {
@@ -2,7 +2,7 @@
 
 use {
     super::{trb, CycleBit},
-    crate::mem::allocator::page_box::PageBox,
+    crate::mem::dma,
     core::{
         convert::TryInto,
         ops::{Index, IndexMut},
@@ -10,10 +10,20 @@ use {
     x86_64::PhysAddr,
 };
 
-pub struct Ring(PageBox<[Trb]>);
+pub struct Ring(dma::Coherent<[Trb]>);
 impl Ring {
+    // A ring segment must not cross a 64 KiB boundary.
+    const ALIGNMENT: usize = 64;
+    const BOUNDARY: usize = 0x1_0000;
+
     pub fn new(num_trb: usize) -> Self {
-        Self(PageBox::new_slice(Trb::null(), num_trb))
+        Self(dma::Coherent::new_slice(
+            Trb::null(),
+            num_trb,
+            dma::Constraints::new()
+                .align(Self::ALIGNMENT)
+                .boundary(Self::BOUNDARY),
+        ))
     }
 
     pub fn len(&self) -> usize {
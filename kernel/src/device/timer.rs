@@ -0,0 +1,53 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// The channel 0 of the PIT raises IRQ 0 periodically.
+
+use {
+    core::{
+        convert::TryFrom,
+        sync::atomic::{AtomicU64, Ordering},
+    },
+    x86_64::instructions::port::Port,
+};
+
+pub const FREQUENCY: u32 = 100;
+
+const PORT_CHANNEL_0: u16 = 0x0040;
+const PORT_COMMAND: u16 = 0x0043;
+
+const BASE_FREQUENCY: u32 = 1_193_182;
+// Channel 0, the low byte then the high byte, and the rate generator mode.
+const COMMAND_RATE_GENERATOR: u8 = 0x34;
+
+static TICKS: AtomicU64 = AtomicU64::new(0);
+
+pub fn init() {
+    let divisor = u16::try_from(BASE_FREQUENCY / FREQUENCY).unwrap();
+    let [low, high] = divisor.to_le_bytes();
+
+    // Safety: These ports are of the PIT, and writing to them has no side effect other than
+    // changing the frequency of IRQ 0.
+    unsafe {
+        Port::new(PORT_COMMAND).write(COMMAND_RATE_GENERATOR);
+        Port::new(PORT_CHANNEL_0).write(low);
+        Port::new(PORT_CHANNEL_0).write(high);
+    }
+}
+
+// Called by the handler of IRQ 0.
+pub fn tick() {
+    TICKS.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn ticks() -> u64 {
+    TICKS.load(Ordering::Relaxed)
+}
+
+// Rounded up so that waiting for the returned ticks never ends too early.
+pub fn ms_to_ticks(ms: u64) -> u64 {
+    ms.saturating_mul(u64::from(FREQUENCY)).saturating_add(999) / 1000
+}
+
+pub fn ticks_to_ms(ticks: u64) -> u64 {
+    ticks.saturating_mul(1000) / u64::from(FREQUENCY)
+}
@@ -1,35 +1,64 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use crate::x86_64::instructions::segmentation;
+use crate::x86_64::instructions::{segmentation, tables};
 use crate::x86_64::structures::gdt::{Descriptor, GlobalDescriptorTable, SegmentSelector};
-use crate::x86_64::PrivilegeLevel;
+use crate::x86_64::structures::tss::TaskStateSegment;
+use crate::x86_64::{PrivilegeLevel, VirtAddr};
 use conquer_once::spin::Lazy;
 
+// The CPU loads RSP0 of the TSS when an interrupt or an exception happens in user mode.
+static mut TSS: TaskStateSegment = TaskStateSegment::new();
+
+// The user data segment is placed right before the user code segment, as `sysret` requires.
 pub static GDT: Lazy<Gdt> = Lazy::new(|| {
     let mut gdt = GlobalDescriptorTable::new();
-    let code_selector = gdt.add_entry(Descriptor::kernel_code_segment());
+    let kernel_code = gdt.add_entry(Descriptor::kernel_code_segment());
+    let kernel_data = gdt.add_entry(Descriptor::kernel_data_segment());
+    let user_data = gdt.add_entry(Descriptor::user_data_segment());
+    let user_code = gdt.add_entry(Descriptor::user_code_segment());
+    // Safety: `TSS` is only written by `set_kernel_stack`, which the CPU does not race with.
+    let tss = gdt.add_entry(Descriptor::tss_segment(unsafe { &TSS }));
 
-    Gdt::new(gdt, code_selector)
+    Gdt {
+        table: gdt,
+        kernel_code,
+        kernel_data,
+        user_data,
+        user_code,
+        tss,
+    }
 });
 
 pub struct Gdt {
     table: GlobalDescriptorTable,
-    code_selector: SegmentSelector,
+    kernel_code: SegmentSelector,
+    kernel_data: SegmentSelector,
+    user_data: SegmentSelector,
+    user_code: SegmentSelector,
+    tss: SegmentSelector,
 }
-
 impl Gdt {
-    fn new(table: GlobalDescriptorTable, code_selector: SegmentSelector) -> Self {
-        Self {
-            table,
-            code_selector,
-        }
+    pub fn kernel_code(&self) -> SegmentSelector {
+        self.kernel_code
+    }
+
+    pub fn kernel_data(&self) -> SegmentSelector {
+        self.kernel_data
+    }
+
+    pub fn user_data(&self) -> SegmentSelector {
+        self.user_data
+    }
+
+    pub fn user_code(&self) -> SegmentSelector {
+        self.user_code
     }
 }
 
 pub fn init() {
     GDT.table.load();
     unsafe {
-        segmentation::set_cs(GDT.code_selector);
+        segmentation::set_cs(GDT.kernel_code);
 
         let null_seg = SegmentSelector::new(0, PrivilegeLevel::Ring0);
         segmentation::load_ds(null_seg);
@@ -37,5 +66,14 @@ pub fn init() {
         segmentation::load_fs(null_seg);
         segmentation::load_gs(null_seg);
         segmentation::load_ss(null_seg);
+
+        tables::load_tss(GDT.tss);
     }
 }
+
+// Call this with interrupts disabled before running a thread which may enter user mode.
+pub fn set_kernel_stack(top: VirtAddr) {
+    // Safety: There is only one CPU, and it reads the TSS only when the privilege level changes,
+    // which does not happen while interrupts are disabled in the kernel.
+    unsafe { TSS.privilege_stack_table[0] = top }
+}
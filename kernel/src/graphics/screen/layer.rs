@@ -1,16 +1,16 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use {
-    super::Vram, conquer_once::spin::OnceCell, core::convert::TryFrom, screen_layer,
-    spinning_top::Spinlock,
+    super::Vram, crate::lock::IrqSpinlock, conquer_once::spin::OnceCell, core::convert::TryFrom,
+    screen_layer,
 };
 
-pub static CONTROLLER: OnceCell<Spinlock<screen_layer::Controller>> = OnceCell::uninit();
+pub static CONTROLLER: OnceCell<IrqSpinlock<screen_layer::Controller>> = OnceCell::uninit();
 
 pub fn init() {
     CONTROLLER
         .try_init_once(|| {
-            Spinlock::new(unsafe {
+            IrqSpinlock::new("layer controller", unsafe {
                 screen_layer::Controller::new(
                     Vram::resolution().as_(),
                     usize::try_from(Vram::bpp()).unwrap(),
@@ -21,7 +21,7 @@ pub fn init() {
         .expect("Layer controller is already initialized.")
 }
 
-pub(super) fn get_controller() -> &'static Spinlock<screen_layer::Controller> {
+pub(super) fn get_controller() -> &'static IrqSpinlock<screen_layer::Controller> {
     CONTROLLER
         .try_get()
         .expect("Layer controller is not initialized.")
@@ -2,11 +2,11 @@
 
 use {
     super::writer::Writer,
+    crate::lock::IrqSpinlock,
     conquer_once::spin::Lazy,
     core::fmt::Write,
     log::{Level, LevelFilter, Metadata, Record, SetLoggerError},
     rgb::RGB8,
-    spinning_top::Spinlock,
     vek::Vec2,
 };
 
@@ -14,8 +14,13 @@ struct Logger;
 
 static LOGGER: Logger = Logger;
 
-static LOG_WRITER: Lazy<Spinlock<Writer>> =
-    Lazy::new(|| Spinlock::new(Writer::new(Vec2::new(0, 100), RGB8::new(0xff, 0xff, 0xff))));
+// Interrupt handlers log messages too.
+static LOG_WRITER: Lazy<IrqSpinlock<Writer>> = Lazy::new(|| {
+    IrqSpinlock::new(
+        "log writer",
+        Writer::new(Vec2::new(0, 100), RGB8::new(0xff, 0xff, 0xff)),
+    )
+});
 
 impl log::Log for Logger {
     fn enabled(&self, metadata: &Metadata) -> bool {
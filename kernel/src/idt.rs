@@ -17,9 +17,12 @@ static IDT: Lazy<InterruptDescriptorTable> = Lazy::new(|| {
     idt[0x06].set_handler_fn(interrupt::handler_06);
     idt[0x07].set_handler_fn(interrupt::handler_07);
     idt[0x09].set_handler_fn(interrupt::handler_09);
+    idt.general_protection_fault
+        .set_handler_fn(interrupt::handler_0d);
     idt[0x10].set_handler_fn(interrupt::handler_10);
     idt[0x13].set_handler_fn(interrupt::handler_13);
     idt[0x14].set_handler_fn(interrupt::handler_14);
+    idt.page_fault.set_handler_fn(interrupt::handler_0e);
     idt[0x20].set_handler_fn(interrupt::handler_20);
     idt[0x21].set_handler_fn(interrupt::handler_21);
     idt[0x2c].set_handler_fn(interrupt::handler_2c);
@@ -1,9 +1,15 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use {
-    crate::device::{keyboard, mouse},
+    crate::{
+        device::{keyboard, mouse, timer},
+        mem::fault,
+        multitask::thread,
+        process,
+    },
+    abi::process::Fault,
     common::constant::PORT_KEY_DATA,
-    x86_64::{instructions::port::Port, structures::idt},
+    x86_64::{instructions::port::Port, registers::control::Cr2, structures::idt},
 };
 
 const PIC0_ICW1: u16 = 0x0020;
@@ -67,12 +73,13 @@ fn enable_nonbuffer_mode() {
 
 pub fn set_init_pic_bits() {
     unsafe {
-        Port::new(PIC0_IMR).write(0xF9_u8);
+        Port::new(PIC0_IMR).write(0xF8_u8);
         Port::new(PIC1_IMR).write(0xEF_u8);
     }
 }
 
-pub extern "x86-interrupt" fn handler_00(_stack_frame: &mut idt::InterruptStackFrame) {
+pub extern "x86-interrupt" fn handler_00(stack_frame: &mut idt::InterruptStackFrame) {
+    kill_if_from_user(stack_frame, Fault::DivideByZero, "Divide-by-zero Error!");
     panic!("Divide-by-zero Error!");
 }
 
@@ -84,19 +91,23 @@ pub extern "x86-interrupt" fn handler_02(_stack_frame: &mut idt::InterruptStackF
     panic!("Non-maskable Interrupt!");
 }
 
-pub extern "x86-interrupt" fn handler_03(_stack_frame: &mut idt::InterruptStackFrame) {
+pub extern "x86-interrupt" fn handler_03(stack_frame: &mut idt::InterruptStackFrame) {
+    kill_if_from_user(stack_frame, Fault::Breakpoint, "Breakpoint!");
     panic!("Breakpoint!");
 }
 
-pub extern "x86-interrupt" fn handler_04(_stack_frame: &mut idt::InterruptStackFrame) {
+pub extern "x86-interrupt" fn handler_04(stack_frame: &mut idt::InterruptStackFrame) {
+    kill_if_from_user(stack_frame, Fault::Overflow, "Overflow!");
     panic!("Overflow!");
 }
 
-pub extern "x86-interrupt" fn handler_05(_stack_frame: &mut idt::InterruptStackFrame) {
+pub extern "x86-interrupt" fn handler_05(stack_frame: &mut idt::InterruptStackFrame) {
+    kill_if_from_user(stack_frame, Fault::BoundRange, "Bound Range Exceeded!");
     panic!("Bound Range Exceeded!");
 }
 
-pub extern "x86-interrupt" fn handler_06(_stack_frame: &mut idt::InterruptStackFrame) {
+pub extern "x86-interrupt" fn handler_06(stack_frame: &mut idt::InterruptStackFrame) {
+    kill_if_from_user(stack_frame, Fault::InvalidOpcode, "Invalid Opcode!");
     panic!("Invalid Opcode!");
 }
 
@@ -108,11 +119,40 @@ pub extern "x86-interrupt" fn handler_09(_stack_frame: &mut idt::InterruptStackF
     panic!("Coprocessor Segment Overrun!");
 }
 
-pub extern "x86-interrupt" fn handler_10(_stack_frame: &mut idt::InterruptStackFrame) {
+pub extern "x86-interrupt" fn handler_0d(
+    stack_frame: &mut idt::InterruptStackFrame,
+    error_code: u64,
+) {
+    kill_if_from_user(
+        stack_frame,
+        Fault::GeneralProtection,
+        "General Protection Fault!",
+    );
+    panic!("General Protection Fault! Error code: {:#x}", error_code);
+}
+
+pub extern "x86-interrupt" fn handler_0e(
+    stack_frame: &mut idt::InterruptStackFrame,
+    error_code: idt::PageFaultErrorCode,
+) {
+    fault::handle(Cr2::read(), error_code, stack_frame);
+}
+
+pub extern "x86-interrupt" fn handler_10(stack_frame: &mut idt::InterruptStackFrame) {
+    kill_if_from_user(
+        stack_frame,
+        Fault::FloatingPoint,
+        "x87 Floating-Point Exception",
+    );
     panic!("x87 Floating-Point Exception");
 }
 
-pub extern "x86-interrupt" fn handler_13(_stack_frame: &mut idt::InterruptStackFrame) {
+pub extern "x86-interrupt" fn handler_13(stack_frame: &mut idt::InterruptStackFrame) {
+    kill_if_from_user(
+        stack_frame,
+        Fault::FloatingPoint,
+        "SIMD Floating-Point Exception",
+    );
     panic!("SIMD Floating-Point Exception");
 }
 
@@ -120,10 +160,19 @@ pub extern "x86-interrupt" fn handler_14(_stack_frame: &mut idt::InterruptStackF
     panic!("Virtualization Exception!");
 }
 
-pub extern "x86-interrupt" fn handler_20(_stack_frame: &mut idt::InterruptStackFrame) {
+// The end of interrupt is sent first because the handler may switch to another thread, and
+// returns only when this thread is scheduled again.
+pub extern "x86-interrupt" fn handler_20(stack_frame: &mut idt::InterruptStackFrame) {
     unsafe {
         Port::new(PIC0_OCW2).write(0x60_u8);
     }
+    timer::tick();
+    thread::on_timer();
+
+    // A killed process ends when it is preempted in user mode.
+    if process::from_user(stack_frame.code_segment) {
+        process::exit_if_terminated();
+    }
 }
 
 pub extern "x86-interrupt" fn handler_21(_stack_frame: &mut idt::InterruptStackFrame) {
@@ -144,3 +193,10 @@ pub extern "x86-interrupt" fn handler_2c(_stack_frame: &mut idt::InterruptStackF
 pub extern "x86-interrupt" fn handler_40(_stack_frame: &mut idt::InterruptStackFrame) {
     info!("Interrupt from 0x40");
 }
+
+// An exception caused by user code kills the process instead of the kernel.
+fn kill_if_from_user(stack_frame: &idt::InterruptStackFrame, fault: Fault, name: &str) {
+    if process::from_user(stack_frame.code_segment) {
+        process::kill_current(fault, format_args!("{}", name));
+    }
+}
@@ -0,0 +1,91 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use {
+    super::{reply, Error, Message},
+    crate::{
+        lock::IrqSpinlock,
+        multitask::{sync::Notify, thread::sync::WaitQueue},
+        process,
+    },
+    alloc::collections::VecDeque,
+};
+
+const CAPACITY: usize = 32;
+
+// A bounded queue of messages with any number of senders and receivers. The queue is locked with
+// interrupts disabled because `WaitQueue::wait_while` checks it so.
+pub struct Endpoint {
+    queue: IrqSpinlock<VecDeque<Message>>,
+    threads: WaitQueue,
+    tasks: Notify,
+}
+impl Endpoint {
+    pub fn new() -> Self {
+        Self {
+            queue: IrqSpinlock::new("ipc endpoint", VecDeque::new()),
+            threads: WaitQueue::new(),
+            tasks: Notify::new(),
+        }
+    }
+
+    // Returns the message back if the queue is full.
+    pub fn send(&self, message: Message) -> Result<(), Message> {
+        {
+            let mut queue = self.queue.lock();
+            if queue.len() >= CAPACITY {
+                return Err(message);
+            }
+            queue.push_back(message);
+        }
+
+        self.threads.notify_one();
+        self.tasks.notify_one();
+        Ok(())
+    }
+
+    pub fn try_receive(&self) -> Option<Message> {
+        self.queue.lock().pop_front()
+    }
+
+    // For threads. Tasks must use `receive` instead. Returns `None` if the process of the thread
+    // is asked to terminate.
+    pub fn receive_blocking(&self) -> Option<Message> {
+        loop {
+            if let Some(message) = self.try_receive() {
+                return Some(message);
+            }
+
+            if process::current_is_terminating() {
+                return None;
+            }
+
+            self.threads
+                .wait_while(|| self.queue.lock().is_empty() && !process::current_is_terminating());
+        }
+    }
+
+    pub async fn receive(&self) -> Message {
+        loop {
+            if let Some(message) = self.try_receive() {
+                return message;
+            }
+
+            self.tasks.notified().await;
+        }
+    }
+
+    // Sends `message` with a reply capability, and blocks the thread until the receiver replies
+    // or drops the capability.
+    pub fn call(&self, mut message: Message) -> Result<Message, Error> {
+        let (reply, waiter) = reply::new();
+        message.reply = Some(reply);
+
+        self.send(message).map_err(|_| Error::Full)?;
+        waiter.wait()
+    }
+}
+impl Default for Endpoint {
+    fn default() -> Self {
+        Self::new()
+    }
+}
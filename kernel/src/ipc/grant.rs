@@ -0,0 +1,97 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use {
+    crate::mem::{allocator::phys::FRAME_MANAGER, phys_to_virt},
+    alloc::{sync::Arc, vec::Vec},
+    core::{cmp, convert::TryFrom, ptr},
+    x86_64::structures::paging::{PageSize, PhysFrame, Size4KiB},
+};
+
+// Pages of a process shared through a message. The frames are freed when the last process which
+// maps them and the last `Grant` are gone.
+#[derive(Clone)]
+pub struct Grant {
+    frames: Arc<Frames>,
+    writable: bool,
+}
+impl Grant {
+    pub(crate) fn new(frames: Arc<Frames>, writable: bool) -> Self {
+        Self { frames, writable }
+    }
+
+    pub fn num_of_pages(&self) -> usize {
+        self.frames.0.len()
+    }
+
+    pub fn writable(&self) -> bool {
+        self.writable
+    }
+
+    // Copies as many bytes as possible from `offset`. Returns the number of bytes copied.
+    pub fn read(&self, offset: usize, buf: &mut [u8]) -> usize {
+        self.for_each_chunk(offset, buf.len(), |src, range| {
+            let dst = &mut buf[range];
+
+            // Safety: `src` points to `dst.len()` bytes in a shared frame.
+            unsafe { ptr::copy_nonoverlapping(src, dst.as_mut_ptr(), dst.len()) }
+        })
+    }
+
+    // Returns `None` if the grant is read-only.
+    pub fn write(&self, offset: usize, data: &[u8]) -> Option<usize> {
+        if !self.writable {
+            return None;
+        }
+
+        Some(self.for_each_chunk(offset, data.len(), |dst, range| {
+            let src = &data[range];
+
+            // Safety: `dst` points to `src.len()` bytes in a shared frame. The processes which map
+            // it may race with this, but the kernel does not rely on its content.
+            unsafe { ptr::copy_nonoverlapping(src.as_ptr(), dst, src.len()) }
+        }))
+    }
+
+    pub(crate) fn frames(&self) -> &Arc<Frames> {
+        &self.frames
+    }
+
+    fn for_each_chunk<F>(&self, offset: usize, len: usize, mut f: F) -> usize
+    where
+        F: FnMut(*mut u8, core::ops::Range<usize>),
+    {
+        let page_size = usize::try_from(Size4KiB::SIZE).unwrap();
+        let end = cmp::min(offset.saturating_add(len), self.num_of_pages() * page_size);
+
+        let mut pos = offset;
+        while pos < end {
+            let frame = self.frames.0[pos / page_size];
+            let chunk = cmp::min(end - pos, page_size - pos % page_size);
+            let addr = phys_to_virt(frame.start_address()) + pos % page_size;
+
+            f(addr.as_mut_ptr(), pos - offset..pos - offset + chunk);
+            pos += chunk;
+        }
+
+        end.saturating_sub(offset)
+    }
+}
+
+pub(crate) struct Frames(Vec<PhysFrame>);
+impl Frames {
+    pub(crate) fn new(frames: Vec<PhysFrame>) -> Self {
+        Self(frames)
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = PhysFrame> + '_ {
+        self.0.iter().copied()
+    }
+}
+impl Drop for Frames {
+    fn drop(&mut self) {
+        let mut frame_manager = FRAME_MANAGER.lock();
+        for frame in &self.0 {
+            frame_manager.free(frame.start_address());
+        }
+    }
+}
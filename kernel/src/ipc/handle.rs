@@ -0,0 +1,110 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use {
+    super::{Endpoint, Reply},
+    alloc::{sync::Arc, vec::Vec},
+    core::convert::TryFrom,
+};
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Handle(u64);
+impl Handle {
+    pub fn from_u64(raw: u64) -> Self {
+        Self(raw)
+    }
+
+    pub fn as_u64(self) -> u64 {
+        self.0
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Rights {
+    pub send: bool,
+    pub receive: bool,
+}
+impl Rights {
+    pub const SEND: Self = Self {
+        send: true,
+        receive: false,
+    };
+    pub const ALL: Self = Self {
+        send: true,
+        receive: true,
+    };
+}
+
+pub enum Capability {
+    Endpoint {
+        endpoint: Arc<Endpoint>,
+        rights: Rights,
+    },
+    Reply(Reply),
+}
+
+// The capability table of a process. Handles are indices, and freed ones are reused.
+#[derive(Default)]
+pub struct Handles(Vec<Option<Capability>>);
+impl Handles {
+    pub fn insert(&mut self, capability: Capability) -> Handle {
+        let index = match self.0.iter().position(Option::is_none) {
+            Some(index) => {
+                self.0[index] = Some(capability);
+                index
+            }
+            None => {
+                self.0.push(Some(capability));
+                self.0.len() - 1
+            }
+        };
+
+        Handle(u64::try_from(index).unwrap())
+    }
+
+    // Returns the endpoint only if the handle has all of `rights`.
+    pub fn endpoint(&self, handle: Handle, rights: Rights) -> Option<Arc<Endpoint>> {
+        match self.get(handle)? {
+            Capability::Endpoint {
+                endpoint,
+                rights: held,
+            } if (held.send || !rights.send) && (held.receive || !rights.receive) => {
+                Some(Arc::clone(endpoint))
+            }
+            _ => None,
+        }
+    }
+
+    // Endpoint capabilities are copied. Replies are not, as they are answered only once.
+    pub fn duplicate(&self, handle: Handle) -> Option<Capability> {
+        match self.get(handle)? {
+            Capability::Endpoint { endpoint, rights } => Some(Capability::Endpoint {
+                endpoint: Arc::clone(endpoint),
+                rights: *rights,
+            }),
+            Capability::Reply(_) => None,
+        }
+    }
+
+    pub fn take_reply(&mut self, handle: Handle) -> Option<Reply> {
+        let slot = self.0.get_mut(index(handle)?)?;
+        match slot.take()? {
+            Capability::Reply(reply) => Some(reply),
+            endpoint => {
+                *slot = Some(endpoint);
+                None
+            }
+        }
+    }
+
+    pub fn remove(&mut self, handle: Handle) -> Option<Capability> {
+        self.0.get_mut(index(handle)?)?.take()
+    }
+
+    fn get(&self, handle: Handle) -> Option<&Capability> {
+        self.0.get(index(handle)?)?.as_ref()
+    }
+}
+
+fn index(handle: Handle) -> Option<usize> {
+    usize::try_from(handle.0).ok()
+}
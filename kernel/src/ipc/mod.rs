@@ -0,0 +1,55 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Message passing between processes and kernel tasks. Messages are queued on endpoints, which
+// processes reach through handles in their capability tables. A message carries a few words
+// inline, and may share pages of the sender through a grant, copy an endpoint capability, or
+// carry a reply capability for `call`.
+//
+// Threads block in `Endpoint::receive_blocking` and `Endpoint::call`, and tasks await
+// `Endpoint::receive`.
+
+pub mod endpoint;
+pub mod grant;
+pub mod handle;
+pub mod reply;
+pub mod service;
+
+pub use {
+    endpoint::Endpoint,
+    grant::Grant,
+    handle::{Capability, Handle, Handles, Rights},
+    reply::Reply,
+};
+
+use {crate::process, abi::ipc::INLINE_WORDS};
+
+pub struct Message {
+    // `None` if a kernel task sent this.
+    pub sender: Option<process::Id>,
+    pub label: u64,
+    pub data: [u64; INLINE_WORDS],
+    pub grant: Option<Grant>,
+    pub capability: Option<Capability>,
+    // Set by `Endpoint::call`.
+    pub reply: Option<Reply>,
+}
+impl Message {
+    pub fn new(label: u64, data: [u64; INLINE_WORDS]) -> Self {
+        Self {
+            sender: None,
+            label,
+            data,
+            grant: None,
+            capability: None,
+            reply: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Full,
+    NoReply,
+    // The process of the calling thread is asked to terminate.
+    Interrupted,
+}
@@ -0,0 +1,65 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use {
+    super::{Error, Message},
+    crate::{lock::IrqSpinlock, multitask::thread::sync::WaitQueue, process},
+    alloc::sync::Arc,
+    core::mem,
+};
+
+// The capability to answer a `call` once. Dropping it without replying fails the call.
+pub struct Reply(Arc<Slot>);
+impl Reply {
+    pub fn send(self, message: Message) {
+        *self.0.state.lock() = State::Replied(message);
+        self.0.waiter.notify_all();
+    }
+}
+impl Drop for Reply {
+    fn drop(&mut self) {
+        let mut state = self.0.state.lock();
+        if let State::Waiting = *state {
+            *state = State::Dropped;
+            drop(state);
+
+            self.0.waiter.notify_all();
+        }
+    }
+}
+
+pub(super) struct Waiter(Arc<Slot>);
+impl Waiter {
+    pub(super) fn wait(self) -> Result<Message, Error> {
+        self.0.waiter.wait_while(|| {
+            matches!(*self.0.state.lock(), State::Waiting) && !process::current_is_terminating()
+        });
+
+        let state = mem::replace(&mut *self.0.state.lock(), State::Dropped);
+        match state {
+            State::Replied(message) => Ok(message),
+            State::Dropped => Err(Error::NoReply),
+            // A reply which comes later is dropped with the slot.
+            State::Waiting => Err(Error::Interrupted),
+        }
+    }
+}
+
+pub(super) fn new() -> (Reply, Waiter) {
+    let slot = Arc::new(Slot {
+        state: IrqSpinlock::new("ipc reply", State::Waiting),
+        waiter: WaitQueue::new(),
+    });
+
+    (Reply(Arc::clone(&slot)), Waiter(slot))
+}
+
+struct Slot {
+    state: IrqSpinlock<State>,
+    waiter: WaitQueue,
+}
+
+enum State {
+    Waiting,
+    Replied(Message),
+    Dropped,
+}
@@ -0,0 +1,50 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Endpoints of kernel tasks which processes find by name with the `lookup` system call.
+
+use {
+    super::{Endpoint, Message},
+    alloc::{collections::BTreeMap, sync::Arc},
+    conquer_once::spin::Lazy,
+    spinning_top::Spinlock,
+};
+
+// The label of a reply to a message whose label the service does not know.
+pub const LABEL_UNKNOWN: u64 = u64::MAX;
+
+const LABEL_ECHO: u64 = 0;
+
+static SERVICES: Lazy<Spinlock<BTreeMap<&'static str, Arc<Endpoint>>>> =
+    Lazy::new(|| Spinlock::new(BTreeMap::new()));
+
+// Panics if `name` is already registered.
+pub fn register(name: &'static str) -> Arc<Endpoint> {
+    let endpoint = Arc::new(Endpoint::new());
+    let old = SERVICES.lock().insert(name, Arc::clone(&endpoint));
+    assert!(old.is_none(), "The service {} is registered twice.", name);
+
+    endpoint
+}
+
+pub fn lookup(name: &str) -> Option<Arc<Endpoint>> {
+    SERVICES.lock().get(name).cloned()
+}
+
+// Replies to `LABEL_ECHO` with the same words. Processes can use this to test IPC.
+pub async fn echo() {
+    let endpoint = register("echo");
+
+    loop {
+        let mut message = endpoint.receive().await;
+        let reply = match message.reply.take() {
+            Some(reply) => reply,
+            None => continue,
+        };
+
+        let label = match message.label {
+            LABEL_ECHO => LABEL_ECHO,
+            _ => LABEL_UNKNOWN,
+        };
+        reply.send(Message::new(label, message.data));
+    }
+}
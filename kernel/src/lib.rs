@@ -28,27 +28,32 @@ mod device;
 mod gdt;
 mod idt;
 mod interrupt;
+mod ipc;
+mod lock;
 mod mem;
 mod multitask;
 mod panic;
+mod process;
+mod syscall;
 
 use {
-    alloc::rc::Rc,
     common::kernelboot,
-    core::cell::RefCell,
     device::{
         keyboard, mouse,
         pci::{ahci, xhci},
+        timer,
     },
     graphics::{
         screen::{self, desktop::Desktop, layer},
         Vram,
     },
-    mem::allocator::{heap, phys::FrameManager},
-    multitask::{
-        executor::Executor,
-        task::{self, Task},
+    mem::{
+        allocator::{heap, phys::FrameManager},
+        fault, meminfo,
+        paging::{self, direct_map, protection},
+        reclaim,
     },
+    multitask::{executor::Executor, pool, task::Priority, thread},
     x86_64::instructions::interrupts,
 };
 
@@ -64,50 +69,82 @@ fn initialization(boot_info: &mut kernelboot::Info) {
     Vram::init(&boot_info);
 
     gdt::init();
+    syscall::init();
     idt::init();
     interrupt::init_pic();
 
     interrupts::enable();
 
+    protection::enable_nx();
+
     heap::init(boot_info.mem_map_mut());
 
     FrameManager::init(boot_info.mem_map_mut());
+    fault::refill_frame_reserve();
+    direct_map::init(boot_info.mem_map_mut());
+
+    // The memory map is in `LOADER_DATA` memory, which is reclaimed.
+    let mem_map = boot_info.mem_map_mut().to_vec();
+    paging::replace_pml4();
+    let bytes_reclaimed = reclaim::boot_memory(&mem_map, boot_info.reserved());
+
+    protection::protect_kernel(&boot_info);
+    protection::enable_write_protection();
 
     layer::init();
 
     screen::log::init().unwrap();
 
+    protection::report_wx_pages();
+
     let desktop = Desktop::new();
     desktop.draw();
 
     info!("Hello Ramen OS!");
     info!("Vram information: {}", Vram::display());
+    meminfo::log();
+    info!(
+        "Reclaimed {} KiB of boot-time memory.",
+        bytes_reclaimed.as_usize() / 1024
+    );
 
     info!(
         "The number of PCI devices: {}",
         device::pci::iter_devices().count()
     );
 
+    timer::init();
+    thread::init();
+
     interrupt::set_init_pic_bits();
 }
 
 #[cfg(not(feature = "qemu_test"))]
 fn run_tasks() -> ! {
-    let task_collection = Rc::new(RefCell::new(task::Collection::new()));
-    task_collection
-        .borrow_mut()
-        .add_task_as_woken(Task::new(keyboard::task()));
-    task_collection
-        .borrow_mut()
-        .add_task_as_woken(Task::new(mouse::task()));
-    task_collection
-        .borrow_mut()
-        .add_task_as_woken(Task::new(xhci::task(task_collection.clone())));
-    task_collection
-        .borrow_mut()
-        .add_task_as_woken(Task::new(ahci::task()));
-
-    let mut executor = Executor::new(task_collection);
+    // Device drivers busy-wait for their controllers. They run in another thread so that input
+    // handling keeps working meanwhile.
+    Executor::run_in_thread(|| {
+        let executor = Executor::new().isolate_panics();
+        let spawner = executor.spawner();
+        spawner.spawn_named("xhci", xhci::task());
+        spawner.spawn_named("ahci", ahci::task());
+
+        executor
+    })
+    .expect("Failed to create a thread for device drivers.");
+
+    // IRQ 12 will be delivered to the bootstrap processor, which runs worker 0.
+    pool::init();
+    pool::spawn(pool::Task::new(mouse::task()).pin_to(0));
+
+    let mut executor = Executor::new();
+    let spawner = executor.spawner();
+    let input = spawner.with_priority(Priority::Input);
+    input.spawn_named("keyboard", keyboard::task());
+    spawner.spawn_named("echo", ipc::service::echo());
+
+    process::init::spawn();
+
     executor.run();
 }
 
@@ -0,0 +1,101 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+#[cfg(debug_assertions)]
+use {super::order, core::panic::Location};
+use {
+    core::{
+        mem::ManuallyDrop,
+        ops::{Deref, DerefMut},
+    },
+    spinning_top::{Spinlock, SpinlockGuard},
+    x86_64::instructions::interrupts,
+};
+
+// Locks of the same name are treated as one lock by the order checker.
+pub struct IrqSpinlock<T> {
+    #[cfg_attr(not(debug_assertions), allow(dead_code))]
+    name: &'static str,
+    inner: Spinlock<T>,
+}
+impl<T> IrqSpinlock<T> {
+    pub const fn new(name: &'static str, value: T) -> Self {
+        Self {
+            name,
+            inner: Spinlock::new(value),
+        }
+    }
+
+    #[track_caller]
+    pub fn lock(&self) -> IrqSpinlockGuard<'_, T> {
+        let were_enabled = interrupts::are_enabled();
+        interrupts::disable();
+
+        #[cfg(debug_assertions)]
+        order::acquire(self.name, Location::caller());
+
+        IrqSpinlockGuard {
+            lock: self,
+            guard: ManuallyDrop::new(self.inner.lock()),
+            were_enabled,
+        }
+    }
+
+    // The order is not checked because this never waits, but the lock counts as held.
+    #[track_caller]
+    pub fn try_lock(&self) -> Option<IrqSpinlockGuard<'_, T>> {
+        let were_enabled = interrupts::are_enabled();
+        interrupts::disable();
+
+        match self.inner.try_lock() {
+            Some(guard) => {
+                #[cfg(debug_assertions)]
+                order::hold(self.name, Location::caller());
+
+                Some(IrqSpinlockGuard {
+                    lock: self,
+                    guard: ManuallyDrop::new(guard),
+                    were_enabled,
+                })
+            }
+            None => {
+                if were_enabled {
+                    interrupts::enable();
+                }
+                None
+            }
+        }
+    }
+}
+
+pub struct IrqSpinlockGuard<'a, T> {
+    #[cfg_attr(not(debug_assertions), allow(dead_code))]
+    lock: &'a IrqSpinlock<T>,
+    guard: ManuallyDrop<SpinlockGuard<'a, T>>,
+    were_enabled: bool,
+}
+impl<T> Deref for IrqSpinlockGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.guard
+    }
+}
+impl<T> DerefMut for IrqSpinlockGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.guard
+    }
+}
+impl<T> Drop for IrqSpinlockGuard<'_, T> {
+    fn drop(&mut self) {
+        // Safety: The guard is not used after this. The lock is released before interrupts are
+        // enabled.
+        unsafe { ManuallyDrop::drop(&mut self.guard) }
+
+        #[cfg(debug_assertions)]
+        order::release(self.lock.name);
+
+        if self.were_enabled {
+            interrupts::enable();
+        }
+    }
+}
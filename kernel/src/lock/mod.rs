@@ -0,0 +1,17 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Spinlocks which disable interrupts while held. An interrupt handler may take one without
+// deadlocking the CPU, and the holder is never preempted. In debug builds, the order in which
+// locks are taken is recorded by their names, and an inversion panics.
+
+mod irq;
+#[cfg(debug_assertions)]
+mod order;
+
+pub use irq::{IrqSpinlock, IrqSpinlockGuard};
+
+// Called by the panic handler, which takes locks in any order.
+pub fn stop_order_checking() {
+    #[cfg(debug_assertions)]
+    order::stop();
+}
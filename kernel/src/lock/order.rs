@@ -0,0 +1,237 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Records that a lock was taken while another was held, and panics if the reverse order, directly
+// or through other locks, was seen before. Interrupts are disabled while an `IrqSpinlock` is held,
+// so one list of held locks is enough on a single CPU.
+//
+// Nothing here allocates or logs because the heap and the log writer use these locks.
+
+use {
+    core::{
+        panic::Location,
+        sync::atomic::{AtomicBool, Ordering},
+    },
+    spinning_top::Spinlock,
+};
+
+// Must not exceed the number of bits of the bitmap in `reachable`.
+const MAX_NAMES: usize = 32;
+const MAX_HELD: usize = 16;
+
+static GRAPH: Spinlock<Graph> = Spinlock::new(Graph::new());
+static STOPPED: AtomicBool = AtomicBool::new(false);
+
+pub(super) fn stop() {
+    STOPPED.store(true, Ordering::Release);
+}
+
+pub(super) fn acquire(name: &'static str, at: &'static Location<'static>) {
+    if STOPPED.load(Ordering::Acquire) {
+        return;
+    }
+
+    let mut graph = GRAPH.lock();
+    let class = graph.class_of(name);
+    let inversion = graph.add_edges(class, at);
+    graph.push_held(class, at);
+    drop(graph);
+
+    if let Some(inversion) = inversion {
+        stop();
+        panic!("{}", inversion);
+    }
+}
+
+pub(super) fn hold(name: &'static str, at: &'static Location<'static>) {
+    if STOPPED.load(Ordering::Acquire) {
+        return;
+    }
+
+    let mut graph = GRAPH.lock();
+    let class = graph.class_of(name);
+    graph.push_held(class, at);
+}
+
+pub(super) fn release(name: &'static str) {
+    if STOPPED.load(Ordering::Acquire) {
+        return;
+    }
+
+    GRAPH.lock().pop_held(name);
+}
+
+#[derive(Copy, Clone)]
+struct Held {
+    class: usize,
+    at: &'static Location<'static>,
+}
+
+// `held_at` is where the first lock was taken, and `taken_at` is where the second one was taken
+// while the first was held.
+#[derive(Copy, Clone)]
+struct Edge {
+    held_at: &'static Location<'static>,
+    taken_at: &'static Location<'static>,
+}
+
+struct Inversion {
+    held: &'static str,
+    held_at: &'static Location<'static>,
+    taken: &'static str,
+    taken_at: &'static Location<'static>,
+    // The first step of the existing path from `taken` to `held`.
+    next: &'static str,
+    before: Edge,
+}
+impl core::fmt::Display for Inversion {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(
+            f,
+            "Lock order inversion: `{}` is taken at {} while `{}` is held from {}, but `{}` was \
+             taken at {} while `{}` was held from {}",
+            self.taken,
+            self.taken_at,
+            self.held,
+            self.held_at,
+            self.next,
+            self.before.taken_at,
+            self.taken,
+            self.before.held_at
+        )?;
+
+        if self.next != self.held {
+            write!(f, ", which leads to `{}`", self.held)?;
+        }
+
+        Ok(())
+    }
+}
+
+struct Graph {
+    names: [Option<&'static str>; MAX_NAMES],
+    // `edges[a][b]` is set if `b` was taken while `a` was held.
+    edges: [[Option<Edge>; MAX_NAMES]; MAX_NAMES],
+    held: [Option<Held>; MAX_HELD],
+}
+impl Graph {
+    const fn new() -> Self {
+        Self {
+            names: [None; MAX_NAMES],
+            edges: [[None; MAX_NAMES]; MAX_NAMES],
+            held: [None; MAX_HELD],
+        }
+    }
+
+    fn class_of(&mut self, name: &'static str) -> usize {
+        if let Some(i) = self.find(name) {
+            return i;
+        }
+
+        let i = self
+            .names
+            .iter()
+            .position(Option::is_none)
+            .expect("Too many names of locks.");
+        self.names[i] = Some(name);
+        i
+    }
+
+    fn find(&self, name: &'static str) -> Option<usize> {
+        self.names.iter().position(|n| *n == Some(name))
+    }
+
+    fn add_edges(&mut self, class: usize, at: &'static Location<'static>) -> Option<Inversion> {
+        for held in self.held.iter().flatten().copied() {
+            if held.class == class {
+                continue;
+            }
+
+            if let Some((next, before)) = self.first_step(class, held.class) {
+                return Some(Inversion {
+                    held: self.name(held.class),
+                    held_at: held.at,
+                    taken: self.name(class),
+                    taken_at: at,
+                    next: self.name(next),
+                    before,
+                });
+            }
+
+            self.edges[held.class][class].get_or_insert(Edge {
+                held_at: held.at,
+                taken_at: at,
+            });
+        }
+
+        None
+    }
+
+    // Returns the first edge of a path from `from` to `to`.
+    fn first_step(&self, from: usize, to: usize) -> Option<(usize, Edge)> {
+        (0..MAX_NAMES).find_map(|next| {
+            let edge = self.edges[from][next]?;
+            if next == to || self.reachable(next, to) {
+                Some((next, edge))
+            } else {
+                None
+            }
+        })
+    }
+
+    fn reachable(&self, from: usize, to: usize) -> bool {
+        let mut visited = 1_u32 << from;
+        let mut stack = [0; MAX_NAMES];
+        let mut len = 1;
+        stack[0] = from;
+
+        while len > 0 {
+            len -= 1;
+            let node = stack[len];
+
+            for next in 0..MAX_NAMES {
+                if self.edges[node][next].is_none() || visited & (1 << next) != 0 {
+                    continue;
+                }
+                if next == to {
+                    return true;
+                }
+
+                visited |= 1 << next;
+                stack[len] = next;
+                len += 1;
+            }
+        }
+
+        false
+    }
+
+    fn name(&self, class: usize) -> &'static str {
+        self.names[class].unwrap()
+    }
+
+    fn push_held(&mut self, class: usize, at: &'static Location<'static>) {
+        let slot = self
+            .held
+            .iter_mut()
+            .find(|h| h.is_none())
+            .expect("Too many locks are held.");
+        *slot = Some(Held { class, at });
+    }
+
+    // Locks may be released in any order. The latest one of the name is removed.
+    fn pop_held(&mut self, name: &'static str) {
+        let class = match self.find(name) {
+            Some(class) => class,
+            None => return,
+        };
+
+        let len = self.held.iter().take_while(|h| h.is_some()).count();
+        if let Some(i) = self.held[..len]
+            .iter()
+            .rposition(|h| h.map(|h| h.class) == Some(class))
+        {
+            self.held[i..len].rotate_left(1);
+            self.held[len - 1] = None;
+        }
+    }
+}
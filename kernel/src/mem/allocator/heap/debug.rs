@@ -0,0 +1,332 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Enabled by the `heap_debug` feature. Each allocation is laid out as below:
+//
+// | Header | red zone | object | red zone |
+//
+// Red zones are checked when the object is freed, and a freed object is filled with
+// `POISON_FREED` so that a use after free is easy to spot. Live allocations are linked to a list
+// which can be dumped to find leaks. F9 dumps the ones made since it was last pressed.
+
+use {
+    super::{alloc_raw, dealloc_raw},
+    crate::{lock::IrqSpinlock, multitask::thread},
+    common::constant::{STACK_BASE, STACK_LOWER},
+    conquer_once::spin::Lazy,
+    core::{
+        alloc::Layout,
+        cmp, mem,
+        ptr::{self, NonNull},
+        slice,
+    },
+    x86_64::VirtAddr,
+};
+
+const BYTES_RED_ZONE: usize = 16;
+const RED_ZONE: u8 = 0xfd;
+const POISON_FREED: u8 = 0xdd;
+
+const ALLOCATED: u64 = 0xa110_ca7e_a110_ca7e;
+const FREED: u64 = 0xf7ee_f7ee_f7ee_f7ee;
+
+const BACKTRACE_DEPTH: usize = 6;
+const NUM_OF_RECORDS_PER_BATCH: usize = 16;
+
+static TRACKER: Lazy<IrqSpinlock<Tracker>> =
+    Lazy::new(|| IrqSpinlock::new("heap debug", Tracker::new()));
+
+// Pass the returned value to `report_allocations_since` to dump only allocations made after this.
+pub fn checkpoint() -> u64 {
+    TRACKER.lock().next_seq
+}
+
+pub fn report_allocations() {
+    report_allocations_since(0);
+}
+
+// Logging may allocate memory, so records are copied out of the list in small batches and printed
+// without the lock.
+pub fn report_allocations_since(checkpoint: u64) {
+    let mut cursor = u64::MAX;
+    let mut num_of_allocations = 0;
+    let mut bytes = 0;
+
+    loop {
+        let (records, len) = match TRACKER.try_lock() {
+            Some(tracker) => tracker.records_before(cursor, checkpoint),
+            None => {
+                warn!("Heap debug: The list of allocations is locked.");
+                return;
+            }
+        };
+
+        for record in &records[..len] {
+            info!(
+                "Heap debug: #{} {} bytes at {:?}, allocated from {:x?}",
+                record.seq, record.size, record.addr, record.callers
+            );
+            num_of_allocations += 1;
+            bytes += record.size;
+        }
+
+        if len < NUM_OF_RECORDS_PER_BATCH {
+            break;
+        }
+        cursor = records[len - 1].seq;
+    }
+
+    info!(
+        "Heap debug: {} allocations, {} bytes in total.",
+        num_of_allocations, bytes
+    );
+}
+
+pub fn alloc(layout: Layout) -> Option<VirtAddr> {
+    let (padded, offset) = pad(layout)?;
+    let block = alloc_raw(padded)?;
+
+    let header: *mut Header = block.as_mut_ptr();
+    // Safety: The block is allocated and large enough to contain the header.
+    unsafe {
+        header.write(Header {
+            next: None,
+            prev: None,
+            size: layout.size(),
+            offset,
+            seq: 0,
+            callers: backtrace(),
+            state: ALLOCATED,
+        })
+    }
+
+    fill(
+        block + mem::size_of::<Header>(),
+        offset - mem::size_of::<Header>(),
+        RED_ZONE,
+    );
+    fill(block + offset + layout.size(), BYTES_RED_ZONE, RED_ZONE);
+
+    TRACKER.lock().push(NonNull::new(header).unwrap());
+
+    Some(block + offset)
+}
+
+pub fn dealloc(addr: VirtAddr, layout: Layout) {
+    let (padded, offset) = pad(layout).expect("Invalid layout");
+    let block = addr - offset;
+    let header: *mut Header = block.as_mut_ptr();
+
+    // Safety: `addr` was returned by `alloc` unless the caller has a bug. The checks below catch
+    // most of such bugs, but a double free is missed if the block is allocated again before that.
+    let callers = unsafe { (*header).check(addr, layout) };
+
+    let front = block + mem::size_of::<Header>();
+    let back = addr + layout.size();
+    if !filled(front, offset - mem::size_of::<Header>(), RED_ZONE)
+        || !filled(back, BYTES_RED_ZONE, RED_ZONE)
+    {
+        panic!(
+            "Heap debug: The red zone of {:?} ({} bytes, allocated from {:x?}) is broken.",
+            addr,
+            layout.size(),
+            callers
+        );
+    }
+
+    TRACKER.lock().remove(NonNull::new(header).unwrap());
+    // Safety: The header is no longer in the list.
+    unsafe { (*header).state = FREED }
+    fill(addr, layout.size(), POISON_FREED);
+
+    dealloc_raw(block, padded);
+}
+
+// Returns the layout with the header and the red zones, and the offset of the object in it.
+fn pad(layout: Layout) -> Option<(Layout, usize)> {
+    let align = cmp::max(layout.align(), mem::align_of::<Header>());
+    let offset = round_up(mem::size_of::<Header>() + BYTES_RED_ZONE, align);
+    let size = offset + layout.size() + BYTES_RED_ZONE;
+
+    Some((Layout::from_size_align(size, align).ok()?, offset))
+}
+
+fn round_up(n: usize, align: usize) -> usize {
+    (n + align - 1) & !(align - 1)
+}
+
+fn fill(start: VirtAddr, len: usize, byte: u8) {
+    // Safety: The area is a part of an allocated block.
+    unsafe { ptr::write_bytes(start.as_mut_ptr::<u8>(), byte, len) }
+}
+
+fn filled(start: VirtAddr, len: usize, byte: u8) -> bool {
+    // Safety: The area is a part of an allocated block.
+    unsafe { slice::from_raw_parts(start.as_ptr::<u8>(), len) }
+        .iter()
+        .all(|&b| b == byte)
+}
+
+// Frame pointers are kept because `eliminate-frame-pointer` is false in the target specification.
+// The first few entries are in the allocator itself.
+#[inline(never)]
+fn backtrace() -> [u64; BACKTRACE_DEPTH] {
+    let mut callers = [0; BACKTRACE_DEPTH];
+    let mut rbp: u64;
+
+    // Safety: Reading `rbp` has no side effect.
+    unsafe { asm!("mov {}, rbp", out(reg) rbp, options(nomem, nostack, preserves_flags)) }
+
+    // The chain is followed only within the stack of the current thread. It may end with a
+    // garbage value which bootx64 left.
+    let stack = thread::try_current_stack().unwrap_or(STACK_LOWER..STACK_BASE);
+
+    for caller in &mut callers {
+        if rbp < stack.start.as_u64() || rbp >= stack.end.as_u64() - 16 {
+            break;
+        }
+
+        let frame = rbp as *const u64;
+        // Safety: `rbp` points to a frame on the stack. The saved `rbp` is at `rbp`, and the
+        // return address is right above it.
+        unsafe {
+            *caller = frame.add(1).read();
+            rbp = frame.read();
+        }
+    }
+
+    callers
+}
+
+// The slab allocator overwrites the first word of a freed block with its free list pointer. Thus
+// `state` is placed at the end.
+#[repr(C)]
+struct Header {
+    next: Option<NonNull<Header>>,
+    prev: Option<NonNull<Header>>,
+    size: usize,
+    offset: usize,
+    seq: u64,
+    callers: [u64; BACKTRACE_DEPTH],
+    state: u64,
+}
+impl Header {
+    // Returns the backtrace of the allocation.
+    fn check(&self, addr: VirtAddr, layout: Layout) -> [u64; BACKTRACE_DEPTH] {
+        match self.state {
+            ALLOCATED => {}
+            FREED => panic!(
+                "Heap debug: Double free of {:?} ({} bytes, allocated from {:x?}).",
+                addr,
+                layout.size(),
+                self.callers
+            ),
+            _ => panic!(
+                "Heap debug: Freeing {:?}, which is not allocated or whose header is broken.",
+                addr
+            ),
+        }
+
+        assert_eq!(
+            self.size,
+            layout.size(),
+            "Heap debug: {:?} is freed with a wrong size.",
+            addr
+        );
+
+        self.callers
+    }
+}
+
+#[derive(Copy, Clone)]
+struct Record {
+    addr: VirtAddr,
+    size: usize,
+    seq: u64,
+    callers: [u64; BACKTRACE_DEPTH],
+}
+
+// The newest allocation comes first. Sequence numbers are given here so that they decrease along
+// the list.
+struct Tracker {
+    head: Option<NonNull<Header>>,
+    next_seq: u64,
+}
+impl Tracker {
+    fn new() -> Self {
+        Self {
+            head: None,
+            next_seq: 1,
+        }
+    }
+
+    fn push(&mut self, mut header: NonNull<Header>) {
+        // Safety: Headers in the list are in allocated blocks, and only touched while the lock is
+        // held.
+        unsafe {
+            header.as_mut().seq = self.next_seq;
+            header.as_mut().next = self.head;
+            header.as_mut().prev = None;
+            if let Some(mut head) = self.head {
+                head.as_mut().prev = Some(header);
+            }
+        }
+        self.head = Some(header);
+        self.next_seq += 1;
+    }
+
+    fn remove(&mut self, header: NonNull<Header>) {
+        // Safety: Same as `push`.
+        unsafe {
+            let Header { next, prev, .. } = *header.as_ptr();
+
+            match prev {
+                Some(mut prev) => prev.as_mut().next = next,
+                None => self.head = next,
+            }
+            if let Some(mut next) = next {
+                next.as_mut().prev = prev;
+            }
+        }
+    }
+
+    // Records whose sequence number is in `checkpoint..cursor`, the newest first.
+    fn records_before(
+        &self,
+        cursor: u64,
+        checkpoint: u64,
+    ) -> ([Record; NUM_OF_RECORDS_PER_BATCH], usize) {
+        let mut records = [Record {
+            addr: VirtAddr::zero(),
+            size: 0,
+            seq: 0,
+            callers: [0; BACKTRACE_DEPTH],
+        }; NUM_OF_RECORDS_PER_BATCH];
+        let mut len = 0;
+
+        let mut header = self.head;
+        while let Some(h) = header {
+            // Safety: Same as `push`.
+            let h = unsafe { h.as_ref() };
+
+            if h.seq < checkpoint || len == NUM_OF_RECORDS_PER_BATCH {
+                break;
+            }
+
+            if h.seq < cursor {
+                records[len] = Record {
+                    addr: VirtAddr::from_ptr(h) + h.offset,
+                    size: h.size,
+                    seq: h.seq,
+                    callers: h.callers,
+                };
+                len += 1;
+            }
+
+            header = h.next;
+        }
+
+        (records, len)
+    }
+}
+// `Tracker` contains raw pointers to headers. They are only touched while the lock is held.
+unsafe impl Send for Tracker {}
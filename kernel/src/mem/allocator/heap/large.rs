@@ -0,0 +1,161 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use {
+    super::super::{
+        super::{
+            paging::pml4::PML4,
+            vma::{self, Area},
+        },
+        phys::{FrameManager, FRAME_MANAGER},
+        virt::{self, Window},
+    },
+    conquer_once::spin::Lazy,
+    core::{cmp, convert::TryFrom, ptr::NonNull},
+    os_units::{Bytes, NumOfPages},
+    spinning_top::Spinlock,
+    x86_64::{
+        instructions::interrupts,
+        structures::paging::{
+            FrameDeallocator, Mapper, Page, PageSize, PageTableFlags, RecursivePageTable, Size4KiB,
+        },
+        VirtAddr,
+    },
+};
+
+// Regions which could not be freed because `FrameManager` or `PML4` was locked.
+static DEFERRED: Lazy<Spinlock<DeferredList>> = Lazy::new(|| Spinlock::new(DeferredList(None)));
+
+// No page is mapped here. The page fault handler maps each page when it is touched first.
+// Callers must fall back to the other way if this function fails.
+//
+// For an alignment larger than a page, extra pages are allocated, and the ones around the aligned
+// range are freed again.
+pub fn alloc(bytes: Bytes, align: usize) -> Option<VirtAddr> {
+    free_deferred_regions();
+
+    let num_of_pages = bytes.as_num_of_pages::<Size4KiB>();
+    let extra = cmp::max(align / usize::try_from(Size4KiB::SIZE).unwrap(), 1) - 1;
+
+    let whole = virt::alloc(
+        Window::Vmalloc,
+        NumOfPages::new(num_of_pages.as_usize() + extra),
+    )?;
+    let virt = whole.align_up(u64::try_from(align).unwrap());
+
+    let head = usize::try_from((virt - whole) / Size4KiB::SIZE).unwrap();
+    free_virt(whole, NumOfPages::new(head));
+    free_virt(
+        virt + num_of_pages.as_bytes().as_usize(),
+        NumOfPages::new(extra - head),
+    );
+
+    let flags = PageTableFlags::PRESENT | PageTableFlags::WRITABLE | PageTableFlags::NO_EXECUTE;
+    let area = Area::anonymous(virt, num_of_pages, flags, "large allocation");
+
+    if interrupts::without_interrupts(|| vma::KERNEL.lock().add(area)).is_err() {
+        free_virt(virt, num_of_pages);
+        return None;
+    }
+
+    Some(virt)
+}
+
+pub fn dealloc(addr: VirtAddr, bytes: Bytes) {
+    let num_of_pages = bytes.as_num_of_pages::<Size4KiB>();
+
+    if !free_region_if_possible(addr, num_of_pages) {
+        DEFERRED.lock().push(addr, num_of_pages);
+    }
+}
+
+fn free_deferred_regions() {
+    loop {
+        let region = DEFERRED.lock().pop();
+        let (addr, num_of_pages) = match region {
+            Some(region) => region,
+            None => return,
+        };
+
+        if !free_region_if_possible(addr, num_of_pages) {
+            DEFERRED.lock().push(addr, num_of_pages);
+            return;
+        }
+    }
+}
+
+fn free_region_if_possible(addr: VirtAddr, num_of_pages: NumOfPages<Size4KiB>) -> bool {
+    let mut frame_manager = match FRAME_MANAGER.try_lock() {
+        Some(frame_manager) => frame_manager,
+        None => return false,
+    };
+    let mut pml4 = match PML4.try_lock() {
+        Some(pml4) => pml4,
+        None => return false,
+    };
+
+    unmap_pages(&mut pml4, &mut frame_manager, addr, num_of_pages);
+    interrupts::without_interrupts(|| vma::KERNEL.lock().remove(addr));
+    free_virt(addr, num_of_pages);
+    true
+}
+
+fn free_virt(addr: VirtAddr, num_of_pages: NumOfPages<Size4KiB>) {
+    if num_of_pages.as_usize() == 0 {
+        return;
+    }
+
+    if let Err(e) = virt::free(addr, num_of_pages) {
+        warn!("Leaking the virtual addresses at {:?}: {:?}", addr, e);
+    }
+}
+
+fn unmap_pages(
+    pml4: &mut RecursivePageTable,
+    frame_manager: &mut FrameManager,
+    start: VirtAddr,
+    num_of_pages: NumOfPages<Size4KiB>,
+) {
+    // Pages which were never touched are not mapped.
+    for i in 0..num_of_pages.as_usize() {
+        if let Ok((frame, flush)) = pml4.unmap(nth_page(start, i)) {
+            flush.flush();
+            unsafe { frame_manager.deallocate_frame(frame) }
+        }
+    }
+}
+
+fn nth_page(start: VirtAddr, n: usize) -> Page<Size4KiB> {
+    Page::from_start_address(start + Size4KiB::SIZE * u64::try_from(n).unwrap()).unwrap()
+}
+
+// The list is stored in the first page of each region, which is still mapped.
+struct DeferredList(Option<NonNull<DeferredRegion>>);
+impl DeferredList {
+    fn push(&mut self, addr: VirtAddr, num_of_pages: NumOfPages<Size4KiB>) {
+        let region: *mut DeferredRegion = addr.as_mut_ptr();
+
+        // Safety: This operation is safe because the region is mapped and no longer used.
+        unsafe {
+            region.write(DeferredRegion {
+                next: self.0,
+                num_of_pages,
+            })
+        }
+        self.0 = NonNull::new(region);
+    }
+
+    fn pop(&mut self) -> Option<(VirtAddr, NumOfPages<Size4KiB>)> {
+        let region = self.0?;
+
+        // Safety: This operation is safe because `region` was written by `push`.
+        let region_ref = unsafe { region.as_ref() };
+        self.0 = region_ref.next;
+        Some((VirtAddr::from_ptr(region.as_ptr()), region_ref.num_of_pages))
+    }
+}
+unsafe impl Send for DeferredList {}
+
+struct DeferredRegion {
+    next: Option<NonNull<DeferredRegion>>,
+    num_of_pages: NumOfPages<Size4KiB>,
+}
@@ -0,0 +1,281 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// WORKAROUND: https://stackoverflow.com/questions/63933070/clippy-says-too-many-arguments-to-static-declaration
+#![allow(clippy::too_many_arguments)]
+
+pub mod debug;
+mod large;
+mod page_source;
+pub mod slab;
+
+use {
+    super::super::{
+        fault,
+        paging::pml4::PML4,
+        vma::{self, Area},
+    },
+    crate::lock::IrqSpinlock,
+    common::constant::{BYTES_KERNEL_HEAP, BYTES_KERNEL_HEAP_INIT, KERNEL_HEAP_ADDR},
+    conquer_once::spin::Lazy,
+    core::{
+        alloc::{GlobalAlloc, Layout},
+        convert::TryFrom,
+        fmt, ptr,
+    },
+    os_units::Bytes,
+    page_source::PageSource,
+    slab::SizeClass,
+    uefi::table::boot,
+    x86_64::{
+        structures::paging::{
+            FrameAllocator, Mapper, Page, PageSize, PageTableFlags, PhysFrame, Size4KiB,
+        },
+        PhysAddr, VirtAddr,
+    },
+};
+
+#[global_allocator]
+static ALLOCATOR: Allocator = Allocator;
+
+// Holding this with interrupts disabled lets code which disables interrupts allocate memory without
+// waiting for a preempted thread.
+static HEAP: Lazy<IrqSpinlock<Heap>> = Lazy::new(|| IrqSpinlock::new("heap", Heap::new()));
+
+// Using UEFI's `allocate_pages` doesn't work for allocating larger memory. It returns out of
+// resrouces.
+//
+// Only a small area is mapped here. The rest of the heap is mapped by the page fault handler when
+// it is touched first.
+pub fn init(mem_map: &mut [boot::MemoryDescriptor]) {
+    let mut temp_phys_allocator = TemporaryFrameAllocator(mem_map);
+    for i in 0..BYTES_KERNEL_HEAP_INIT
+        .as_num_of_pages::<Size4KiB>()
+        .as_usize()
+    {
+        let frame = temp_phys_allocator
+            .allocate_frame()
+            .expect("OOM during initializing heap area!");
+
+        let page =
+            Page::<Size4KiB>::containing_address(KERNEL_HEAP_ADDR + Size4KiB::SIZE * i as u64);
+        unsafe {
+            PML4.lock()
+                .map_to(
+                    page,
+                    frame,
+                    PageTableFlags::PRESENT | PageTableFlags::WRITABLE | PageTableFlags::NO_EXECUTE,
+                    &mut temp_phys_allocator,
+                )
+                .unwrap()
+                .flush();
+        };
+    }
+
+    register_lazy_area();
+
+    HEAP.lock().init(KERNEL_HEAP_ADDR, BYTES_KERNEL_HEAP);
+}
+
+pub fn stats() -> Stats {
+    HEAP.lock().stats
+}
+
+struct Allocator;
+unsafe impl GlobalAlloc for Allocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = if cfg!(feature = "heap_debug") {
+            debug::alloc(layout)
+        } else {
+            alloc_raw(layout)
+        };
+
+        fault::refill_frame_reserve();
+
+        ptr.map_or(ptr::null_mut(), VirtAddr::as_mut_ptr::<u8>)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let addr = VirtAddr::from_ptr(ptr);
+
+        if cfg!(feature = "heap_debug") {
+            debug::dealloc(addr, layout)
+        } else {
+            dealloc_raw(addr, layout)
+        }
+    }
+}
+
+fn alloc_raw(layout: Layout) -> Option<VirtAddr> {
+    match SizeClass::for_layout(layout) {
+        Some(class) => HEAP.lock().alloc_small(class),
+        None => alloc_large(layout),
+    }
+}
+
+fn dealloc_raw(addr: VirtAddr, layout: Layout) {
+    match SizeClass::for_layout(layout) {
+        Some(class) => HEAP.lock().dealloc_small(class, addr),
+        None => dealloc_large(addr, layout),
+    }
+}
+
+fn alloc_large(layout: Layout) -> Option<VirtAddr> {
+    let bytes = Bytes::new(layout.size());
+    let addr = large::alloc(bytes, layout.align()).or_else(|| {
+        // The contiguous pages are aligned only to a page.
+        if layout.align() > usize::try_from(Size4KiB::SIZE).unwrap() {
+            None
+        } else {
+            HEAP.lock().alloc_contiguous(bytes)
+        }
+    })?;
+
+    HEAP.lock().stats.record_alloc(bytes);
+    Some(addr)
+}
+
+fn dealloc_large(addr: VirtAddr, layout: Layout) {
+    let bytes = Bytes::new(layout.size());
+
+    if PageSource::contains(addr) {
+        HEAP.lock().dealloc_contiguous(addr, bytes);
+    } else {
+        large::dealloc(addr, bytes);
+    }
+
+    HEAP.lock().stats.record_dealloc(bytes);
+}
+
+fn register_lazy_area() {
+    let start = KERNEL_HEAP_ADDR + BYTES_KERNEL_HEAP_INIT.as_usize();
+    let bytes = Bytes::new(BYTES_KERNEL_HEAP.as_usize() - BYTES_KERNEL_HEAP_INIT.as_usize());
+    let flags = PageTableFlags::PRESENT | PageTableFlags::WRITABLE | PageTableFlags::NO_EXECUTE;
+
+    vma::KERNEL
+        .lock()
+        .add(Area::anonymous(
+            start,
+            bytes.as_num_of_pages(),
+            flags,
+            "kernel heap",
+        ))
+        .expect("Failed to register the kernel heap area.");
+}
+
+struct Heap {
+    page_source: PageSource,
+    caches: [slab::Cache; slab::NUM_OF_CLASSES],
+    stats: Stats,
+}
+impl Heap {
+    fn new() -> Self {
+        Self {
+            page_source: PageSource::new(),
+            caches: [slab::Cache::new(); slab::NUM_OF_CLASSES],
+            stats: Stats::default(),
+        }
+    }
+
+    fn init(&mut self, start: VirtAddr, bytes: Bytes) {
+        self.page_source.init(start, bytes);
+    }
+
+    fn alloc_small(&mut self, class: SizeClass) -> Option<VirtAddr> {
+        if self.caches[class.index()].empty() {
+            let page = self.page_source.alloc_page()?;
+            self.caches[class.index()].add_slab(page, class.size());
+            self.stats.bytes_of_pages += usize::try_from(Size4KiB::SIZE).unwrap();
+        }
+
+        let addr = self.caches[class.index()].pop()?;
+        self.stats.record_alloc(Bytes::new(class.size()));
+        self.stats.objects_in_use[class.index()] += 1;
+        Some(addr)
+    }
+
+    fn dealloc_small(&mut self, class: SizeClass, addr: VirtAddr) {
+        self.caches[class.index()].push(addr);
+        self.stats.record_dealloc(Bytes::new(class.size()));
+        self.stats.objects_in_use[class.index()] -= 1;
+    }
+
+    fn alloc_contiguous(&mut self, bytes: Bytes) -> Option<VirtAddr> {
+        let num_of_pages = bytes.as_num_of_pages::<Size4KiB>();
+        let addr = self.page_source.alloc_contiguous(num_of_pages)?;
+
+        self.stats.bytes_of_pages += num_of_pages.as_bytes().as_usize();
+        Some(addr)
+    }
+
+    fn dealloc_contiguous(&mut self, addr: VirtAddr, bytes: Bytes) {
+        let num_of_pages = bytes.as_num_of_pages::<Size4KiB>();
+        for i in 0..num_of_pages.as_usize() {
+            self.page_source
+                .free_page(addr + Size4KiB::SIZE * u64::try_from(i).unwrap());
+        }
+
+        self.stats.bytes_of_pages -= num_of_pages.as_bytes().as_usize();
+    }
+}
+// `Heap` contains raw pointers to free objects. They are only touched while the lock is held.
+unsafe impl Send for Heap {}
+
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Stats {
+    pub bytes_of_pages: usize,
+    pub bytes_in_use: usize,
+    pub num_of_allocations: usize,
+    pub num_of_deallocations: usize,
+    pub objects_in_use: [usize; slab::NUM_OF_CLASSES],
+}
+impl Stats {
+    fn record_alloc(&mut self, bytes: Bytes) {
+        self.bytes_in_use += bytes.as_usize();
+        self.num_of_allocations += 1;
+    }
+
+    fn record_dealloc(&mut self, bytes: Bytes) {
+        self.bytes_in_use -= bytes.as_usize();
+        self.num_of_deallocations += 1;
+    }
+}
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} KiB in use, {} KiB of pages, {} allocs, {} frees",
+            self.bytes_in_use / 1024,
+            self.bytes_of_pages / 1024,
+            self.num_of_allocations,
+            self.num_of_deallocations
+        )
+    }
+}
+
+struct TemporaryFrameAllocator<'a>(&'a mut [boot::MemoryDescriptor]);
+unsafe impl<'a> FrameAllocator<Size4KiB> for TemporaryFrameAllocator<'a> {
+    fn allocate_frame(&mut self) -> Option<PhysFrame<Size4KiB>> {
+        for desc in self.0.iter_mut() {
+            if desc.ty == boot::MemoryType::CONVENTIONAL && desc.page_count > 0 {
+                let frame =
+                    PhysFrame::<Size4KiB>::from_start_address(PhysAddr::new(desc.phys_start))
+                        .unwrap();
+                desc.phys_start += Size4KiB::SIZE;
+                desc.page_count -= 1;
+
+                return Some(frame);
+            }
+        }
+
+        None
+    }
+}
+
+#[alloc_error_handler]
+fn alloc_error(layout: Layout) -> ! {
+    if cfg!(feature = "heap_debug") {
+        debug::report_allocations();
+    }
+
+    panic!("Allocation failed! {:?}", layout);
+}
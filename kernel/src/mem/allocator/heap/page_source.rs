@@ -0,0 +1,72 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use {
+    common::constant::{BYTES_KERNEL_HEAP, KERNEL_HEAP_ADDR},
+    core::{convert::TryFrom, ptr::NonNull},
+    os_units::{Bytes, NumOfPages},
+    x86_64::{structures::paging::Size4KiB, VirtAddr},
+};
+
+pub struct PageSource {
+    next: VirtAddr,
+    end: VirtAddr,
+    free_pages: Option<NonNull<FreePage>>,
+}
+impl PageSource {
+    pub fn new() -> Self {
+        Self {
+            next: VirtAddr::zero(),
+            end: VirtAddr::zero(),
+            free_pages: None,
+        }
+    }
+
+    pub fn contains(addr: VirtAddr) -> bool {
+        KERNEL_HEAP_ADDR <= addr && addr < KERNEL_HEAP_ADDR + BYTES_KERNEL_HEAP.as_usize()
+    }
+
+    pub fn init(&mut self, start: VirtAddr, bytes: Bytes) {
+        self.next = start;
+        self.end = start + bytes.as_usize();
+    }
+
+    pub fn alloc_page(&mut self) -> Option<VirtAddr> {
+        self.pop_free_page()
+            .or_else(|| self.alloc_contiguous(NumOfPages::new(1)))
+    }
+
+    pub fn alloc_contiguous(&mut self, num_of_pages: NumOfPages<Size4KiB>) -> Option<VirtAddr> {
+        let bytes = num_of_pages.as_bytes().as_usize();
+        if self.next + bytes > self.end {
+            return None;
+        }
+
+        let addr = self.next;
+        self.next += u64::try_from(bytes).unwrap();
+        Some(addr)
+    }
+
+    pub fn free_page(&mut self, addr: VirtAddr) {
+        let page: *mut FreePage = addr.as_mut_ptr();
+
+        // Safety: This operation is safe because the page is mapped and no one uses it.
+        unsafe {
+            page.write(FreePage {
+                next: self.free_pages,
+            })
+        }
+        self.free_pages = NonNull::new(page);
+    }
+
+    fn pop_free_page(&mut self) -> Option<VirtAddr> {
+        let page = self.free_pages?;
+
+        // Safety: This operation is safe because `page` is a free page in the heap area.
+        self.free_pages = unsafe { page.as_ref().next };
+        Some(VirtAddr::from_ptr(page.as_ptr()))
+    }
+}
+
+struct FreePage {
+    next: Option<NonNull<FreePage>>,
+}
@@ -0,0 +1,74 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use {
+    core::{alloc::Layout, cmp, convert::TryFrom, ptr::NonNull},
+    x86_64::{
+        structures::paging::{PageSize, Size4KiB},
+        VirtAddr,
+    },
+};
+
+pub const NUM_OF_CLASSES: usize = 9;
+const SIZES: [usize; NUM_OF_CLASSES] = [8, 16, 32, 64, 128, 256, 512, 1024, 2048];
+
+#[derive(Copy, Clone, Debug)]
+pub struct SizeClass(usize);
+impl SizeClass {
+    // Every object is aligned to its size because a slab is one page and all sizes are powers of
+    // two. Therefore taking the maximum of the size and the alignment is enough.
+    pub fn for_layout(layout: Layout) -> Option<Self> {
+        let size = cmp::max(layout.size(), layout.align());
+        SIZES.iter().position(|&s| s >= size).map(Self)
+    }
+
+    pub fn size(self) -> usize {
+        SIZES[self.0]
+    }
+
+    pub fn index(self) -> usize {
+        self.0
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct Cache {
+    head: Option<NonNull<FreeObject>>,
+}
+impl Cache {
+    pub const fn new() -> Self {
+        Self { head: None }
+    }
+
+    pub fn empty(&self) -> bool {
+        self.head.is_none()
+    }
+
+    pub fn pop(&mut self) -> Option<VirtAddr> {
+        let object = self.head?;
+
+        // Safety: This operation is safe because `object` is a free object in a mapped slab.
+        self.head = unsafe { object.as_ref().next };
+        Some(VirtAddr::from_ptr(object.as_ptr()))
+    }
+
+    pub fn push(&mut self, addr: VirtAddr) {
+        let object: *mut FreeObject = addr.as_mut_ptr();
+
+        // Safety: This operation is safe because the object is not used by anyone and is large
+        // enough to contain `FreeObject`.
+        unsafe { object.write(FreeObject { next: self.head }) }
+        self.head = NonNull::new(object);
+    }
+
+    pub fn add_slab(&mut self, page: VirtAddr, object_size: usize) {
+        let num_of_objects = usize::try_from(Size4KiB::SIZE).unwrap() / object_size;
+
+        for i in (0..num_of_objects).rev() {
+            self.push(page + object_size * i);
+        }
+    }
+}
+
+struct FreeObject {
+    next: Option<NonNull<FreeObject>>,
+}
@@ -1,7 +1,8 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use {
-    super::{super::paging::pml4::PML4, phys::FRAME_MANAGER, virt},
+    super::phys::{Tag, FRAME_MANAGER},
+    crate::mem::{phys_to_virt, virt_to_phys},
     core::{
         convert::TryFrom,
         marker::PhantomData,
@@ -10,13 +11,7 @@ use {
         ptr, slice,
     },
     os_units::{Bytes, NumOfPages},
-    x86_64::{
-        structures::paging::{
-            FrameDeallocator, Mapper, MapperAllSizes, Page, PageSize, PageTableFlags, PhysFrame,
-            Size4KiB,
-        },
-        PhysAddr, VirtAddr,
-    },
+    x86_64::{structures::paging::Size4KiB, PhysAddr, VirtAddr},
 };
 
 pub struct PageBox<T: ?Sized> {
@@ -103,7 +98,7 @@ where
 }
 impl<T: ?Sized> PageBox<T> {
     pub fn phys_addr(&self) -> PhysAddr {
-        PML4.lock().translate_addr(self.virt).unwrap()
+        virt_to_phys(self.virt).unwrap()
     }
 
     fn new_zeroed_from_bytes(bytes: Bytes) -> Self {
@@ -124,46 +119,18 @@ impl<T: ?Sized> PageBox<T> {
         }
     }
 
+    // Frames are reached through the direct map, so no page needs to be mapped here.
     fn allocate_pages(num_of_pages: NumOfPages<Size4KiB>) -> VirtAddr {
-        let virt_addr =
-            virt::search_free_addr(num_of_pages).expect("OOM during creating `PageBox`");
-
         let phys_addr = FRAME_MANAGER
             .lock()
-            .alloc(num_of_pages)
+            .alloc(num_of_pages, Tag::PageBox)
             .expect("OOM during creating `PageBox");
 
-        for i in 0..u64::try_from(num_of_pages.as_usize()).unwrap() {
-            let page =
-                Page::<Size4KiB>::from_start_address(virt_addr + Size4KiB::SIZE * i).unwrap();
-            let frame = PhysFrame::from_start_address(phys_addr + Size4KiB::SIZE * i).unwrap();
-
-            unsafe {
-                PML4.lock()
-                    .map_to(
-                        page,
-                        frame,
-                        PageTableFlags::PRESENT | PageTableFlags::WRITABLE,
-                        &mut *FRAME_MANAGER.lock(),
-                    )
-                    .unwrap()
-                    .flush()
-            }
-        }
-
-        virt_addr
+        phys_to_virt(phys_addr)
     }
 }
 impl<T: ?Sized> Drop for PageBox<T> {
     fn drop(&mut self) {
-        let num_of_pages = self.bytes.as_num_of_pages::<Size4KiB>();
-
-        for i in 0..u64::try_from(num_of_pages.as_usize()).unwrap() {
-            let page = Page::from_start_address(self.virt + Size4KiB::SIZE * i).unwrap();
-
-            let (frame, flush) = PML4.lock().unmap(page).unwrap();
-            flush.flush();
-            unsafe { FRAME_MANAGER.lock().deallocate_frame(frame) }
-        }
+        FRAME_MANAGER.lock().free(self.phys_addr());
     }
 }
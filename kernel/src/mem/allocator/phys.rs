@@ -1,10 +1,9 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use {
-    crate::mem::paging,
     alloc::collections::vec_deque::VecDeque,
     conquer_once::spin::Lazy,
-    core::convert::TryFrom,
+    core::{cmp, convert::TryFrom, mem},
     os_units::NumOfPages,
     spinning_top::Spinlock,
     uefi::table::boot::{self, MemoryType},
@@ -14,6 +13,8 @@ use {
     },
 };
 
+pub const NUM_OF_TAGS: usize = 5;
+
 pub static FRAME_MANAGER: Lazy<Spinlock<FrameManager>> =
     Lazy::new(|| Spinlock::new(FrameManager(VecDeque::new())));
 
@@ -21,30 +22,97 @@ pub struct FrameManager(VecDeque<Frames>);
 impl FrameManager {
     pub fn init(mem_map: &[boot::MemoryDescriptor]) {
         FRAME_MANAGER.lock().init_static(mem_map);
-        paging::mark_pages_as_unused();
     }
 
-    pub fn alloc(&mut self, num_of_pages: NumOfPages<Size4KiB>) -> Option<PhysAddr> {
+    pub fn alloc(&mut self, num_of_pages: NumOfPages<Size4KiB>, tag: Tag) -> Option<PhysAddr> {
+        let frames = self.alloc_where(num_of_pages, |_| true)?;
+        frames.tag = Some(tag);
+        Some(frames.start)
+    }
+
+    // Unlike `alloc`, this never grows the list, and so never uses the heap. The page fault
+    // handler uses this because the faulting code may hold the heap lock.
+    pub fn alloc_frame_without_heap(&mut self, tag: Tag) -> Option<PhysAddr> {
+        let spare = self.0.capacity() - self.0.len();
+
+        // Splitting a node of `2^n` pages down to one page adds `n` nodes.
+        let frames = self.alloc_where(NumOfPages::new(1), |frames| {
+            usize::try_from(frames.num_of_pages.as_usize().trailing_zeros()).unwrap() <= spare
+        })?;
+        frames.tag = Some(tag);
+        Some(frames.start)
+    }
+
+    // The returned frames are aligned to their size rounded up to a power of two, so they never
+    // cross a boundary of that size. They also end at or below `limit` if it is given.
+    pub fn alloc_for_dma(
+        &mut self,
+        num_of_pages: NumOfPages<Size4KiB>,
+        limit: Option<PhysAddr>,
+    ) -> Option<PhysAddr> {
+        let bytes =
+            num_of_pages.as_usize().next_power_of_two() * usize::try_from(Size4KiB::SIZE).unwrap();
+        let bytes = u64::try_from(bytes).unwrap();
+
+        let frames = self.alloc_where(num_of_pages, |frames| {
+            frames.start.is_aligned(bytes)
+                && limit.map_or(true, |limit| frames.start + bytes <= limit)
+        })?;
+        frames.tag = Some(Tag::Dma);
+        Some(frames.start)
+    }
+
+    pub fn stats(&self) -> Stats {
+        let mut stats = Stats::default();
+
+        for frames in &self.0 {
+            let bytes = frames.num_of_pages.as_bytes().as_usize();
+            stats.bytes_total += bytes;
+
+            match frames.tag {
+                Some(tag) => stats.bytes_per_tag[tag as usize] += bytes,
+                None => {
+                    stats.bytes_free += bytes;
+                    stats.num_of_free_blocks += 1;
+                    stats.bytes_largest_free_block =
+                        cmp::max(stats.bytes_largest_free_block, bytes);
+                }
+            }
+        }
+
+        stats.bytes_of_list = self.0.len() * mem::size_of::<Frames>();
+        stats
+    }
+
+    fn alloc_where<T>(&mut self, num_of_pages: NumOfPages<Size4KiB>, f: T) -> Option<&mut Frames>
+    where
+        T: Fn(&Frames) -> bool,
+    {
         let num_of_pages = NumOfPages::new(num_of_pages.as_usize().next_power_of_two());
 
         for i in 0..self.0.len() {
-            if self.0[i].num_of_pages >= num_of_pages && self.0[i].available {
+            if self.0[i].num_of_pages >= num_of_pages && self.0[i].available && f(&self.0[i]) {
                 self.split_node(i, num_of_pages);
 
-                let addr = self.0[i].start;
                 self.0[i].available = false;
-
-                return Some(addr);
+                return Some(&mut self.0[i]);
             }
         }
 
         None
     }
 
+    // For memory which becomes free after booting.
+    pub fn add(&mut self, start: PhysAddr, num_of_pages: NumOfPages<Size4KiB>) {
+        self.add_range(start, num_of_pages.as_usize());
+        self.merge_all_nodes();
+    }
+
     pub fn free(&mut self, addr: PhysAddr) {
         for i in 0..self.0.len() {
             if self.0[i].start == addr && !self.0[i].available {
                 self.0[i].available = true;
+                self.0[i].tag = None;
                 return self.merge_all_nodes();
             }
         }
@@ -60,15 +128,40 @@ impl FrameManager {
         self.merge_all_nodes();
     }
 
+    // Registering every page one by one makes booting slow and needs a lot of memory for the
+    // list. Register the largest aligned chunks instead.
     fn init_for_descriptor(&mut self, descriptor: &boot::MemoryDescriptor) {
-        for i in 0..descriptor.page_count {
-            let addr = PhysAddr::new(descriptor.phys_start + Size4KiB::SIZE * i);
-            let frames = Frames::new(addr, NumOfPages::new(1), true);
+        self.add_range(
+            PhysAddr::new(descriptor.phys_start),
+            usize::try_from(descriptor.page_count).unwrap(),
+        );
+    }
 
-            self.0.push_back(frames);
+    // Nodes are kept sorted by address. Otherwise adjacent nodes are not merged.
+    fn add_range(&mut self, mut addr: PhysAddr, mut rest: usize) {
+        while rest > 0 {
+            let num_of_pages = Self::largest_aligned_chunk(addr, rest);
+            let i = self
+                .0
+                .iter()
+                .position(|frames| frames.start > addr)
+                .unwrap_or_else(|| self.0.len());
+            self.0
+                .insert(i, Frames::new(addr, NumOfPages::new(num_of_pages), true));
+
+            addr += num_of_pages * usize::try_from(Size4KiB::SIZE).unwrap();
+            rest -= num_of_pages;
         }
     }
 
+    fn largest_aligned_chunk(addr: PhysAddr, max_pages: usize) -> usize {
+        let frame_index = addr.as_u64() / Size4KiB::SIZE;
+        let alignment = 1_usize << frame_index.trailing_zeros().min(63);
+        let fits = (max_pages + 1).next_power_of_two() / 2;
+
+        cmp::min(alignment, fits)
+    }
+
     fn split_node(&mut self, i: usize, num_of_pages: NumOfPages<Size4KiB>) {
         if self.0[i].available {
             while self.0[i].num_of_pages > num_of_pages {
@@ -132,9 +225,10 @@ impl FrameManager {
         ty == MemoryType::CONVENTIONAL
     }
 }
+// `Mapper` allocates page tables through this trait.
 unsafe impl FrameAllocator<Size4KiB> for FrameManager {
     fn allocate_frame(&mut self) -> Option<PhysFrame<Size4KiB>> {
-        let addr = self.alloc(NumOfPages::new(1))?;
+        let addr = self.alloc(NumOfPages::new(1), Tag::PageTables)?;
         Some(PhysFrame::from_start_address(addr).unwrap())
     }
 }
@@ -145,11 +239,54 @@ impl FrameDeallocator<Size4KiB> for FrameManager {
     }
 }
 
+// What allocated frames are used for.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Tag {
+    // Including page tables created by the page fault handler.
+    Heap,
+    PageTables,
+    Dma,
+    PageBox,
+    // Pages and page tables of user processes.
+    User,
+}
+impl Tag {
+    pub const ALL: [Self; NUM_OF_TAGS] = [
+        Self::Heap,
+        Self::PageTables,
+        Self::Dma,
+        Self::PageBox,
+        Self::User,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Heap => "heap",
+            Self::PageTables => "page tables",
+            Self::Dma => "DMA buffers",
+            Self::PageBox => "PageBox",
+            Self::User => "user processes",
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Stats {
+    pub bytes_total: usize,
+    pub bytes_free: usize,
+    pub bytes_per_tag: [usize; NUM_OF_TAGS],
+    pub num_of_free_blocks: usize,
+    pub bytes_largest_free_block: usize,
+    // The list of `FrameManager` itself is on the heap.
+    pub bytes_of_list: usize,
+}
+
 #[derive(Debug)]
 struct Frames {
     start: PhysAddr,
     num_of_pages: NumOfPages<Size4KiB>,
     available: bool,
+    tag: Option<Tag>,
 }
 impl Frames {
     fn new(start: PhysAddr, num_of_pages: NumOfPages<Size4KiB>, available: bool) -> Self {
@@ -157,6 +294,7 @@ impl Frames {
             start,
             num_of_pages,
             available,
+            tag: None,
         }
     }
 }
@@ -1,42 +1,202 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use {
-    crate::mem::paging::pml4::PML4,
-    common::constant::BYTES_AVAILABLE_RAM,
+    common::constant::{BYTES_MMIO_AREA, BYTES_VMALLOC_AREA, MMIO_ADDR, VMALLOC_ADDR},
+    conquer_once::spin::Lazy,
     core::convert::TryFrom,
-    os_units::NumOfPages,
+    os_units::{Bytes, NumOfPages},
+    spinning_top::Spinlock,
     x86_64::{
-        structures::paging::{MapperAllSizes, PageSize, Size4KiB},
+        structures::paging::{PageSize, Size4KiB},
         VirtAddr,
     },
 };
 
-pub fn search_free_addr(num_pages: NumOfPages<Size4KiB>) -> Option<VirtAddr> {
-    let mut cnt = 0;
-    let mut start = None;
-    for addr in
-        (0..BYTES_AVAILABLE_RAM.as_usize()).step_by(usize::try_from(Size4KiB::SIZE).unwrap())
-    {
-        let addr = VirtAddr::new(addr as _);
-        if available(addr) {
-            if start.is_none() {
-                start = Some(addr);
-            }
-
-            cnt += 1;
-
-            if cnt >= num_pages.as_usize() {
-                return start;
-            }
+// The heap is backed by this allocator. Do not use any heap-allocated collections here.
+const MAX_FREE_RANGES: usize = 256;
+
+static VIRT_MANAGER: Lazy<Spinlock<VirtManager>> = Lazy::new(|| Spinlock::new(VirtManager::new()));
+
+pub fn alloc(window: Window, num_of_pages: NumOfPages<Size4KiB>) -> Option<VirtAddr> {
+    VIRT_MANAGER.lock().alloc(window, num_of_pages)
+}
+
+// Fails if the range is next to no free range and the list of free ranges is full. The range
+// stays allocated then.
+pub fn free(addr: VirtAddr, num_of_pages: NumOfPages<Size4KiB>) -> Result<(), Error> {
+    let window = Window::containing(addr)
+        .unwrap_or_else(|| panic!("{:?} is not in any kernel virtual address window.", addr));
+
+    VIRT_MANAGER.lock().free(window, addr, num_of_pages)
+}
+
+#[derive(Debug)]
+pub enum Error {
+    TooManyFreeRanges,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Window {
+    Mmio,
+    Vmalloc,
+}
+impl Window {
+    const ALL: [Window; 2] = [Window::Mmio, Window::Vmalloc];
+
+    fn containing(addr: VirtAddr) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|w| w.start() <= addr && addr < w.start() + w.bytes().as_usize())
+    }
+
+    fn start(self) -> VirtAddr {
+        match self {
+            Self::Mmio => MMIO_ADDR,
+            Self::Vmalloc => VMALLOC_ADDR,
+        }
+    }
+
+    fn bytes(self) -> Bytes {
+        match self {
+            Self::Mmio => BYTES_MMIO_AREA,
+            Self::Vmalloc => BYTES_VMALLOC_AREA,
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+struct VirtManager([FreeList; 2]);
+impl VirtManager {
+    fn new() -> Self {
+        Self([FreeList::new(Window::Mmio), FreeList::new(Window::Vmalloc)])
+    }
+
+    fn alloc(&mut self, window: Window, num_of_pages: NumOfPages<Size4KiB>) -> Option<VirtAddr> {
+        self.0[window.index()].alloc(num_of_pages.as_usize())
+    }
+
+    fn free(
+        &mut self,
+        window: Window,
+        addr: VirtAddr,
+        num_of_pages: NumOfPages<Size4KiB>,
+    ) -> Result<(), Error> {
+        self.0[window.index()].free(addr, num_of_pages.as_usize())
+    }
+}
+
+// Free ranges are kept sorted by their start addresses, and adjacent ranges are always merged.
+struct FreeList {
+    ranges: [Range; MAX_FREE_RANGES],
+    len: usize,
+}
+impl FreeList {
+    fn new(window: Window) -> Self {
+        let mut ranges = [Range::null(); MAX_FREE_RANGES];
+        ranges[0] = Range::new(
+            window.start(),
+            window.bytes().as_num_of_pages::<Size4KiB>().as_usize(),
+        );
+
+        Self { ranges, len: 1 }
+    }
+
+    fn alloc(&mut self, num_of_pages: usize) -> Option<VirtAddr> {
+        if num_of_pages == 0 {
+            return None;
+        }
+
+        let i = (0..self.len).find(|&i| self.ranges[i].num_of_pages >= num_of_pages)?;
+        let addr = self.ranges[i].start;
+
+        self.ranges[i].start += Size4KiB::SIZE * u64::try_from(num_of_pages).unwrap();
+        self.ranges[i].num_of_pages -= num_of_pages;
+        if self.ranges[i].num_of_pages == 0 {
+            self.remove(i);
+        }
+
+        Some(addr)
+    }
+
+    // Merging with a neighbour never needs a new slot, so only an isolated range can fail.
+    fn free(&mut self, addr: VirtAddr, num_of_pages: usize) -> Result<(), Error> {
+        let freed = Range::new(addr, num_of_pages);
+        let i = (0..self.len)
+            .find(|&i| self.ranges[i].start > addr)
+            .unwrap_or(self.len);
+
+        self.assert_not_overlapping(i, &freed);
+
+        if i > 0 && self.ranges[i - 1].end() == freed.start {
+            self.ranges[i - 1].num_of_pages += num_of_pages;
+            self.merge_with_next(i - 1);
+        } else if i < self.len && freed.end() == self.ranges[i].start {
+            self.ranges[i].start = addr;
+            self.ranges[i].num_of_pages += num_of_pages;
         } else {
-            cnt = 0;
-            start = None;
+            self.insert(i, freed)?;
+        }
+
+        Ok(())
+    }
+
+    fn assert_not_overlapping(&self, i: usize, freed: &Range) {
+        let overlaps_prev = i > 0 && self.ranges[i - 1].end() > freed.start;
+        let overlaps_next = i < self.len && freed.end() > self.ranges[i].start;
+
+        assert!(
+            !overlaps_prev && !overlaps_next,
+            "Double free of virtual address: {:?}",
+            freed.start
+        );
+    }
+
+    fn merge_with_next(&mut self, i: usize) {
+        if i + 1 < self.len && self.ranges[i].end() == self.ranges[i + 1].start {
+            self.ranges[i].num_of_pages += self.ranges[i + 1].num_of_pages;
+            self.remove(i + 1);
+        }
+    }
+
+    fn insert(&mut self, i: usize, range: Range) -> Result<(), Error> {
+        if self.len >= MAX_FREE_RANGES {
+            return Err(Error::TooManyFreeRanges);
         }
+
+        self.ranges.copy_within(i..self.len, i + 1);
+        self.ranges[i] = range;
+        self.len += 1;
+        Ok(())
     }
 
-    None
+    fn remove(&mut self, i: usize) {
+        self.ranges.copy_within(i + 1..self.len, i);
+        self.len -= 1;
+    }
 }
 
-fn available(addr: VirtAddr) -> bool {
-    PML4.lock().translate_addr(addr).is_none() && !addr.is_null()
+#[derive(Copy, Clone)]
+struct Range {
+    start: VirtAddr,
+    num_of_pages: usize,
+}
+impl Range {
+    fn new(start: VirtAddr, num_of_pages: usize) -> Self {
+        Self {
+            start,
+            num_of_pages,
+        }
+    }
+
+    fn null() -> Self {
+        Self::new(VirtAddr::zero(), 0)
+    }
+
+    fn end(&self) -> VirtAddr {
+        self.start + Size4KiB::SIZE * u64::try_from(self.num_of_pages).unwrap()
+    }
 }
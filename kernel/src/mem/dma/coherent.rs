@@ -0,0 +1,124 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use {
+    super::{super::phys_to_virt, Constraints, Region},
+    core::{
+        marker::PhantomData,
+        mem,
+        ops::{Deref, DerefMut},
+        ptr, slice,
+    },
+    os_units::Bytes,
+    x86_64::{PhysAddr, VirtAddr},
+};
+
+// A buffer which both the CPU and a device access. x86_64 keeps caches coherent with DMA, so the
+// buffer is accessed through the direct map without any cache maintenance.
+pub struct Coherent<T: ?Sized> {
+    region: Region,
+    bytes: Bytes,
+    _marker: PhantomData<T>,
+}
+impl<T> Coherent<T> {
+    pub fn new(x: T, constraints: Constraints) -> Self {
+        let bytes = Bytes::new(mem::size_of::<T>());
+        let constraints = constraints.align(mem::align_of::<T>());
+
+        let mut coherent = Self::new_zeroed_from_bytes(bytes, constraints);
+        coherent.write_initial_value(x);
+        coherent
+    }
+
+    fn write_initial_value(&mut self, x: T) {
+        // Safety: This operation is safe because the memory is allocated and is aligned to
+        // `align_of::<T>()`.
+        unsafe { ptr::write(self.virt().as_mut_ptr(), x) }
+    }
+}
+impl<T> Deref for Coherent<T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        // Safety: This operation is safe because the memory is allocated and is not used by the
+        // others.
+        unsafe { &*self.virt().as_ptr() }
+    }
+}
+impl<T> DerefMut for Coherent<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // Safety: This operation is safe because the memory is allocated and is not used by the
+        // others.
+        unsafe { &mut *self.virt().as_mut_ptr() }
+    }
+}
+
+impl<T> Coherent<[T]>
+where
+    T: Copy + Clone,
+{
+    pub fn new_slice(x: T, num_of_elements: usize, constraints: Constraints) -> Self {
+        let bytes = Bytes::new(mem::size_of::<T>() * num_of_elements);
+        let constraints = constraints.align(mem::align_of::<T>());
+
+        let mut coherent = Self::new_zeroed_from_bytes(bytes, constraints);
+        for element in coherent.iter_mut() {
+            *element = x;
+        }
+        coherent
+    }
+
+    fn num_of_elements(&self) -> usize {
+        self.bytes.as_usize() / mem::size_of::<T>()
+    }
+}
+impl<T> Deref for Coherent<[T]>
+where
+    T: Copy + Clone,
+{
+    type Target = [T];
+    fn deref(&self) -> &Self::Target {
+        unsafe { slice::from_raw_parts(self.virt().as_ptr(), self.num_of_elements()) }
+    }
+}
+impl<T> DerefMut for Coherent<[T]>
+where
+    T: Copy + Clone,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { slice::from_raw_parts_mut(self.virt().as_mut_ptr(), self.num_of_elements()) }
+    }
+}
+impl<T: ?Sized> Coherent<T> {
+    pub fn phys_addr(&self) -> PhysAddr {
+        self.region.addr
+    }
+
+    fn virt(&self) -> VirtAddr {
+        phys_to_virt(self.region.addr)
+    }
+
+    fn new_zeroed_from_bytes(bytes: Bytes, constraints: Constraints) -> Self {
+        let region = super::alloc(bytes, constraints).unwrap_or_else(|| {
+            panic!(
+                "Failed to allocate a DMA buffer of {:?} with {:?}",
+                bytes, constraints
+            )
+        });
+
+        let coherent = Self {
+            region,
+            bytes,
+            _marker: PhantomData,
+        };
+        coherent.write_all_bytes_with_zero();
+        coherent
+    }
+
+    fn write_all_bytes_with_zero(&self) {
+        unsafe { ptr::write_bytes(self.virt().as_mut_ptr::<u8>(), 0, self.bytes.as_usize()) }
+    }
+}
+impl<T: ?Sized> Drop for Coherent<T> {
+    fn drop(&mut self) {
+        super::free(self.region);
+    }
+}
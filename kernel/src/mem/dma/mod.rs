@@ -0,0 +1,138 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+mod coherent;
+mod pool;
+pub mod sg;
+
+pub use coherent::Coherent;
+
+use {
+    super::allocator::{heap::slab::SizeClass, phys::FRAME_MANAGER},
+    core::{alloc::Layout, cmp},
+    os_units::Bytes,
+    x86_64::{structures::paging::Size4KiB, PhysAddr},
+};
+
+// Requirements of a device on the memory it accesses. For example, xHCI requires
+// `Constraints::new().align(64).boundary(0x1_0000)` for TRB rings.
+#[derive(Copy, Clone, Debug)]
+pub struct Constraints {
+    align: usize,
+    boundary: Option<usize>,
+    width: AddrWidth,
+}
+impl Constraints {
+    pub fn new() -> Self {
+        Self {
+            align: 1,
+            boundary: None,
+            width: AddrWidth::Bits64,
+        }
+    }
+
+    pub fn align(mut self, align: usize) -> Self {
+        assert!(align.is_power_of_two(), "Invalid alignment: {}", align);
+        self.align = cmp::max(self.align, align);
+        self
+    }
+
+    // A buffer must not cross an address which is a multiple of `boundary`.
+    pub fn boundary(mut self, boundary: usize) -> Self {
+        assert!(boundary.is_power_of_two(), "Invalid boundary: {}", boundary);
+        self.boundary = Some(boundary);
+        self
+    }
+
+    pub fn width(mut self, width: AddrWidth) -> Self {
+        self.width = width;
+        self
+    }
+
+    fn limit(self) -> Option<PhysAddr> {
+        self.width.limit()
+    }
+
+    fn below_limit(self, addr: PhysAddr, bytes: usize) -> bool {
+        self.limit().map_or(true, |limit| addr + bytes <= limit)
+    }
+}
+impl Default for Constraints {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AddrWidth {
+    Bits32,
+    Bits64,
+}
+impl AddrWidth {
+    fn limit(self) -> Option<PhysAddr> {
+        match self {
+            Self::Bits32 => Some(PhysAddr::new(1 << 32)),
+            Self::Bits64 => None,
+        }
+    }
+}
+
+// A physically contiguous area which satisfies some `Constraints`.
+#[derive(Copy, Clone, Debug)]
+struct Region {
+    addr: PhysAddr,
+    source: Source,
+}
+
+#[derive(Copy, Clone, Debug)]
+enum Source {
+    Pool(SizeClass, AddrWidth),
+    Frames,
+}
+
+// Objects are aligned to their size rounded up to a power of two, both in the pool and in the
+// frame allocator. Such an object never crosses a boundary which is not smaller than it.
+fn alloc(bytes: Bytes, constraints: Constraints) -> Option<Region> {
+    let size = cmp::max(bytes.as_usize(), 1).next_power_of_two();
+    let size = cmp::max(size, constraints.align);
+
+    if constraints
+        .boundary
+        .map_or(false, |boundary| size > boundary)
+    {
+        return None;
+    }
+
+    let layout = Layout::from_size_align(size, size).ok()?;
+    match SizeClass::for_layout(layout) {
+        Some(class) => alloc_from_pool(class, constraints.width),
+        None => alloc_frames(size, constraints),
+    }
+}
+
+fn free(region: Region) {
+    match region.source {
+        Source::Pool(class, width) => pool::free(class, width, region.addr),
+        Source::Frames => FRAME_MANAGER.lock().free(region.addr),
+    }
+}
+
+fn alloc_from_pool(class: SizeClass, width: AddrWidth) -> Option<Region> {
+    let addr = pool::alloc(class, width)?;
+
+    Some(Region {
+        addr,
+        source: Source::Pool(class, width),
+    })
+}
+
+fn alloc_frames(size: usize, constraints: Constraints) -> Option<Region> {
+    let num_of_pages = Bytes::new(size).as_num_of_pages::<Size4KiB>();
+    let addr = FRAME_MANAGER
+        .lock()
+        .alloc_for_dma(num_of_pages, constraints.limit())?;
+
+    Some(Region {
+        addr,
+        source: Source::Frames,
+    })
+}
@@ -0,0 +1,73 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use {
+    super::{
+        super::{
+            allocator::{
+                heap::slab::{Cache, SizeClass, NUM_OF_CLASSES},
+                phys::FRAME_MANAGER,
+            },
+            phys_to_virt, virt_to_phys,
+        },
+        AddrWidth,
+    },
+    conquer_once::spin::Lazy,
+    os_units::NumOfPages,
+    spinning_top::Spinlock,
+    x86_64::PhysAddr,
+};
+
+// Small DMA objects like FIS areas and command lists are carved out of pages in the same way as
+// the heap does. Pages are never returned to `FrameManager`.
+static POOLS: Lazy<Spinlock<Pools>> = Lazy::new(|| Spinlock::new(Pools::new()));
+
+pub fn alloc(class: SizeClass, width: AddrWidth) -> Option<PhysAddr> {
+    POOLS.lock().alloc(class, width)
+}
+
+pub fn free(class: SizeClass, width: AddrWidth, addr: PhysAddr) {
+    POOLS.lock().free(class, width, addr)
+}
+
+struct Pools {
+    any: [Cache; NUM_OF_CLASSES],
+    below_4gib: [Cache; NUM_OF_CLASSES],
+}
+impl Pools {
+    fn new() -> Self {
+        Self {
+            any: [Cache::new(); NUM_OF_CLASSES],
+            below_4gib: [Cache::new(); NUM_OF_CLASSES],
+        }
+    }
+
+    fn alloc(&mut self, class: SizeClass, width: AddrWidth) -> Option<PhysAddr> {
+        let cache = &mut self.caches(width)[class.index()];
+
+        if cache.empty() {
+            let page = Self::alloc_page(width)?;
+            cache.add_slab(phys_to_virt(page), class.size());
+        }
+
+        cache.pop().and_then(virt_to_phys)
+    }
+
+    fn free(&mut self, class: SizeClass, width: AddrWidth, addr: PhysAddr) {
+        self.caches(width)[class.index()].push(phys_to_virt(addr));
+    }
+
+    fn caches(&mut self, width: AddrWidth) -> &mut [Cache; NUM_OF_CLASSES] {
+        match width {
+            AddrWidth::Bits32 => &mut self.below_4gib,
+            AddrWidth::Bits64 => &mut self.any,
+        }
+    }
+
+    fn alloc_page(width: AddrWidth) -> Option<PhysAddr> {
+        FRAME_MANAGER
+            .lock()
+            .alloc_for_dma(NumOfPages::new(1), width.limit())
+    }
+}
+// `Pools` contains raw pointers to free objects. They are only touched while the lock is held.
+unsafe impl Send for Pools {}
@@ -0,0 +1,137 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use {
+    super::{super::virt_to_phys, Constraints},
+    core::{cmp, convert::TryFrom, marker::PhantomData},
+    os_units::Bytes,
+    x86_64::{
+        structures::paging::{PageSize, Size4KiB},
+        PhysAddr, VirtAddr,
+    },
+};
+
+// The list does not use the heap so that the page fault handler can build one to page in.
+pub const MAX_SEGMENTS: usize = 8;
+
+// A list of physically contiguous segments of a buffer for a large transfer. The buffer is
+// borrowed until the list is dropped so that no one frees it while a device accesses it.
+//
+// Every page of the buffer must be mapped. Pages of a lazily mapped area must be touched first.
+pub struct List<'a> {
+    segments: [Segment; MAX_SEGMENTS],
+    len: usize,
+    _marker: PhantomData<&'a mut [u8]>,
+}
+impl<'a> List<'a> {
+    // For transfers in which the device reads the buffer.
+    pub fn to_device(buf: &'a [u8], constraints: Constraints) -> Result<Self, Error> {
+        Self::new(VirtAddr::from_ptr(buf.as_ptr()), buf.len(), constraints)
+    }
+
+    // For transfers in which the device writes to the buffer.
+    pub fn from_device(buf: &'a mut [u8], constraints: Constraints) -> Result<Self, Error> {
+        Self::new(VirtAddr::from_ptr(buf.as_ptr()), buf.len(), constraints)
+    }
+
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments[..self.len]
+    }
+
+    fn new(start: VirtAddr, len: usize, constraints: Constraints) -> Result<Self, Error> {
+        let mut list = Self {
+            segments: [Segment::null(); MAX_SEGMENTS],
+            len: 0,
+            _marker: PhantomData,
+        };
+
+        let mut offset = 0;
+        while offset < len {
+            let virt = start + offset;
+            let phys = virt_to_phys(virt).ok_or(Error::NotMapped(virt))?;
+            let bytes = cmp::min(len - offset, Self::bytes_until_split(phys, constraints));
+
+            if !constraints.below_limit(phys, bytes) {
+                return Err(Error::AboveLimit(phys));
+            }
+
+            list.push(
+                Segment {
+                    addr: phys,
+                    bytes: Bytes::new(bytes),
+                },
+                constraints,
+            )?;
+            offset += bytes;
+        }
+
+        Ok(list)
+    }
+
+    // A segment is split at each page because the next page may not be physically contiguous, and
+    // at each boundary.
+    fn bytes_until_split(phys: PhysAddr, constraints: Constraints) -> usize {
+        let split = cmp::min(
+            Size4KiB::SIZE,
+            constraints
+                .boundary
+                .map_or(Size4KiB::SIZE, |b| u64::try_from(b).unwrap()),
+        );
+
+        let phys = phys.as_u64();
+        usize::try_from((phys / split + 1) * split - phys).unwrap()
+    }
+
+    fn push(&mut self, segment: Segment, constraints: Constraints) -> Result<(), Error> {
+        if let Some(last) = self.len.checked_sub(1).map(|i| &mut self.segments[i]) {
+            if last.continues_to(segment.addr, constraints) {
+                last.bytes = Bytes::new(last.bytes.as_usize() + segment.bytes.as_usize());
+                return Ok(());
+            }
+        }
+
+        if !segment
+            .addr
+            .is_aligned(u64::try_from(constraints.align).unwrap())
+        {
+            return Err(Error::Misaligned(segment.addr));
+        }
+
+        let slot = self
+            .segments
+            .get_mut(self.len)
+            .ok_or(Error::TooManySegments)?;
+        *slot = segment;
+        self.len += 1;
+        Ok(())
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Segment {
+    pub addr: PhysAddr,
+    pub bytes: Bytes,
+}
+impl Segment {
+    fn null() -> Self {
+        Self {
+            addr: PhysAddr::zero(),
+            bytes: Bytes::new(0),
+        }
+    }
+
+    fn continues_to(&self, phys: PhysAddr, constraints: Constraints) -> bool {
+        let crosses_boundary = constraints
+            .boundary
+            .map_or(false, |b| phys.is_aligned(u64::try_from(b).unwrap()));
+
+        self.addr + self.bytes.as_usize() == phys && !crosses_boundary
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    NotMapped(VirtAddr),
+    Misaligned(PhysAddr),
+    AboveLimit(PhysAddr),
+    TooManySegments,
+}
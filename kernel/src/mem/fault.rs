@@ -0,0 +1,238 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use {
+    super::{
+        allocator::phys::{Tag, FRAME_MANAGER},
+        paging::recursive::{index_of, table_of},
+        swap,
+        vma::{self, Access, Area},
+    },
+    crate::process,
+    abi::process::Fault,
+    conquer_once::spin::Lazy,
+    core::{convert::TryFrom, ptr},
+    os_units::NumOfPages,
+    spinning_top::Spinlock,
+    x86_64::{
+        instructions::{interrupts, tlb},
+        registers::control::Cr3,
+        structures::{
+            idt::{InterruptStackFrame, PageFaultErrorCode},
+            paging::{Page, PageSize, PageTable, PageTableFlags, PhysFrame, Size4KiB},
+        },
+        VirtAddr,
+    },
+};
+
+// A page fault may happen while `FrameManager` or `PML4` is locked, for example when
+// `FrameManager` grows its list on the heap. Thus the handler never waits for these locks. Frames
+// are taken from `FrameManager` if it is free, and from this reserve otherwise. Page tables are
+// edited through the recursive mapping directly.
+const NUM_OF_RESERVED_FRAMES: usize = 64;
+
+const UPPER_HALF_START: VirtAddr = VirtAddr::new_truncate(0xffff_8000_0000_0000);
+
+static RESERVE: Lazy<Spinlock<Reserve>> = Lazy::new(|| Spinlock::new(Reserve::new()));
+
+pub fn handle(addr: VirtAddr, error: PageFaultErrorCode, stack_frame: &InterruptStackFrame) {
+    let access = access(error);
+
+    // The lower half belongs to the process whose PML4 is in CR3, and the upper half to the
+    // kernel.
+    if addr < UPPER_HALF_START {
+        handle_in_process(addr, error, access, stack_frame);
+        return;
+    }
+
+    let area = find_area(addr);
+
+    match area {
+        Some(area) if !error.contains(PageFaultErrorCode::PROTECTION_VIOLATION) => {
+            if !area.allows(access) {
+                invalid_access(addr, access, Some(&area), stack_frame);
+            }
+
+            map_page(Page::containing_address(addr), area.flags());
+        }
+        _ => invalid_access(addr, access, area.as_ref(), stack_frame),
+    }
+}
+
+// Call this regularly from a context where the heap and `FrameManager` may be used.
+pub fn refill_frame_reserve() {
+    loop {
+        // The handler panics if a preempted thread holds the lock.
+        if interrupts::without_interrupts(|| RESERVE.lock().full()) {
+            return;
+        }
+
+        let frame = match FRAME_MANAGER.try_lock() {
+            Some(mut frame_manager) => frame_manager.alloc(NumOfPages::new(1), Tag::Heap),
+            None => return,
+        };
+
+        match frame {
+            Some(frame) => interrupts::without_interrupts(|| {
+                RESERVE
+                    .lock()
+                    .push(PhysFrame::from_start_address(frame).unwrap())
+            }),
+            None => return,
+        }
+    }
+}
+
+// The kernel accesses user memory only through the direct map, so a fault in the lower half must
+// come from user mode. No lock of the kernel is held then, so the fault is handled like a system
+// call, with interrupts enabled.
+fn handle_in_process(
+    addr: VirtAddr,
+    error: PageFaultErrorCode,
+    access: Access,
+    stack_frame: &InterruptStackFrame,
+) {
+    if !access.by_user() {
+        invalid_access(addr, access, None, stack_frame);
+    }
+
+    let (pml4, _) = Cr3::read();
+
+    interrupts::enable();
+    let handled = !error.contains(PageFaultErrorCode::PROTECTION_VIOLATION)
+        && process::handle_page_fault(pml4, addr, access);
+    interrupts::disable();
+
+    if !handled {
+        invalid_access(addr, access, None, stack_frame);
+    }
+}
+
+fn find_area(addr: VirtAddr) -> Option<Area> {
+    vma::KERNEL
+        .try_lock()
+        .expect("Page fault while the list of memory areas is locked.")
+        .find(addr)
+}
+
+fn access(error: PageFaultErrorCode) -> Access {
+    let by_user = error.contains(PageFaultErrorCode::USER_MODE);
+
+    if error.contains(PageFaultErrorCode::INSTRUCTION_FETCH) {
+        Access::Execute { by_user }
+    } else if error.contains(PageFaultErrorCode::CAUSED_BY_WRITE) {
+        Access::Write { by_user }
+    } else {
+        Access::Read { by_user }
+    }
+}
+
+fn invalid_access(
+    addr: VirtAddr,
+    access: Access,
+    area: Option<&Area>,
+    stack_frame: &InterruptStackFrame,
+) -> ! {
+    if access.by_user() {
+        process::kill_current(
+            Fault::Segmentation,
+            format_args!("Invalid {:?} to {:?}", access, addr),
+        );
+    }
+
+    match area {
+        Some(area) => panic!(
+            "Invalid {:?} to {:?} in {}\n{:#?}",
+            access, addr, area, stack_frame
+        ),
+        None => panic!(
+            "Invalid {:?} to {:?} outside any memory area\n{:#?}",
+            access, addr, stack_frame
+        ),
+    }
+}
+
+// The page is filled with zeros unless it is swapped out.
+fn map_page(page: Page<Size4KiB>, flags: PageTableFlags) {
+    let table_flags = PageTableFlags::PRESENT
+        | PageTableFlags::WRITABLE
+        | (flags & PageTableFlags::USER_ACCESSIBLE);
+
+    for level in (2..=4).rev() {
+        let entry = &mut table_of(page, level)[index_of(page, level)];
+
+        if entry.is_unused() {
+            let frame = take_frame();
+            entry.set_frame(frame, table_flags);
+            clear_page(table_of(page, level - 1) as *mut PageTable as *mut u8);
+        }
+    }
+
+    let entry = &mut table_of(page, 1)[index_of(page, 1)];
+    let frame = take_frame();
+
+    match swap::slot_of(entry) {
+        Some(slot) => {
+            swap::page_in(slot, frame);
+
+            // The copy in the swap area is freed, so the page must be written again when it is
+            // paged out next time.
+            entry.set_frame(
+                frame,
+                flags | PageTableFlags::PRESENT | PageTableFlags::DIRTY,
+            );
+            tlb::flush(page.start_address());
+        }
+        None => {
+            entry.set_frame(frame, flags | PageTableFlags::PRESENT);
+            tlb::flush(page.start_address());
+
+            clear_page(page.start_address().as_mut_ptr());
+        }
+    }
+}
+
+fn take_frame() -> PhysFrame {
+    let frame = FRAME_MANAGER
+        .try_lock()
+        .and_then(|mut frame_manager| frame_manager.alloc_frame_without_heap(Tag::Heap));
+
+    match frame {
+        Some(frame) => PhysFrame::from_start_address(frame).unwrap(),
+        None => RESERVE
+            .try_lock()
+            .and_then(|mut reserve| reserve.pop())
+            .expect("No frame is available for page faults."),
+    }
+}
+
+fn clear_page(ptr: *mut u8) {
+    // Safety: `ptr` points to a page which was just mapped and no one uses.
+    unsafe { ptr::write_bytes(ptr, 0, usize::try_from(Size4KiB::SIZE).unwrap()) }
+}
+
+struct Reserve {
+    frames: [Option<PhysFrame>; NUM_OF_RESERVED_FRAMES],
+    len: usize,
+}
+impl Reserve {
+    fn new() -> Self {
+        Self {
+            frames: [None; NUM_OF_RESERVED_FRAMES],
+            len: 0,
+        }
+    }
+
+    fn full(&self) -> bool {
+        self.len == NUM_OF_RESERVED_FRAMES
+    }
+
+    fn push(&mut self, frame: PhysFrame) {
+        self.frames[self.len] = Some(frame);
+        self.len += 1;
+    }
+
+    fn pop(&mut self) -> Option<PhysFrame> {
+        self.len = self.len.checked_sub(1)?;
+        self.frames[self.len].take()
+    }
+}
@@ -0,0 +1,80 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use {
+    super::{
+        allocator::{
+            heap,
+            phys::{Tag, FRAME_MANAGER},
+        },
+        mmio, swap,
+    },
+    common::constant::BYTES_KERNEL_HEAP_INIT,
+};
+
+pub fn log() {
+    // Logging may allocate memory and lock `FrameManager`, so the lock is released first.
+    let stats = match FRAME_MANAGER.try_lock() {
+        Some(frame_manager) => frame_manager.stats(),
+        None => {
+            warn!("meminfo: FrameManager is locked.");
+            return;
+        }
+    };
+
+    info!(
+        "meminfo: {} KiB total, {} KiB free, {} KiB used",
+        kib(stats.bytes_total),
+        kib(stats.bytes_free),
+        kib(stats.bytes_total - stats.bytes_free)
+    );
+    for &tag in &Tag::ALL {
+        info!(
+            "meminfo:   {}: {} KiB",
+            tag.name(),
+            kib(stats.bytes_per_tag[tag as usize])
+        );
+    }
+
+    // The first part of the heap is allocated before `FrameManager` is initialized.
+    info!(
+        "meminfo:   early heap: {} KiB",
+        kib(BYTES_KERNEL_HEAP_INIT.as_usize())
+    );
+    info!(
+        "meminfo:   frame manager: {} KiB of its list on the heap",
+        kib(stats.bytes_of_list)
+    );
+
+    let (num_of_regions, bytes_mmio) = mmio::usage();
+    info!(
+        "meminfo: MMIO: {} regions, {} KiB mapped",
+        num_of_regions,
+        kib(bytes_mmio.as_usize())
+    );
+    info!("meminfo: Heap: {}", heap::stats());
+
+    match swap::stats() {
+        Some(stats) => info!("meminfo: Swap: {}", stats),
+        None => info!("meminfo: Swap: none"),
+    }
+
+    info!(
+        "meminfo: Fragmentation: {} free blocks, the largest is {} KiB ({}%)",
+        stats.num_of_free_blocks,
+        kib(stats.bytes_largest_free_block),
+        fragmentation(stats.bytes_largest_free_block, stats.bytes_free)
+    );
+}
+
+// The ratio of free memory which is not in the largest free block.
+fn fragmentation(bytes_largest_free_block: usize, bytes_free: usize) -> usize {
+    if bytes_free == 0 {
+        0
+    } else {
+        100 - bytes_largest_free_block * 100 / bytes_free
+    }
+}
+
+fn kib(bytes: usize) -> usize {
+    bytes / 1024
+}
@@ -0,0 +1,224 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use {
+    crate::mem::{
+        allocator::{
+            phys::FRAME_MANAGER,
+            virt::{self, Window},
+        },
+        paging::pml4::PML4,
+    },
+    alloc::sync::Arc,
+    core::{
+        convert::TryFrom,
+        marker::PhantomData,
+        mem, ptr,
+        sync::atomic::{AtomicUsize, Ordering},
+    },
+    os_units::{Bytes, NumOfPages},
+    x86_64::{
+        structures::paging::{Mapper, Page, PageSize, PageTableFlags, PhysFrame, Size4KiB},
+        PhysAddr, VirtAddr,
+    },
+};
+
+static NUM_OF_REGIONS: AtomicUsize = AtomicUsize::new(0);
+static BYTES_MAPPED: AtomicUsize = AtomicUsize::new(0);
+
+pub struct ReadOnly;
+pub struct WriteOnly;
+pub struct ReadWrite;
+
+pub trait Readable {}
+impl Readable for ReadOnly {}
+impl Readable for ReadWrite {}
+
+pub trait Writable {}
+impl Writable for WriteOnly {}
+impl Writable for ReadWrite {}
+
+// A memory-mapped area of a device, usually a whole BAR. It is mapped only once, and every
+// register in it shares the mapping.
+pub struct Region {
+    virt: VirtAddr,
+    bytes: Bytes,
+}
+impl Region {
+    pub fn new(phys: PhysAddr, bytes: Bytes) -> Arc<Self> {
+        NUM_OF_REGIONS.fetch_add(1, Ordering::Relaxed);
+        BYTES_MAPPED.fetch_add(bytes.as_usize(), Ordering::Relaxed);
+
+        Arc::new(Self {
+            virt: map_pages(phys, bytes),
+            bytes,
+        })
+    }
+
+    pub fn register<T, A>(self: &Arc<Self>, offset: Bytes) -> Register<T, A> {
+        self.assert_contains(offset, Bytes::new(mem::size_of::<T>()));
+
+        Register {
+            region: self.clone(),
+            offset,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn array<T, A>(self: &Arc<Self>, offset: Bytes, len: usize) -> Array<T, A> {
+        self.assert_contains(offset, Bytes::new(mem::size_of::<T>() * len));
+
+        Array {
+            region: self.clone(),
+            offset,
+            len,
+            _marker: PhantomData,
+        }
+    }
+
+    fn assert_contains(&self, offset: Bytes, bytes: Bytes) {
+        assert!(
+            offset.as_usize() + bytes.as_usize() <= self.bytes.as_usize(),
+            "The register at {:#x} is out of the MMIO region.",
+            offset.as_usize()
+        );
+    }
+
+    fn addr(&self, offset: Bytes) -> VirtAddr {
+        self.virt + offset.as_usize()
+    }
+}
+impl Drop for Region {
+    fn drop(&mut self) {
+        unmap_pages(self.virt, self.bytes);
+
+        NUM_OF_REGIONS.fetch_sub(1, Ordering::Relaxed);
+        BYTES_MAPPED.fetch_sub(self.bytes.as_usize(), Ordering::Relaxed);
+    }
+}
+
+// Returns the number of mapped regions and their total size. They are device memory, not RAM.
+pub fn usage() -> (usize, Bytes) {
+    (
+        NUM_OF_REGIONS.load(Ordering::Relaxed),
+        Bytes::new(BYTES_MAPPED.load(Ordering::Relaxed)),
+    )
+}
+
+pub struct Register<T, A> {
+    region: Arc<Region>,
+    offset: Bytes,
+    _marker: PhantomData<(T, A)>,
+}
+impl<T, A: Readable> Register<T, A> {
+    pub fn read(&self) -> T {
+        // Safety: The address is in the mapped region and is checked when `self` is created.
+        unsafe { ptr::read_volatile(self.region.addr(self.offset).as_ptr()) }
+    }
+}
+impl<T, A: Writable> Register<T, A> {
+    pub fn write(&mut self, val: T) {
+        // Safety: The address is in the mapped region and is checked when `self` is created.
+        unsafe { ptr::write_volatile(self.region.addr(self.offset).as_mut_ptr(), val) }
+    }
+}
+impl<T> Register<T, ReadWrite> {
+    pub fn update<U>(&mut self, f: U)
+    where
+        U: Fn(&mut T),
+    {
+        let mut val = self.read();
+        f(&mut val);
+        self.write(val);
+    }
+}
+
+pub struct Array<T, A> {
+    region: Arc<Region>,
+    offset: Bytes,
+    len: usize,
+    _marker: PhantomData<(T, A)>,
+}
+impl<T, A> Array<T, A> {
+    fn addr_to_elem(&self, index: usize) -> VirtAddr {
+        assert!(index < self.len, "Index out of range: {}", index);
+        self.region.addr(Bytes::new(
+            self.offset.as_usize() + mem::size_of::<T>() * index,
+        ))
+    }
+}
+impl<T, A: Readable> Array<T, A> {
+    pub fn read(&self, index: usize) -> T {
+        // Safety: The address is in the mapped region and is checked when `self` is created.
+        unsafe { ptr::read_volatile(self.addr_to_elem(index).as_ptr()) }
+    }
+}
+impl<T, A: Writable> Array<T, A> {
+    pub fn write(&mut self, index: usize, val: T) {
+        // Safety: The address is in the mapped region and is checked when `self` is created.
+        unsafe { ptr::write_volatile(self.addr_to_elem(index).as_mut_ptr(), val) }
+    }
+}
+impl<T> Array<T, ReadWrite> {
+    pub fn update<U>(&mut self, index: usize, f: U)
+    where
+        U: Fn(&mut T),
+    {
+        let mut val = self.read(index);
+        f(&mut val);
+        self.write(index, val);
+    }
+}
+
+// Device registers must not be cached, and the kernel writes to them.
+fn flags() -> PageTableFlags {
+    PageTableFlags::PRESENT
+        | PageTableFlags::WRITABLE
+        | PageTableFlags::NO_CACHE
+        | PageTableFlags::WRITE_THROUGH
+        | PageTableFlags::NO_EXECUTE
+}
+
+fn map_pages(start: PhysAddr, bytes: Bytes) -> VirtAddr {
+    let start_frame_addr = start.align_down(Size4KiB::SIZE);
+    let num_pages = num_of_pages(start.as_u64(), bytes);
+
+    let virt = virt::alloc(Window::Mmio, num_pages).expect("OOM during mapping an MMIO region.");
+
+    for i in 0..u64::try_from(num_pages.as_usize()).unwrap() {
+        let page = Page::<Size4KiB>::containing_address(virt + Size4KiB::SIZE * i);
+        let frame = PhysFrame::containing_address(start_frame_addr + Size4KiB::SIZE * i);
+
+        unsafe {
+            PML4.lock()
+                .map_to(page, frame, flags(), &mut *FRAME_MANAGER.lock())
+                .unwrap()
+                .flush()
+        }
+    }
+
+    virt + start.as_u64() % Size4KiB::SIZE
+}
+
+fn unmap_pages(start: VirtAddr, bytes: Bytes) {
+    let start_page_addr = start.align_down(Size4KiB::SIZE);
+    let num_pages = num_of_pages(start.as_u64(), bytes);
+
+    for i in 0..u64::try_from(num_pages.as_usize()).unwrap() {
+        let page = Page::<Size4KiB>::containing_address(start_page_addr + Size4KiB::SIZE * i);
+
+        let (_, flush) = PML4.lock().unmap(page).unwrap();
+        flush.flush();
+    }
+
+    if let Err(e) = virt::free(start_page_addr, num_pages) {
+        warn!(
+            "Leaking the virtual addresses at {:?}: {:?}",
+            start_page_addr, e
+        );
+    }
+}
+
+fn num_of_pages(start: u64, bytes: Bytes) -> NumOfPages<Size4KiB> {
+    let page_offset = usize::try_from(start % Size4KiB::SIZE).unwrap();
+    Bytes::new(page_offset + bytes.as_usize()).as_num_of_pages()
+}
@@ -1,5 +1,25 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-pub mod accessor;
 pub mod allocator;
+pub mod dma;
+pub mod fault;
+pub mod meminfo;
+pub mod mmio;
 pub mod paging;
+pub mod reclaim;
+pub mod swap;
+pub mod vma;
+
+use {
+    paging::{direct_map, pml4::PML4},
+    x86_64::{structures::paging::MapperAllSizes, PhysAddr, VirtAddr},
+};
+
+pub fn phys_to_virt(phys: PhysAddr) -> VirtAddr {
+    direct_map::to_virt(phys)
+        .unwrap_or_else(|| panic!("{:?} is not covered by the direct map.", phys))
+}
+
+pub fn virt_to_phys(virt: VirtAddr) -> Option<PhysAddr> {
+    direct_map::to_phys(virt).or_else(|| PML4.lock().translate_addr(virt))
+}
@@ -0,0 +1,110 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use {
+    super::pml4::PML4,
+    crate::mem::allocator::phys::FRAME_MANAGER,
+    common::constant::{BYTES_PHYS_MAP_AREA, PHYS_MAP_ADDR},
+    core::{
+        arch::x86_64::__cpuid,
+        convert::TryFrom,
+        sync::atomic::{AtomicU64, Ordering},
+    },
+    uefi::table::boot::{self, MemoryType},
+    x86_64::{
+        structures::paging::{
+            Mapper, Page, PageSize, PageTableFlags, PhysFrame, RecursivePageTable, Size1GiB,
+            Size2MiB, Size4KiB,
+        },
+        PhysAddr, VirtAddr,
+    },
+};
+
+static BYTES_MAPPED: AtomicU64 = AtomicU64::new(0);
+
+pub fn init(mem_map: &[boot::MemoryDescriptor]) {
+    let end = phys_end(mem_map);
+    assert!(
+        end <= u64::try_from(BYTES_PHYS_MAP_AREA.as_usize()).unwrap(),
+        "Physical memory is too large to map directly."
+    );
+
+    let bytes_mapped = if supports_1gib_pages() {
+        map_all::<Size1GiB>(end)
+    } else {
+        map_all::<Size2MiB>(end)
+    };
+
+    BYTES_MAPPED.store(bytes_mapped, Ordering::Relaxed);
+}
+
+pub fn to_virt(phys: PhysAddr) -> Option<VirtAddr> {
+    if phys.as_u64() < BYTES_MAPPED.load(Ordering::Relaxed) {
+        Some(PHYS_MAP_ADDR + phys.as_u64())
+    } else {
+        None
+    }
+}
+
+pub fn to_phys(virt: VirtAddr) -> Option<PhysAddr> {
+    let offset = virt.as_u64().checked_sub(PHYS_MAP_ADDR.as_u64())?;
+
+    if offset < BYTES_MAPPED.load(Ordering::Relaxed) {
+        Some(PhysAddr::new(offset))
+    } else {
+        None
+    }
+}
+
+// RAM holes below `end` are mapped too. Drivers must not touch MMIO through this map because it is
+// cacheable.
+fn map_all<S: PageSize>(end: u64) -> u64
+where
+    for<'a> RecursivePageTable<'a>: Mapper<S>,
+{
+    let end = PhysAddr::new(end).align_up(S::SIZE).as_u64();
+
+    for offset in (0..end).step_by(usize::try_from(S::SIZE).unwrap()) {
+        let page = Page::<S>::containing_address(PHYS_MAP_ADDR + offset);
+        let frame = PhysFrame::<S>::containing_address(PhysAddr::new(offset));
+
+        // Safety: The direct map area is used by nothing else, and `frame` is not owned by any
+        // Rust object through this address.
+        unsafe {
+            PML4.lock()
+                .map_to(
+                    page,
+                    frame,
+                    PageTableFlags::PRESENT | PageTableFlags::WRITABLE | PageTableFlags::NO_EXECUTE,
+                    &mut *FRAME_MANAGER.lock(),
+                )
+                .unwrap()
+                .flush();
+        }
+    }
+
+    end
+}
+
+fn phys_end(mem_map: &[boot::MemoryDescriptor]) -> u64 {
+    mem_map
+        .iter()
+        .filter(|d| is_ram(d.ty))
+        .map(|d| d.phys_start + d.page_count * Size4KiB::SIZE)
+        .max()
+        .unwrap_or(0)
+}
+
+fn is_ram(ty: MemoryType) -> bool {
+    ty != MemoryType::RESERVED
+        && ty != MemoryType::UNUSABLE
+        && ty != MemoryType::MMIO
+        && ty != MemoryType::MMIO_PORT_SPACE
+}
+
+fn supports_1gib_pages() -> bool {
+    const PDPE1GB: u32 = 1 << 26;
+
+    // Safety: `cpuid` is always available on x86_64 processors.
+    let extended = unsafe { __cpuid(0x8000_0001) };
+    extended.edx & PDPE1GB != 0
+}
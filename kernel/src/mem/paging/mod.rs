@@ -1,14 +1,124 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+pub mod direct_map;
 pub mod pml4;
+pub mod protection;
+pub mod recursive;
 
-use {common::constant::RECUR_PML4_ADDR, x86_64::structures::paging::PageTable};
+use {
+    super::{
+        allocator::phys::{Tag, FRAME_MANAGER},
+        phys_to_virt,
+    },
+    common::constant::RECUR_PML4_ADDR,
+    conquer_once::spin::OnceCell,
+    os_units::NumOfPages,
+    x86_64::{
+        registers::control::Cr3,
+        structures::paging::{FrameAllocator, PageTable, PageTableFlags, PhysFrame},
+    },
+};
 
-pub fn mark_pages_as_unused() {
-    let page_table = unsafe { &mut *(RECUR_PML4_ADDR.as_mut_ptr() as *mut PageTable) };
+const PML4_INDEX_UPPER_HALF: usize = 256;
+const PML4_INDEX_RECURSIVE: usize = 511;
 
-    // Entry 510 and 511 are used by kernel.
-    for i in 0..510 {
-        page_table[i].set_unused();
+static KERNEL_PML4: OnceCell<PhysFrame> = OnceCell::uninit();
+
+// UEFI's PML4 is in boot services memory, which is reclaimed later. The upper half holding the
+// kernel and the direct map is copied to a new PML4. The lower half, where UEFI identity-mapped the
+// memory, is dropped.
+//
+// Every entry of the upper half is filled here. The PML4 of each process copies these entries, so
+// the kernel must not add any entry to the PML4 later.
+pub fn replace_pml4() {
+    let frame = FRAME_MANAGER
+        .lock()
+        .allocate_frame()
+        .expect("Failed to allocate a frame for PML4.");
+
+    let current = unsafe { &*(RECUR_PML4_ADDR.as_ptr() as *const PageTable) };
+    // Safety: The frame was just allocated and no one uses it.
+    let new = unsafe { table_mut(frame) };
+
+    new.zero();
+    for (new, current) in new
+        .iter_mut()
+        .zip(current.iter())
+        .take(PML4_INDEX_RECURSIVE)
+        .skip(PML4_INDEX_UPPER_HALF)
+    {
+        if current.is_unused() {
+            new.set_frame(
+                alloc_empty_table(),
+                PageTableFlags::PRESENT | PageTableFlags::WRITABLE,
+            );
+        } else {
+            *new = current.clone();
+        }
+    }
+    new[PML4_INDEX_RECURSIVE].set_frame(frame, PageTableFlags::PRESENT | PageTableFlags::WRITABLE);
+
+    let (_, flags) = Cr3::read();
+    // Safety: The new PML4 maps everything the kernel uses, and the recursive entry points to it.
+    unsafe { Cr3::write(frame, flags) }
+
+    KERNEL_PML4
+        .try_init_once(|| frame)
+        .expect("PML4 is already replaced.");
+}
+
+pub fn kernel_pml4() -> PhysFrame {
+    *KERNEL_PML4.try_get().expect("PML4 is not replaced yet.")
+}
+
+// Returns a PML4 whose upper half is shared with the kernel and whose lower half is empty.
+pub fn new_pml4() -> Option<PhysFrame> {
+    let frame =
+        PhysFrame::from_start_address(FRAME_MANAGER.lock().alloc(NumOfPages::new(1), Tag::User)?)
+            .unwrap();
+
+    // Safety: The frame was just allocated and no one uses it. The kernel PML4 is never edited
+    // after `replace_pml4`.
+    let (new, kernel) = unsafe { (table_mut(frame), table_mut(kernel_pml4())) };
+
+    new.zero();
+    for (new, kernel) in new
+        .iter_mut()
+        .zip(kernel.iter())
+        .take(PML4_INDEX_RECURSIVE)
+        .skip(PML4_INDEX_UPPER_HALF)
+    {
+        *new = kernel.clone();
+    }
+    new[PML4_INDEX_RECURSIVE].set_frame(frame, PageTableFlags::PRESENT | PageTableFlags::WRITABLE);
+
+    Some(frame)
+}
+
+// Call this with interrupts disabled. The upper half is the same in every PML4, so the kernel
+// keeps running.
+pub fn activate(pml4: PhysFrame) {
+    let (current, flags) = Cr3::read();
+
+    if current != pml4 {
+        // Safety: `pml4` is the kernel PML4 or was created by `new_pml4`.
+        unsafe { Cr3::write(pml4, flags) }
     }
 }
+
+// Safety: `frame` must be a page table which no one else accesses while the returned reference
+// lives.
+pub unsafe fn table_mut<'a>(frame: PhysFrame) -> &'a mut PageTable {
+    &mut *(phys_to_virt(frame.start_address()).as_mut_ptr() as *mut PageTable)
+}
+
+fn alloc_empty_table() -> PhysFrame {
+    let frame = FRAME_MANAGER
+        .lock()
+        .allocate_frame()
+        .expect("Failed to allocate a page table.");
+
+    // Safety: The frame was just allocated and no one uses it.
+    unsafe { table_mut(frame) }.zero();
+    frame
+}
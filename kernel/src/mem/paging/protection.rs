@@ -0,0 +1,201 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use {
+    super::{pml4::PML4, PML4_INDEX_RECURSIVE},
+    common::{
+        constant::{KERNEL_ADDR, RECUR_PML4_ADDR, STACK_BASE, STACK_LOWER, VRAM_ADDR},
+        kernelboot,
+    },
+    core::convert::TryFrom,
+    x86_64::{
+        registers::{
+            control::{Cr0, Cr0Flags},
+            model_specific::{Efer, EferFlags},
+        },
+        structures::paging::{Mapper, Page, PageSize, PageTable, PageTableFlags, Size4KiB},
+        VirtAddr,
+    },
+};
+
+// Defined in `os.ld`.
+extern "C" {
+    static __text_start: u8;
+    static __text_end: u8;
+    static __data_start: u8;
+    static __kernel_end: u8;
+}
+
+const NUM_OF_ENTRIES: usize = 512;
+// The kernel is mapped through the recursive entry. `PML4[510]` works as the page directory of
+// `0xffff_ffff_8000_0000..0xffff_ffff_c000_0000`.
+const PML4_INDEX_KERNEL: usize = 510;
+
+// This must be called before any mapping sets `NO_EXECUTE`. Otherwise the bit is treated as a
+// reserved one and accessing the page causes a page fault.
+pub fn enable_nx() {
+    // Safety: Enabling NX does not change the meaning of any existing mapping.
+    unsafe { Efer::update(|flags| flags.insert(EferFlags::NO_EXECUTE_ENABLE)) }
+}
+
+// bootx64 maps the kernel, the stack and VRAM as writable and executable.
+pub fn protect_kernel(boot_info: &kernelboot::Info) {
+    let (text_start, text_end, data_start, kernel_end) = unsafe {
+        (
+            VirtAddr::from_ptr(&__text_start),
+            VirtAddr::from_ptr(&__text_end).align_up(Size4KiB::SIZE),
+            VirtAddr::from_ptr(&__data_start),
+            VirtAddr::from_ptr(&__kernel_end),
+        )
+    };
+
+    let read_only = PageTableFlags::PRESENT | PageTableFlags::NO_EXECUTE;
+    let read_write = read_only | PageTableFlags::WRITABLE;
+
+    // ELF headers.
+    update_flags(KERNEL_ADDR, text_start, read_only);
+    update_flags(text_start, text_end, PageTableFlags::PRESENT);
+    // `.rodata` and `.eh_frame`.
+    update_flags(text_end, data_start, read_only);
+    // `.data` and `.bss`.
+    update_flags(data_start, kernel_end, read_write);
+
+    update_flags(STACK_LOWER, STACK_BASE, read_write);
+    update_flags(
+        VRAM_ADDR,
+        VRAM_ADDR + boot_info.vram().bytes().as_usize(),
+        read_write,
+    );
+}
+
+// All page tables must be reachable through writable entries before calling this.
+pub fn enable_write_protection() {
+    // Safety: The kernel never writes to read-only pages on purpose.
+    unsafe { Cr0::update(|flags| flags.insert(Cr0Flags::WRITE_PROTECT)) }
+}
+
+pub fn report_wx_pages() {
+    let num_of_pages = count_wx_pages();
+
+    if num_of_pages == 0 {
+        info!("W^X: No page is both writable and executable.");
+    } else {
+        warn!(
+            "W^X: {} pages are both writable and executable.",
+            num_of_pages
+        );
+    }
+}
+
+fn update_flags(start: VirtAddr, end: VirtAddr, flags: PageTableFlags) {
+    let pages = Page::<Size4KiB>::range(
+        Page::containing_address(start),
+        Page::containing_address(end.align_up(Size4KiB::SIZE)),
+    );
+
+    let mut pml4 = PML4.lock();
+    for page in pages {
+        // Safety: The page is mapped by bootx64, and only its permission changes.
+        unsafe { pml4.update_flags(page, flags) }
+            .unwrap_or_else(|e| panic!("Failed to update the flags of {:?}: {:?}", page, e))
+            .flush();
+    }
+}
+
+// The recursive entry itself is skipped. It is a writable window to the page tables, not a code
+// mapping.
+fn count_wx_pages() -> usize {
+    let pml4 = unsafe { table(RECUR_PML4_ADDR) };
+    let recursive = Access::of(pml4[PML4_INDEX_RECURSIVE].flags());
+
+    let kernel = scan(
+        child_table_addr(RECUR_PML4_ADDR, PML4_INDEX_KERNEL),
+        2,
+        KERNEL_ADDR.as_u64(),
+        recursive.child(pml4[PML4_INDEX_KERNEL].flags()),
+    );
+
+    let others: usize = (0..NUM_OF_ENTRIES)
+        .filter(|&i| i != PML4_INDEX_RECURSIVE && i != PML4_INDEX_KERNEL)
+        .filter(|&i| pml4[i].flags().contains(PageTableFlags::PRESENT))
+        .map(|i| {
+            scan(
+                child_table_addr(RECUR_PML4_ADDR, i),
+                3,
+                bytes_per_entry(4) * u64::try_from(i).unwrap(),
+                Access::of(pml4[i].flags()),
+            )
+        })
+        .sum();
+
+    kernel + others
+}
+
+// `table_addr` is the address of the table through the recursive mapping, and `base` is the first
+// address the table maps.
+fn scan(table_addr: VirtAddr, level: u8, base: u64, parent: Access) -> usize {
+    let table = unsafe { table(table_addr) };
+    let mut num_of_pages = 0;
+
+    for (i, entry) in table.iter().enumerate() {
+        let flags = entry.flags();
+        if !flags.contains(PageTableFlags::PRESENT) {
+            continue;
+        }
+
+        let access = parent.child(flags);
+        let addr = base + bytes_per_entry(level) * u64::try_from(i).unwrap();
+
+        if level == 1 || flags.contains(PageTableFlags::HUGE_PAGE) {
+            if access.writable && access.executable {
+                warn!("W^X: {:?} is writable and executable.", canonical(addr));
+                num_of_pages += 1;
+            }
+        } else {
+            num_of_pages += scan(child_table_addr(table_addr, i), level - 1, addr, access);
+        }
+    }
+
+    num_of_pages
+}
+
+/// Safety: `addr` must be the address of a page table through the recursive mapping.
+unsafe fn table<'a>(addr: VirtAddr) -> &'a PageTable {
+    &*addr.as_ptr()
+}
+
+fn child_table_addr(table_addr: VirtAddr, index: usize) -> VirtAddr {
+    canonical(table_addr.as_u64() << 9 | u64::try_from(index).unwrap() << 12)
+}
+
+fn bytes_per_entry(level: u8) -> u64 {
+    Size4KiB::SIZE << (9 * (level - 1))
+}
+
+fn canonical(addr: u64) -> VirtAddr {
+    VirtAddr::new_truncate(addr)
+}
+
+#[derive(Copy, Clone)]
+struct Access {
+    writable: bool,
+    executable: bool,
+}
+impl Access {
+    fn of(flags: PageTableFlags) -> Self {
+        Self {
+            writable: flags.contains(PageTableFlags::WRITABLE),
+            executable: !flags.contains(PageTableFlags::NO_EXECUTE),
+        }
+    }
+
+    // A page is writable only if every level allows writing, and it is executable only if no level
+    // forbids executing.
+    fn child(self, flags: PageTableFlags) -> Self {
+        let child = Self::of(flags);
+
+        Self {
+            writable: self.writable && child.writable,
+            executable: self.executable && child.executable,
+        }
+    }
+}
@@ -0,0 +1,45 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Page tables are edited through the recursive mapping directly where `PML4` cannot be locked,
+// for example in the page fault handler.
+
+use {
+    core::convert::TryFrom,
+    x86_64::{
+        structures::paging::{Page, PageTable, PageTableEntry, PageTableFlags, Size4KiB},
+        VirtAddr,
+    },
+};
+
+// `level` 4 is PML4, and 1 is a page table.
+pub fn table_of<'a>(page: Page<Size4KiB>, level: u8) -> &'a mut PageTable {
+    const ADDR_MASK: u64 = 0xffff_ffff_f000;
+
+    // The top `level` indices of the address are the recursive index, and the rest are the
+    // upper indices of `page`.
+    let shift = 9 * u64::from(level);
+    let recursive_part = ADDR_MASK & !((1 << (48 - shift)) - 1);
+    let page_part = (page.start_address().as_u64() & ADDR_MASK) >> shift & !0xfff;
+
+    let addr = VirtAddr::new_truncate(recursive_part | page_part);
+
+    // Safety: The recursive entry maps every page table to this address.
+    unsafe { &mut *addr.as_mut_ptr() }
+}
+
+pub fn index_of(page: Page<Size4KiB>, level: u8) -> usize {
+    let shift = 12 + 9 * (u64::from(level) - 1);
+    usize::try_from((page.start_address().as_u64() >> shift) & 0x1ff).unwrap()
+}
+
+// Returns `None` if any table on the way is not present.
+pub fn leaf_entry<'a>(page: Page<Size4KiB>) -> Option<&'a mut PageTableEntry> {
+    for level in (2..=4).rev() {
+        let entry = &table_of(page, level)[index_of(page, level)];
+        if !entry.flags().contains(PageTableFlags::PRESENT) {
+            return None;
+        }
+    }
+
+    Some(&mut table_of(page, 1)[index_of(page, 1)])
+}
@@ -0,0 +1,67 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use {
+    super::allocator::phys::{FrameManager, FRAME_MANAGER},
+    common::mem::reserved,
+    core::convert::TryFrom,
+    os_units::{Bytes, NumOfPages},
+    uefi::table::boot::{self, MemoryType},
+    x86_64::{
+        structures::paging::{PageSize, Size4KiB},
+        PhysAddr,
+    },
+};
+
+// UEFI's code and data, and bootx64 itself, are no longer needed once the kernel has copied the
+// memory map and switched to its own PML4. Only the kernel image, the stack and VRAM in the
+// reserved map are kept. ACPI tables are in `ACPI_RECLAIM` and `ACPI_NON_VOLATILE` memory, which
+// is never reclaimed here.
+pub fn boot_memory(mem_map: &[boot::MemoryDescriptor], reserved: &reserved::Map) -> Bytes {
+    let mut frame_manager = FRAME_MANAGER.lock();
+
+    let num_of_pages: usize = mem_map
+        .iter()
+        .filter(|d| reclaimable(d.ty))
+        .map(|d| reclaim_descriptor(&mut frame_manager, d, reserved))
+        .sum();
+
+    NumOfPages::<Size4KiB>::new(num_of_pages).as_bytes()
+}
+
+fn reclaimable(ty: MemoryType) -> bool {
+    ty == MemoryType::BOOT_SERVICES_CODE
+        || ty == MemoryType::BOOT_SERVICES_DATA
+        || ty == MemoryType::LOADER_CODE
+        || ty == MemoryType::LOADER_DATA
+}
+
+// Frames in the reserved map split the descriptor into several runs.
+fn reclaim_descriptor(
+    frame_manager: &mut FrameManager,
+    descriptor: &boot::MemoryDescriptor,
+    reserved: &reserved::Map,
+) -> usize {
+    let page_count = usize::try_from(descriptor.page_count).unwrap();
+    let mut num_of_reclaimed = 0;
+    let mut run_start = None;
+
+    for i in 0..=page_count {
+        let addr =
+            PhysAddr::new(descriptor.phys_start + Size4KiB::SIZE * u64::try_from(i).unwrap());
+        let free = i < page_count && !reserved.iter().any(|r| r.contains(addr));
+
+        match (free, run_start) {
+            (true, None) => run_start = Some(addr),
+            (false, Some(start)) => {
+                let num_of_pages = usize::try_from((addr - start) / Size4KiB::SIZE).unwrap();
+                frame_manager.add(start, NumOfPages::new(num_of_pages));
+
+                num_of_reclaimed += num_of_pages;
+                run_start = None;
+            }
+            _ => {}
+        }
+    }
+
+    num_of_reclaimed
+}
@@ -0,0 +1,60 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Enabled by the `swap_test` feature. Fills swappable memory larger than the free memory so that
+// most of its pages are paged out, and checks that they are read back intact.
+
+use {
+    super::{super::allocator::phys::FRAME_MANAGER, balance, stats, Memory},
+    core::convert::TryFrom,
+    os_units::Bytes,
+    x86_64::structures::paging::{PageSize, Size4KiB},
+};
+
+// Beyond the free memory, so that pages must be paged out to touch all of them.
+const BYTES_OVER_FREE: usize = 16 * 1024 * 1024;
+
+pub fn run() {
+    let bytes_free = match FRAME_MANAGER.try_lock() {
+        Some(frame_manager) => frame_manager.stats().bytes_free,
+        None => return,
+    };
+    let bytes = bytes_free + BYTES_OVER_FREE;
+
+    let mut memory = match Memory::new(Bytes::new(bytes)) {
+        Some(memory) => memory,
+        None => {
+            warn!("Swap test: Failed to allocate {} KiB.", bytes / 1024);
+            return;
+        }
+    };
+
+    let bytes_per_page = usize::try_from(Size4KiB::SIZE).unwrap();
+
+    // The executor does not go idle meanwhile, so pages are paged out here.
+    for (i, page) in memory.chunks_mut(bytes_per_page).enumerate() {
+        for byte in page {
+            *byte = pattern(i);
+        }
+        balance();
+    }
+
+    let mut num_of_broken_pages = 0;
+    for (i, page) in memory.chunks(bytes_per_page).enumerate() {
+        if page.iter().any(|&byte| byte != pattern(i)) {
+            num_of_broken_pages += 1;
+        }
+        balance();
+    }
+
+    info!(
+        "Swap test: {} of {} pages are broken. {}",
+        num_of_broken_pages,
+        bytes / bytes_per_page,
+        stats().unwrap_or_default()
+    );
+}
+
+// Never zero, so that a page which is lost and filled with zeros again is found.
+fn pattern(page_index: usize) -> u8 {
+    u8::try_from(page_index % 255).unwrap() + 1
+}
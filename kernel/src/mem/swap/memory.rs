@@ -0,0 +1,136 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use {
+    super::{
+        super::{
+            allocator::{
+                phys::FRAME_MANAGER,
+                virt::{self, Window},
+            },
+            vma::{self, Area},
+        },
+        release,
+    },
+    alloc::vec::Vec,
+    conquer_once::spin::Lazy,
+    core::{
+        convert::TryFrom,
+        ops::{Deref, DerefMut},
+        slice,
+    },
+    os_units::{Bytes, NumOfPages},
+    spinning_top::Spinlock,
+    x86_64::{
+        instructions::interrupts,
+        structures::paging::{Page, PageSize, PageTableFlags, Size4KiB},
+        VirtAddr,
+    },
+};
+
+// Regions whose pages could not be released because `FrameManager` was locked.
+static DEFERRED: Lazy<Spinlock<Vec<(VirtAddr, NumOfPages<Size4KiB>)>>> =
+    Lazy::new(|| Spinlock::new(Vec::new()));
+
+// Anonymous memory whose pages may be paged out while they are not touched. Each page is mapped
+// and filled with zeros when it is touched first.
+//
+// Do not touch this memory while interrupts are disabled or while `FrameManager`, `PML4` or the
+// list of memory areas is locked, because the page fault handler may read the disk.
+pub struct Memory {
+    start: VirtAddr,
+    num_of_pages: NumOfPages<Size4KiB>,
+}
+impl Memory {
+    pub fn new(bytes: Bytes) -> Option<Self> {
+        free_deferred_regions();
+
+        let num_of_pages = bytes.as_num_of_pages::<Size4KiB>();
+        let start = virt::alloc(Window::Vmalloc, num_of_pages)?;
+
+        let flags = PageTableFlags::PRESENT | PageTableFlags::WRITABLE | PageTableFlags::NO_EXECUTE;
+        let area = Area::anonymous(start, num_of_pages, flags, "swappable memory").swappable();
+
+        if interrupts::without_interrupts(|| vma::KERNEL.lock().add(area)).is_err() {
+            if let Err(e) = virt::free(start, num_of_pages) {
+                warn!("Leaking the virtual addresses at {:?}: {:?}", start, e);
+            }
+            return None;
+        }
+
+        Some(Self {
+            start,
+            num_of_pages,
+        })
+    }
+
+    fn len(&self) -> usize {
+        self.num_of_pages.as_bytes().as_usize()
+    }
+}
+impl Deref for Memory {
+    type Target = [u8];
+    fn deref(&self) -> &Self::Target {
+        // Safety: The area is registered, so every page is mapped when it is touched.
+        unsafe { slice::from_raw_parts(self.start.as_ptr(), self.len()) }
+    }
+}
+impl DerefMut for Memory {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // Safety: Same as `deref`.
+        unsafe { slice::from_raw_parts_mut(self.start.as_mut_ptr(), self.len()) }
+    }
+}
+impl Drop for Memory {
+    fn drop(&mut self) {
+        // The area is removed first so that no page is paged out in the meantime.
+        interrupts::without_interrupts(|| vma::KERNEL.lock().remove(self.start));
+
+        if !free_region_if_possible(self.start, self.num_of_pages) {
+            DEFERRED.lock().push((self.start, self.num_of_pages));
+        }
+    }
+}
+
+pub(super) fn free_deferred_regions() {
+    loop {
+        let region = DEFERRED.lock().pop();
+        let (start, num_of_pages) = match region {
+            Some(region) => region,
+            None => return,
+        };
+
+        if !free_region_if_possible(start, num_of_pages) {
+            DEFERRED.lock().push((start, num_of_pages));
+            return;
+        }
+    }
+}
+
+// Interrupts are disabled while pages are released, so a preempted thread may hold `FrameManager`
+// and it is not waited for.
+fn free_region_if_possible(start: VirtAddr, num_of_pages: NumOfPages<Size4KiB>) -> bool {
+    let released = interrupts::without_interrupts(|| {
+        let mut frame_manager = match FRAME_MANAGER.try_lock() {
+            Some(frame_manager) => frame_manager,
+            None => return false,
+        };
+
+        for i in 0..num_of_pages.as_usize() {
+            let offset = Size4KiB::SIZE * u64::try_from(i).unwrap();
+            release(
+                Page::from_start_address(start + offset).unwrap(),
+                &mut frame_manager,
+            );
+        }
+
+        true
+    });
+
+    if released {
+        if let Err(e) = virt::free(start, num_of_pages) {
+            warn!("Leaking the virtual addresses at {:?}: {:?}", start, e);
+        }
+    }
+
+    released
+}
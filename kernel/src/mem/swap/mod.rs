@@ -0,0 +1,339 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+#[cfg(feature = "swap_test")]
+pub mod exercise;
+mod memory;
+
+pub use memory::Memory;
+
+use {
+    super::{
+        allocator::phys::{FrameManager, FRAME_MANAGER},
+        paging::recursive,
+        phys_to_virt,
+        vma::{self, Area},
+    },
+    crate::device::block::{self, gpt, BYTES_PER_SECTOR},
+    alloc::{boxed::Box, vec, vec::Vec},
+    conquer_once::spin::Lazy,
+    core::{convert::TryFrom, fmt, slice},
+    spinning_top::Spinlock,
+    x86_64::{
+        instructions::{interrupts, tlb},
+        structures::paging::{Page, PageSize, PageTableEntry, PageTableFlags, PhysFrame, Size4KiB},
+        PhysAddr,
+    },
+};
+
+// Pages are paged out only while free memory is below this.
+const BYTES_LOW_WATERMARK: usize = 4 * 1024 * 1024;
+const MAX_PAGES_OUT_PER_BALANCE: usize = 64;
+
+const BYTES_PER_PAGE: usize = 4096;
+const SECTORS_PER_PAGE: usize = BYTES_PER_PAGE / BYTES_PER_SECTOR;
+
+// The entry of a swapped-out page is not present, has this bit, and has the slot number in its
+// address field.
+const SWAPPED_OUT: PageTableFlags = PageTableFlags::BIT_9;
+
+static SWAP: Lazy<Spinlock<Option<Swap>>> = Lazy::new(|| Spinlock::new(None));
+
+pub fn init(device: Box<dyn block::Device + Send>, partition: gpt::Partition) {
+    let swap = Swap::new(device, partition);
+    let bytes_total = swap.stats().bytes_total;
+
+    interrupts::without_interrupts(|| *SWAP.lock() = Some(swap));
+
+    info!(
+        "Swap: {} KiB on LBA {}..={}",
+        bytes_total / 1024,
+        partition.first_lba,
+        partition.last_lba
+    );
+}
+
+pub fn stats() -> Option<Stats> {
+    interrupts::without_interrupts(|| SWAP.lock().as_ref().map(Swap::stats))
+}
+
+pub fn slot_of(entry: &PageTableEntry) -> Option<Slot> {
+    let flags = entry.flags();
+
+    if flags.contains(PageTableFlags::PRESENT) || !flags.contains(SWAPPED_OUT) {
+        None
+    } else {
+        Some(Slot(
+            usize::try_from(entry.addr().as_u64() / Size4KiB::SIZE).unwrap(),
+        ))
+    }
+}
+
+// Called by the page fault handler. The slot is freed after reading.
+pub fn page_in(slot: Slot, frame: PhysFrame) {
+    let mut swap = SWAP
+        .try_lock()
+        .expect("Page fault while the swap area is locked.");
+    let swap = swap
+        .as_mut()
+        .expect("A page is swapped out, but there is no swap area.");
+
+    if let Err(e) = swap.read(slot, frame) {
+        panic!("Failed to read {:?} from the swap area: {:?}", slot, e);
+    }
+
+    swap.free_slot(slot);
+    swap.stats.pages_in += 1;
+}
+
+// Call this when the CPU is idle. Pages of swappable areas are paged out if free memory is low.
+//
+// Interrupts are disabled while the swap area is locked so that nothing which may touch a
+// swapped-out page runs in the meantime.
+pub fn balance() {
+    memory::free_deferred_regions();
+
+    let bytes_free = match FRAME_MANAGER.try_lock() {
+        Some(frame_manager) => frame_manager.stats().bytes_free,
+        None => return,
+    };
+    if bytes_free >= BYTES_LOW_WATERMARK {
+        return;
+    }
+
+    let areas = interrupts::without_interrupts(|| vma::KERNEL.lock().swappable_areas());
+
+    let result = interrupts::without_interrupts(|| {
+        let mut swap = SWAP.try_lock()?;
+        let mut frame_manager = FRAME_MANAGER.try_lock()?;
+
+        Some(swap.as_mut()?.page_out_some(&mut frame_manager, &areas))
+    });
+
+    if let Some(Err(e)) = result {
+        warn!("Swap: Failed to page out: {:?}", e);
+    }
+}
+
+// Frees the frame or the slot which backs `page`, and makes the entry unused.
+fn release(page: Page<Size4KiB>, frame_manager: &mut FrameManager) {
+    let entry = match recursive::leaf_entry(page) {
+        Some(entry) => entry,
+        None => return,
+    };
+
+    if entry.flags().contains(PageTableFlags::PRESENT) {
+        frame_manager.free(entry.addr());
+    } else if let Some(slot) = slot_of(entry) {
+        if let Some(swap) = SWAP.lock().as_mut() {
+            swap.free_slot(slot);
+        }
+    }
+
+    entry.set_unused();
+    tlb::flush(page.start_address());
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Slot(usize);
+
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Stats {
+    pub bytes_total: usize,
+    pub bytes_used: usize,
+    pub pages_out: usize,
+    pub pages_in: usize,
+    // Clean pages are dropped instead of being written, and are filled with zeros again.
+    pub pages_dropped: usize,
+}
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} KiB used of {} KiB, {} pages out, {} pages in, {} pages dropped",
+            self.bytes_used / 1024,
+            self.bytes_total / 1024,
+            self.pages_out,
+            self.pages_in,
+            self.pages_dropped
+        )
+    }
+}
+
+#[derive(Debug)]
+enum Error {
+    Full,
+    Device(block::Error),
+}
+impl From<block::Error> for Error {
+    fn from(e: block::Error) -> Self {
+        Self::Device(e)
+    }
+}
+
+struct Swap {
+    device: Box<dyn block::Device + Send>,
+    partition: gpt::Partition,
+    // One bit per slot. A set bit means the slot is used.
+    bitmap: Vec<u64>,
+    num_of_slots: usize,
+    // The position of the clock hand in the pages of all swappable areas.
+    hand: usize,
+    stats: Stats,
+}
+impl Swap {
+    fn new(device: Box<dyn block::Device + Send>, partition: gpt::Partition) -> Self {
+        let num_of_sectors = usize::try_from(partition.num_of_sectors()).unwrap();
+        let num_of_slots = num_of_sectors / SECTORS_PER_PAGE;
+
+        Self {
+            device,
+            partition,
+            bitmap: vec![0; (num_of_slots + 63) / 64],
+            num_of_slots,
+            hand: 0,
+            stats: Stats {
+                bytes_total: num_of_slots * BYTES_PER_PAGE,
+                ..Stats::default()
+            },
+        }
+    }
+
+    fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    // A clock algorithm. An accessed page gets a second chance, and the hand goes around at most
+    // twice.
+    fn page_out_some(
+        &mut self,
+        frame_manager: &mut FrameManager,
+        areas: &[Option<Area>],
+    ) -> Result<(), Error> {
+        let num_of_pages: usize = areas
+            .iter()
+            .flatten()
+            .map(|a| a.num_of_pages().as_usize())
+            .sum();
+        if num_of_pages == 0 {
+            return Ok(());
+        }
+
+        let mut num_of_paged_out = 0;
+
+        for _ in 0..num_of_pages * 2 {
+            if num_of_paged_out == MAX_PAGES_OUT_PER_BALANCE {
+                break;
+            }
+
+            self.hand = (self.hand + 1) % num_of_pages;
+            let page = Self::nth_page(areas, self.hand);
+
+            if self.page_out(page, frame_manager)? {
+                num_of_paged_out += 1;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Returns true if the frame of `page` is freed.
+    fn page_out(
+        &mut self,
+        page: Page<Size4KiB>,
+        frame_manager: &mut FrameManager,
+    ) -> Result<bool, Error> {
+        let entry = match recursive::leaf_entry(page) {
+            Some(entry) if entry.flags().contains(PageTableFlags::PRESENT) => entry,
+            _ => return Ok(false),
+        };
+
+        let flags = entry.flags();
+        let frame = PhysFrame::containing_address(entry.addr());
+
+        if flags.contains(PageTableFlags::ACCESSED) {
+            entry.set_flags(flags - PageTableFlags::ACCESSED);
+            tlb::flush(page.start_address());
+            return Ok(false);
+        }
+
+        if flags.contains(PageTableFlags::DIRTY) {
+            let slot = self.alloc_slot().ok_or(Error::Full)?;
+            if let Err(e) = self.write(slot, frame) {
+                self.free_slot(slot);
+                return Err(e.into());
+            }
+
+            let slot_addr = Size4KiB::SIZE * u64::try_from(slot.0).unwrap();
+            entry.set_addr(PhysAddr::new(slot_addr), SWAPPED_OUT);
+            self.stats.pages_out += 1;
+        } else {
+            entry.set_unused();
+            self.stats.pages_dropped += 1;
+        }
+
+        tlb::flush(page.start_address());
+        frame_manager.free(frame.start_address());
+
+        Ok(true)
+    }
+
+    fn nth_page(areas: &[Option<Area>], mut n: usize) -> Page<Size4KiB> {
+        for area in areas.iter().flatten() {
+            let num_of_pages = area.num_of_pages().as_usize();
+            if n < num_of_pages {
+                let offset = Size4KiB::SIZE * u64::try_from(n).unwrap();
+                return Page::from_start_address(area.start() + offset).unwrap();
+            }
+            n -= num_of_pages;
+        }
+
+        unreachable!("The page index is out of the swappable areas.");
+    }
+
+    fn read(&mut self, slot: Slot, frame: PhysFrame) -> Result<(), block::Error> {
+        let lba = self.lba(slot);
+        let buf = Self::frame_as_slice(frame);
+
+        self.device.read(lba, buf)
+    }
+
+    fn write(&mut self, slot: Slot, frame: PhysFrame) -> Result<(), block::Error> {
+        let lba = self.lba(slot);
+        let buf = Self::frame_as_slice(frame);
+
+        self.device.write(lba, buf)
+    }
+
+    fn lba(&self, slot: Slot) -> u64 {
+        self.partition.first_lba + u64::try_from(slot.0 * SECTORS_PER_PAGE).unwrap()
+    }
+
+    // The direct map is used so that the buffer is physically contiguous, and translating it does
+    // not lock `PML4`.
+    fn frame_as_slice<'a>(frame: PhysFrame) -> &'a mut [u8] {
+        let virt = phys_to_virt(frame.start_address());
+
+        // Safety: The frame is not used by anyone else while it is read or written.
+        unsafe { slice::from_raw_parts_mut(virt.as_mut_ptr(), BYTES_PER_PAGE) }
+    }
+
+    fn alloc_slot(&mut self) -> Option<Slot> {
+        let i = (0..self.num_of_slots).find(|&i| self.bitmap[i / 64] & (1 << (i % 64)) == 0)?;
+
+        self.bitmap[i / 64] |= 1 << (i % 64);
+        self.stats.bytes_used += BYTES_PER_PAGE;
+        Some(Slot(i))
+    }
+
+    fn free_slot(&mut self, slot: Slot) {
+        let Slot(i) = slot;
+        assert!(
+            self.bitmap[i / 64] & (1 << (i % 64)) != 0,
+            "Double free of swap slot {}",
+            i
+        );
+
+        self.bitmap[i / 64] &= !(1 << (i % 64));
+        self.stats.bytes_used -= BYTES_PER_PAGE;
+    }
+}
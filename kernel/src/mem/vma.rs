@@ -0,0 +1,178 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use {
+    conquer_once::spin::Lazy,
+    core::{convert::TryFrom, fmt},
+    os_units::NumOfPages,
+    spinning_top::Spinlock,
+    x86_64::{
+        structures::paging::{PageSize, PageTableFlags, Size4KiB},
+        VirtAddr,
+    },
+};
+
+// The page fault handler looks up this list. It must not allocate memory on the heap because
+// the heap itself grows through page faults.
+const MAX_AREAS: usize = 64;
+
+// Lock this with interrupts disabled. The page fault handler panics if a preempted thread holds
+// the lock.
+pub static KERNEL: Lazy<Spinlock<AddressSpace>> = Lazy::new(|| Spinlock::new(AddressSpace::new()));
+
+pub struct AddressSpace {
+    areas: [Option<Area>; MAX_AREAS],
+}
+impl AddressSpace {
+    pub fn new() -> Self {
+        Self {
+            areas: [None; MAX_AREAS],
+        }
+    }
+
+    pub fn add(&mut self, area: Area) -> Result<(), Error> {
+        if self.areas.iter().flatten().any(|a| a.overlaps(&area)) {
+            return Err(Error::Overlapping);
+        }
+
+        let slot = self
+            .areas
+            .iter_mut()
+            .find(|a| a.is_none())
+            .ok_or(Error::TooManyAreas)?;
+        *slot = Some(area);
+
+        Ok(())
+    }
+
+    pub fn remove(&mut self, start: VirtAddr) -> Option<Area> {
+        self.areas
+            .iter_mut()
+            .find(|a| matches!(a, Some(a) if a.start == start))?
+            .take()
+    }
+
+    // A copy is returned so that the caller can touch memory without holding the lock. The page
+    // fault handler panics if the list is locked.
+    pub fn swappable_areas(&self) -> [Option<Area>; MAX_AREAS] {
+        let mut areas = [None; MAX_AREAS];
+        for (dst, src) in areas
+            .iter_mut()
+            .zip(self.areas.iter().flatten().filter(|a| a.swappable))
+        {
+            *dst = Some(*src);
+        }
+        areas
+    }
+
+    pub fn find(&self, addr: VirtAddr) -> Option<Area> {
+        self.areas
+            .iter()
+            .flatten()
+            .find(|a| a.contains(addr))
+            .copied()
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Area {
+    start: VirtAddr,
+    num_of_pages: NumOfPages<Size4KiB>,
+    flags: PageTableFlags,
+    name: &'static str,
+    swappable: bool,
+}
+impl Area {
+    #[allow(clippy::too_many_arguments)]
+    pub fn anonymous(
+        start: VirtAddr,
+        num_of_pages: NumOfPages<Size4KiB>,
+        flags: PageTableFlags,
+        name: &'static str,
+    ) -> Self {
+        Self {
+            start,
+            num_of_pages,
+            flags,
+            name,
+            swappable: false,
+        }
+    }
+
+    // Pages of the area may be paged out to the swap area. Only mark memory which is never touched
+    // while interrupts are disabled or locks which the page fault handler needs are held.
+    pub fn swappable(self) -> Self {
+        Self {
+            swappable: true,
+            ..self
+        }
+    }
+
+    pub fn start(&self) -> VirtAddr {
+        self.start
+    }
+
+    pub fn num_of_pages(&self) -> NumOfPages<Size4KiB> {
+        self.num_of_pages
+    }
+
+    pub fn flags(&self) -> PageTableFlags {
+        self.flags
+    }
+
+    pub fn allows(&self, access: Access) -> bool {
+        let writable = self.flags.contains(PageTableFlags::WRITABLE);
+        let executable = !self.flags.contains(PageTableFlags::NO_EXECUTE);
+        let user = self.flags.contains(PageTableFlags::USER_ACCESSIBLE);
+
+        match access {
+            Access::Read { by_user } => user || !by_user,
+            Access::Write { by_user } => writable && (user || !by_user),
+            Access::Execute { by_user } => executable && (user || !by_user),
+        }
+    }
+
+    fn end(&self) -> VirtAddr {
+        self.start + Size4KiB::SIZE * u64::try_from(self.num_of_pages.as_usize()).unwrap()
+    }
+
+    fn contains(&self, addr: VirtAddr) -> bool {
+        self.start <= addr && addr < self.end()
+    }
+
+    fn overlaps(&self, other: &Self) -> bool {
+        self.start < other.end() && other.start < self.end()
+    }
+}
+impl fmt::Display for Area {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "\"{}\" {:?}..{:?} ({:?}{})",
+            self.name,
+            self.start,
+            self.end(),
+            self.flags,
+            if self.swappable { ", swappable" } else { "" }
+        )
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum Access {
+    Read { by_user: bool },
+    Write { by_user: bool },
+    Execute { by_user: bool },
+}
+impl Access {
+    pub fn by_user(self) -> bool {
+        match self {
+            Self::Read { by_user } | Self::Write { by_user } | Self::Execute { by_user } => by_user,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Overlapping,
+    TooManyAreas,
+}
@@ -1,48 +1,93 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use {
-    super::task,
+    super::{spawner::Spawner, task, thread},
+    crate::mem::swap,
     alloc::{collections::BTreeMap, rc::Rc},
     core::{
         cell::RefCell,
         task::{Context, Poll, Waker},
     },
-    x86_64::instructions::interrupts,
 };
 
+// The number of times a task may be polled in a round. A round ends when no task which has budget
+// left is woken.
+const POLL_BUDGET: u32 = 4;
+
 pub struct Executor {
     task_collection: Rc<RefCell<task::Collection>>,
     waker_collection: BTreeMap<task::Id, Waker>,
+    isolates_panics: bool,
+    polls_in_round: BTreeMap<task::Id, u32>,
 }
 
 impl Executor {
-    pub fn new(task_collection: Rc<RefCell<task::Collection>>) -> Self {
+    pub fn new() -> Self {
+        Self::with_collection(Rc::new(RefCell::new(task::Collection::new())))
+    }
+
+    // A task which panics is removed, and the executor continues in a new thread. The stack of
+    // the task is abandoned without unwinding, so the memory it owns leaks and the locks it holds
+    // are never released. The kernel still halts if the panic happens while the heap or the
+    // scheduler is locked.
+    pub fn isolate_panics(self) -> Self {
         Self {
-            task_collection,
-            waker_collection: BTreeMap::new(),
+            isolates_panics: true,
+            ..self
         }
     }
 
+    pub fn spawner(&self) -> Spawner {
+        Spawner::new(self.task_collection.clone())
+    }
+
+    // An executor cannot be sent to another thread because tasks share `Rc`s. Thus `f` creates the
+    // executor in the new thread.
+    pub fn run_in_thread<F>(f: F) -> Result<thread::Id, thread::Error>
+    where
+        F: FnOnce() -> Self + Send + 'static,
+    {
+        thread::spawn(move || {
+            f().run();
+        })
+    }
+
     pub fn run(&mut self) -> ! {
+        self.spawner().register(self.isolates_panics);
+
         loop {
             self.run_woken_tasks();
-            self.sleep_if_idle();
+
+            // Let the other threads run before the next round.
+            if self.task_collection.borrow_mut().requeue_deferred() {
+                thread::yield_now();
+            } else {
+                self.sleep_if_idle();
+            }
         }
     }
 
+    // The thread blocks, and the other threads run meanwhile.
     fn sleep_if_idle(&self) {
-        interrupts::disable();
-        if self.task_collection.borrow().woken_task_exists() {
-            interrupts::enable()
-        } else {
-            interrupts::enable_and_hlt()
-        }
+        swap::balance();
+
+        let wait = self.task_collection.borrow().waiter();
+        wait();
     }
 
     fn run_woken_tasks(&mut self) {
         while let Some(id) = self.pop_woken_task_id() {
+            let polls = self.polls_in_round.entry(id).or_insert(0);
+            if *polls >= POLL_BUDGET {
+                self.task_collection.borrow_mut().defer(id);
+                continue;
+            }
+            *polls += 1;
+
             self.run_task(id);
         }
+
+        self.polls_in_round.clear();
     }
 
     fn pop_woken_task_id(&mut self) -> Option<task::Id> {
@@ -53,6 +98,7 @@ impl Executor {
         let Self {
             task_collection,
             waker_collection,
+            ..
         } = self;
 
         let mut task = match task_collection.borrow_mut().remove_task(id) {
@@ -62,10 +108,15 @@ impl Executor {
 
         let waker = waker_collection
             .entry(id)
-            .or_insert_with(|| task_collection.borrow_mut().create_waker(id));
+            .or_insert_with(|| task_collection.borrow_mut().create_waker(&task));
 
         let mut context = Context::from_waker(waker);
-        match task.poll(&mut context) {
+
+        task_collection.borrow_mut().set_running(Some(&task));
+        let poll = task.poll(&mut context);
+        task_collection.borrow_mut().set_running(None);
+
+        match poll {
             Poll::Ready(_) => {
                 self.task_collection.borrow_mut().remove_task(id);
                 self.waker_collection.remove(&id);
@@ -73,4 +124,52 @@ impl Executor {
             Poll::Pending => self.task_collection.borrow_mut().add_task_as_sleep(task),
         }
     }
+
+    fn with_collection(task_collection: Rc<RefCell<task::Collection>>) -> Self {
+        Self {
+            task_collection,
+            waker_collection: BTreeMap::new(),
+            isolates_panics: false,
+            polls_in_round: BTreeMap::new(),
+        }
+    }
 }
+
+// Called by the panic handler. If the current thread runs an executor which isolates panics, the
+// panicking task is already out of the collection because it is being polled. Its panic hook
+// completes its `JoinHandle`, the executor continues in a new thread with the remaining tasks, and
+// this thread exits. Otherwise this function returns.
+pub fn recover_from_panic() {
+    let spawner = match thread::try_current().and_then(Spawner::take_isolating) {
+        Some(spawner) => spawner,
+        None => return,
+    };
+
+    let collection = spawner.collection();
+    // The collection is unusable if the panic happened while it was borrowed.
+    let (name, hook) = match collection.try_borrow_mut() {
+        Ok(mut c) => (c.running().unwrap_or("unknown"), c.take_panic_hook()),
+        Err(_) => return,
+    };
+    error!("The task \"{}\" panicked and is removed.", name);
+
+    if let Some(hook) = hook {
+        hook();
+    }
+
+    // Safety of `Send`: This thread never runs again, so the `Rc` is only used by the new thread.
+    let collection = AbandonedCollection(collection);
+    let spawned = thread::spawn(move || {
+        let collection = collection;
+        Executor::with_collection(collection.0)
+            .isolate_panics()
+            .run();
+    });
+
+    if spawned.is_ok() {
+        thread::exit();
+    }
+}
+
+struct AbandonedCollection(Rc<RefCell<task::Collection>>);
+unsafe impl Send for AbandonedCollection {}
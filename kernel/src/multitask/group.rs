@@ -0,0 +1,60 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use {
+    super::spawner::{JoinHandle, Spawner},
+    alloc::vec::Vec,
+    core::future::Future,
+};
+
+// Child tasks whose lifetime is bound to the group. Dropping the group cancels every child which
+// is still running, so a driver can tear down all of its tasks at once.
+pub struct TaskGroup {
+    spawner: Spawner,
+    children: Vec<JoinHandle<()>>,
+}
+impl TaskGroup {
+    pub fn new() -> Self {
+        Self::with_spawner(Spawner::current())
+    }
+
+    pub fn with_spawner(spawner: Spawner) -> Self {
+        Self {
+            spawner,
+            children: Vec::new(),
+        }
+    }
+
+    pub fn spawn<F>(&mut self, future: F)
+    where
+        F: Future<Output = ()> + 'static,
+    {
+        self.spawn_named("unnamed", future)
+    }
+
+    pub fn spawn_named<F>(&mut self, name: &'static str, future: F)
+    where
+        F: Future<Output = ()> + 'static,
+    {
+        self.children.retain(|c| !c.is_finished());
+        self.children.push(self.spawner.spawn_named(name, future));
+    }
+
+    // Waits until every child finishes or is cancelled.
+    pub async fn join_all(&mut self) {
+        for child in &mut self.children {
+            let _ = child.await;
+        }
+        self.children.clear();
+    }
+
+    pub fn cancel_all(&mut self) {
+        for child in self.children.drain(..) {
+            child.cancel();
+        }
+    }
+}
+impl Drop for TaskGroup {
+    fn drop(&mut self) {
+        self.cancel_all();
+    }
+}
@@ -1,4 +1,9 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 pub mod executor;
+pub mod group;
+pub mod pool;
+pub mod spawner;
+pub mod sync;
 pub mod task;
+pub mod thread;
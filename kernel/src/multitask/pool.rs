@@ -0,0 +1,204 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// A pool of worker threads which run `Send` tasks. Each worker has its own run queue, and a worker
+// whose queue is empty steals tasks from the others. A task may be pinned to a worker, and then it
+// is never stolen.
+//
+// The kernel does not bring up application processors yet, so the workers share the bootstrap
+// processor and are switched by the timer. Once each CPU runs one worker, waking a worker on
+// another CPU becomes an IPI, and worker 0 is the one on the bootstrap processor.
+//
+// Tasks which are not `Send`, such as the device drivers, keep running on an `Executor`, which
+// stays in the thread that created it. A panic in a task of the pool is not isolated.
+
+use {
+    super::thread::{
+        self,
+        sync::{Mutex, WaitQueue},
+    },
+    crate::lock::IrqSpinlock,
+    alloc::{boxed::Box, collections::VecDeque, sync::Arc, task::Wake, vec::Vec},
+    conquer_once::spin::OnceCell,
+    core::{
+        future::Future,
+        pin::Pin,
+        sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+        task::{Context, Waker},
+    },
+};
+
+const NUM_OF_WORKERS: usize = 2;
+
+static POOL: OnceCell<Pool> = OnceCell::uninit();
+
+pub fn init() {
+    POOL.try_init_once(|| Pool {
+        workers: (0..NUM_OF_WORKERS).map(|_| Worker::new()).collect(),
+    })
+    .expect("The pool is already initialized.");
+
+    for index in 0..NUM_OF_WORKERS {
+        thread::spawn(move || {
+            run_worker(index);
+        })
+        .expect("Failed to create a worker thread.");
+    }
+}
+
+pub fn spawn(task: Task) {
+    Arc::new(task).schedule();
+}
+
+fn pool() -> &'static Pool {
+    POOL.try_get().expect("The pool is not initialized.")
+}
+
+fn run_worker(index: usize) -> ! {
+    let worker = &pool().workers[index];
+
+    loop {
+        match worker.pop().or_else(|| pool().steal(index)) {
+            Some(task) => task.run(index),
+            None => worker
+                .idle
+                .wait_while(|| worker.is_empty() && !pool().has_stealable(index)),
+        }
+    }
+}
+
+struct Pool {
+    workers: Vec<Worker>,
+}
+impl Pool {
+    // Takes the oldest unpinned task of another worker.
+    fn steal(&self, thief: usize) -> Option<Arc<Task>> {
+        self.others(thief).find_map(Worker::steal)
+    }
+
+    fn has_stealable(&self, thief: usize) -> bool {
+        self.others(thief).any(Worker::has_stealable)
+    }
+
+    // Wakes a worker other than `busy` so that it steals a task.
+    fn notify_thief(&self, busy: usize) {
+        for worker in self.others(busy) {
+            if worker.idle.notify_one() {
+                return;
+            }
+        }
+    }
+
+    fn others(&self, index: usize) -> impl Iterator<Item = &Worker> {
+        self.workers
+            .iter()
+            .enumerate()
+            .filter(move |(i, _)| *i != index)
+            .map(|(_, w)| w)
+    }
+}
+
+struct Worker {
+    queue: IrqSpinlock<VecDeque<Arc<Task>>>,
+    idle: WaitQueue,
+}
+impl Worker {
+    fn new() -> Self {
+        Self {
+            queue: IrqSpinlock::new("pool queue", VecDeque::new()),
+            idle: WaitQueue::new(),
+        }
+    }
+
+    fn push(&self, task: Arc<Task>) {
+        self.queue.lock().push_back(task);
+    }
+
+    fn pop(&self) -> Option<Arc<Task>> {
+        self.queue.lock().pop_front()
+    }
+
+    fn steal(&self) -> Option<Arc<Task>> {
+        let mut queue = self.queue.lock();
+        let i = queue.iter().position(|t| t.pinned.is_none())?;
+        queue.remove(i)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.queue.lock().is_empty()
+    }
+
+    fn has_stealable(&self) -> bool {
+        self.queue.lock().iter().any(|t| t.pinned.is_none())
+    }
+}
+
+pub struct Task {
+    future: Mutex<Option<Pin<Box<dyn Future<Output = ()> + Send>>>>,
+    pinned: Option<usize>,
+    // The worker which polled the task last. An unpinned task is queued there when it is woken.
+    home: AtomicUsize,
+    // Set while the task is in a queue.
+    scheduled: AtomicBool,
+}
+impl Task {
+    pub fn new(future: impl Future<Output = ()> + Send + 'static) -> Self {
+        Self {
+            future: Mutex::new(Some(Box::pin(future))),
+            pinned: None,
+            home: AtomicUsize::new(0),
+            scheduled: AtomicBool::new(false),
+        }
+    }
+
+    // Panics if there is no such worker.
+    pub fn pin_to(self, worker: usize) -> Self {
+        assert!(worker < NUM_OF_WORKERS, "No such worker: {}", worker);
+
+        Self {
+            pinned: Some(worker),
+            home: AtomicUsize::new(worker),
+            ..self
+        }
+    }
+
+    // May be called by an interrupt handler through the waker.
+    fn schedule(self: &Arc<Self>) {
+        if self.scheduled.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        let pool = pool();
+        let home = self.home.load(Ordering::Relaxed);
+
+        pool.workers[home].push(self.clone());
+        if !pool.workers[home].idle.notify_one() && self.pinned.is_none() {
+            pool.notify_thief(home);
+        }
+    }
+
+    // The flag is cleared before polling so that a wake during the poll queues the task again.
+    fn run(self: Arc<Self>, worker: usize) {
+        self.home.store(worker, Ordering::Relaxed);
+        self.scheduled.store(false, Ordering::Release);
+
+        let waker = Waker::from(self.clone());
+        let mut context = Context::from_waker(&waker);
+
+        // Another worker may be polling the task if it was woken during that poll and stolen.
+        let mut future = self.future.lock();
+        if let Some(f) = future.as_mut() {
+            if f.as_mut().poll(&mut context).is_ready() {
+                *future = None;
+            }
+        }
+    }
+}
+impl Wake for Task {
+    fn wake(self: Arc<Self>) {
+        self.schedule();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.schedule();
+    }
+}
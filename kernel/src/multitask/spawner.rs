@@ -0,0 +1,271 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use {
+    super::{
+        task::{self, Priority, Task},
+        thread,
+    },
+    alloc::{boxed::Box, rc::Rc, vec::Vec},
+    conquer_once::spin::Lazy,
+    core::{
+        cell::RefCell,
+        future::Future,
+        pin::Pin,
+        task::{Context, Poll, Waker},
+    },
+    spinning_top::Spinlock,
+};
+
+// The spawner of the executor which each thread runs.
+static SPAWNERS: Lazy<Spinlock<Registry>> = Lazy::new(|| Spinlock::new(Registry(Vec::new())));
+
+// Spawns tasks on an executor. A task running on an executor gets its spawner with `current`
+// instead of receiving the task collection as an argument.
+#[derive(Clone)]
+pub struct Spawner {
+    collection: Rc<RefCell<task::Collection>>,
+    priority: Priority,
+}
+impl Spawner {
+    pub fn new(collection: Rc<RefCell<task::Collection>>) -> Self {
+        Self {
+            collection,
+            priority: Priority::default(),
+        }
+    }
+
+    // Tasks spawned by the returned spawner have `priority`.
+    pub fn with_priority(&self, priority: Priority) -> Self {
+        Self {
+            collection: self.collection.clone(),
+            priority,
+        }
+    }
+
+    // Panics if the current thread does not run an executor. The returned spawner spawns tasks
+    // with the default priority.
+    pub fn current() -> Self {
+        SPAWNERS
+            .lock()
+            .find(thread::current())
+            .expect("The current thread does not run an executor.")
+    }
+
+    pub fn spawn<F>(&self, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + 'static,
+        F::Output: 'static,
+    {
+        self.spawn_named("unnamed", future)
+    }
+
+    pub fn spawn_named<F>(&self, name: &'static str, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + 'static,
+        F::Output: 'static,
+    {
+        let shared = Rc::new(RefCell::new(Shared::new()));
+        let on_panic = shared.clone();
+        let task = Task::new_named(
+            name,
+            Cancellable {
+                future: Box::pin(future),
+                shared: shared.clone(),
+            },
+        )
+        .with_priority(self.priority)
+        .with_panic_hook(Rc::new(move || {
+            // The panic may have happened while `shared` was borrowed.
+            if let Ok(mut shared) = on_panic.try_borrow_mut() {
+                shared.panic();
+            }
+        }));
+        let id = task.id();
+
+        self.collection.borrow_mut().add_task_as_woken(task);
+
+        JoinHandle { id, shared }
+    }
+
+    // Makes `current` return this spawner in the current thread.
+    pub(super) fn register(&self, isolates_panics: bool) {
+        SPAWNERS
+            .lock()
+            .add(thread::current(), self.clone(), isolates_panics);
+    }
+
+    // Unregisters the spawner of `thread` if its executor isolates panics. The registry may be
+    // locked if the panic happened in it.
+    pub(super) fn take_isolating(thread: thread::Id) -> Option<Self> {
+        SPAWNERS.try_lock()?.take_isolating(thread)
+    }
+
+    pub fn log_stats(&self) {
+        let stats = self.collection.borrow().stats();
+
+        info!("tasks: {} waiting", stats.len());
+        for s in stats {
+            info!("tasks:   {}", s);
+        }
+    }
+
+    pub(super) fn collection(&self) -> Rc<RefCell<task::Collection>> {
+        self.collection.clone()
+    }
+}
+
+// Awaiting this returns the output of the task. Dropping this does not cancel the task.
+pub struct JoinHandle<T> {
+    id: task::Id,
+    shared: Rc<RefCell<Shared<T>>>,
+}
+impl<T> JoinHandle<T> {
+    pub fn id(&self) -> task::Id {
+        self.id
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.shared.borrow().finished
+    }
+
+    // The future of the task is dropped when the executor polls it next time.
+    pub fn cancel(&self) {
+        let waker = {
+            let mut shared = self.shared.borrow_mut();
+            shared.cancelled = true;
+            shared.task_waker.take()
+        };
+
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+impl<T> Future for JoinHandle<T> {
+    type Output = Result<T, JoinError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut shared = self.shared.borrow_mut();
+
+        if shared.finished {
+            let error = if shared.panicked {
+                JoinError::Panicked
+            } else {
+                JoinError::Cancelled
+            };
+            Poll::Ready(shared.output.take().ok_or(error))
+        } else {
+            shared.join_waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum JoinError {
+    // Also returned if the output is already taken.
+    Cancelled,
+    Panicked,
+}
+
+struct Shared<T> {
+    output: Option<T>,
+    finished: bool,
+    cancelled: bool,
+    panicked: bool,
+    join_waker: Option<Waker>,
+    task_waker: Option<Waker>,
+}
+impl<T> Shared<T> {
+    fn new() -> Self {
+        Self {
+            output: None,
+            finished: false,
+            cancelled: false,
+            panicked: false,
+            join_waker: None,
+            task_waker: None,
+        }
+    }
+
+    fn panic(&mut self) {
+        self.panicked = true;
+        self.finish(None);
+    }
+
+    fn finish(&mut self, output: Option<T>) {
+        self.output = output;
+        self.finished = true;
+
+        if let Some(waker) = self.join_waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+struct Cancellable<F: Future> {
+    future: Pin<Box<F>>,
+    shared: Rc<RefCell<Shared<F::Output>>>,
+}
+impl<F: Future> Future for Cancellable<F> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let this = Pin::into_inner(self);
+
+        if this.shared.borrow().cancelled {
+            this.shared.borrow_mut().finish(None);
+            return Poll::Ready(());
+        }
+
+        this.shared.borrow_mut().task_waker = Some(cx.waker().clone());
+
+        match this.future.as_mut().poll(cx) {
+            Poll::Ready(output) => {
+                this.shared.borrow_mut().finish(Some(output));
+                Poll::Ready(())
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+struct Registry(Vec<Entry>);
+impl Registry {
+    fn add(&mut self, thread: thread::Id, spawner: Spawner, isolates_panics: bool) {
+        assert!(
+            self.find(thread).is_none(),
+            "The thread already runs an executor."
+        );
+        self.0.push(Entry {
+            thread,
+            spawner,
+            isolates_panics,
+        });
+    }
+
+    fn find(&self, thread: thread::Id) -> Option<Spawner> {
+        self.0
+            .iter()
+            .find(|e| e.thread == thread)
+            .map(|e| e.spawner.clone())
+    }
+
+    fn take_isolating(&mut self, thread: thread::Id) -> Option<Spawner> {
+        let i = self
+            .0
+            .iter()
+            .position(|e| e.thread == thread && e.isolates_panics)?;
+
+        Some(self.0.swap_remove(i).spawner)
+    }
+}
+
+struct Entry {
+    thread: thread::Id,
+    spawner: Spawner,
+    isolates_panics: bool,
+}
+// `Spawner` contains an `Rc`. Each spawner is cloned and dropped only by the thread which
+// registered it.
+unsafe impl Send for Registry {}
@@ -0,0 +1,83 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use {
+    conquer_once::spin::OnceCell,
+    core::{
+        pin::Pin,
+        sync::atomic::{AtomicBool, Ordering},
+        task::{Context, Poll},
+    },
+    crossbeam_queue::ArrayQueue,
+    futures_util::{stream::Stream, task::AtomicWaker},
+};
+
+// A bounded channel with any number of senders and a single receiver. It can be a `static`, and
+// `send` never allocates, so an interrupt handler can send values to a task.
+pub struct Channel<T> {
+    queue: OnceCell<ArrayQueue<T>>,
+    capacity: usize,
+    waker: AtomicWaker,
+    received: AtomicBool,
+}
+impl<T> Channel<T> {
+    pub const fn new(capacity: usize) -> Self {
+        Self {
+            queue: OnceCell::uninit(),
+            capacity,
+            waker: AtomicWaker::new(),
+            received: AtomicBool::new(false),
+        }
+    }
+
+    // Allocates the buffer. Call this before anyone sends a value.
+    pub fn init(&self) {
+        self.queue
+            .try_init_once(|| ArrayQueue::new(self.capacity))
+            .expect("The channel is already initialized.")
+    }
+
+    // Returns the value back if the channel is full.
+    pub fn send(&self, x: T) -> Result<(), T> {
+        self.queue().push(x)?;
+        self.waker.wake();
+        Ok(())
+    }
+
+    // Panics if the receiver was already taken.
+    pub fn receiver(&self) -> Receiver<'_, T> {
+        assert!(
+            !self.received.swap(true, Ordering::AcqRel),
+            "The channel already has a receiver."
+        );
+
+        Receiver { channel: self }
+    }
+
+    fn queue(&self) -> &ArrayQueue<T> {
+        self.queue
+            .try_get()
+            .expect("The channel is not initialized.")
+    }
+}
+
+// The stream never ends because senders are not counted.
+pub struct Receiver<'a, T> {
+    channel: &'a Channel<T>,
+}
+impl<'a, T> Receiver<'a, T> {
+    pub fn try_recv(&mut self) -> Option<T> {
+        self.channel.queue().pop()
+    }
+}
+impl<'a, T> Stream for Receiver<'a, T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        self.channel.waker.register(cx.waker());
+
+        match self.try_recv() {
+            Some(x) => Poll::Ready(Some(x)),
+            None => Poll::Pending,
+        }
+    }
+}
@@ -0,0 +1,12 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Primitives for async tasks. Nothing here blocks the thread or allocates memory after
+// construction, and the methods which only signal, like `Channel::send` and `Notify::notify_one`,
+// may be called in interrupt handlers.
+
+pub mod channel;
+pub mod notify;
+
+mod waiters;
+
+pub use {channel::Channel, notify::Notify};
@@ -0,0 +1,80 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use {
+    super::waiters::Waiters,
+    core::{
+        future::Future,
+        pin::Pin,
+        sync::atomic::{AtomicBool, AtomicU64, Ordering},
+        task::{Context, Poll},
+    },
+};
+
+// An event which tasks wait for. `notify_one` leaves a permit which one waiter consumes, even if
+// no one waits yet. `notify_all` wakes only the futures created before it.
+pub struct Notify {
+    permit: AtomicBool,
+    generation: AtomicU64,
+    waiters: Waiters,
+}
+impl Notify {
+    pub fn new() -> Self {
+        Self {
+            permit: AtomicBool::new(false),
+            generation: AtomicU64::new(0),
+            waiters: Waiters::new(),
+        }
+    }
+
+    pub fn notified(&self) -> Notified<'_> {
+        Notified {
+            notify: self,
+            generation: self.generation.load(Ordering::Acquire),
+            slot: None,
+        }
+    }
+
+    // These may be called in an interrupt handler.
+    pub fn notify_one(&self) {
+        self.permit.store(true, Ordering::Release);
+        self.waiters.wake_all();
+    }
+
+    pub fn notify_all(&self) {
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        self.waiters.wake_all();
+    }
+}
+impl Default for Notify {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct Notified<'a> {
+    notify: &'a Notify,
+    generation: u64,
+    slot: Option<usize>,
+}
+impl Future for Notified<'_> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let notify = self.notify;
+        notify.waiters.register(&mut self.slot, cx.waker());
+
+        if notify.generation.load(Ordering::Acquire) != self.generation
+            || notify.permit.swap(false, Ordering::AcqRel)
+        {
+            notify.waiters.unregister(&mut self.slot);
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+impl Drop for Notified<'_> {
+    fn drop(&mut self) {
+        self.notify.waiters.unregister(&mut self.slot);
+    }
+}
@@ -0,0 +1,81 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use {
+    core::{
+        convert::TryFrom,
+        sync::atomic::{AtomicU32, Ordering},
+        task::Waker,
+    },
+    futures_util::task::AtomicWaker,
+};
+
+const MAX_WAITERS: usize = 32;
+
+// Wakers of pending futures, kept without a lock so that an interrupt handler can wake them. Each
+// future occupies a slot until it completes or is dropped.
+//
+// Every waiter is woken on a signal and checks its condition again, as there are only a few
+// waiters.
+pub(super) struct Waiters {
+    used: AtomicU32,
+    wakers: [AtomicWaker; MAX_WAITERS],
+}
+impl Waiters {
+    pub(super) fn new() -> Self {
+        Self {
+            used: AtomicU32::new(0),
+            wakers: Default::default(),
+        }
+    }
+
+    // Call this before checking the condition so that a signal between them is not lost. If every
+    // slot is in use, the future is polled again soon instead.
+    pub(super) fn register(&self, slot: &mut Option<usize>, waker: &Waker) {
+        if slot.is_none() {
+            *slot = self.claim();
+        }
+
+        match slot {
+            Some(i) => self.wakers[*i].register(waker),
+            None => waker.wake_by_ref(),
+        }
+    }
+
+    pub(super) fn unregister(&self, slot: &mut Option<usize>) {
+        if let Some(i) = slot.take() {
+            self.wakers[i].take();
+            self.used.fetch_and(!(1 << i), Ordering::Release);
+        }
+    }
+
+    pub(super) fn wake_all(&self) {
+        let used = self.used.load(Ordering::Acquire);
+
+        for (i, waker) in self.wakers.iter().enumerate() {
+            if used & (1 << i) != 0 {
+                waker.wake();
+            }
+        }
+    }
+
+    fn claim(&self) -> Option<usize> {
+        let mut used = self.used.load(Ordering::Acquire);
+
+        loop {
+            let i = usize::try_from((!used).trailing_zeros()).unwrap();
+            if i >= MAX_WAITERS {
+                return None;
+            }
+
+            match self.used.compare_exchange_weak(
+                used,
+                used | 1 << i,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => return Some(i),
+                Err(current) => used = current,
+            }
+        }
+    }
+}
@@ -1,32 +1,85 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use {
-    alloc::{boxed::Box, collections::BTreeMap, sync::Arc, task::Wake},
+    super::thread::sync::WaitQueue,
+    crate::device::timer,
+    alloc::{
+        boxed::Box,
+        collections::{BTreeMap, VecDeque},
+        rc::Rc,
+        sync::Arc,
+        task::Wake,
+        vec::Vec,
+    },
     core::{
+        arch::x86_64::_rdtsc,
+        fmt,
         future::Future,
         pin::Pin,
-        sync::atomic::{AtomicU64, Ordering},
+        sync::atomic::{AtomicBool, AtomicU64, Ordering},
         task::{Context, Poll, Waker},
     },
     crossbeam_queue::ArrayQueue,
 };
 
+const MAX_WOKEN_TASKS: usize = 100;
+const NUM_OF_PRIORITIES: usize = 3;
+
+// A woken task of a lower priority is run after this many tasks of higher priorities are chosen
+// over it.
+const MAX_SKIPS: u32 = 16;
+
+// Called instead of dropping the future when the task panics, because its stack is abandoned.
+pub type PanicHook = Rc<dyn Fn()>;
+
 pub struct Collection {
     tasks: BTreeMap<Id, Task>,
-    woken_task_ids: Arc<ArrayQueue<Id>>,
+    woken: Arc<Woken>,
+    // Tasks found by scanning after the queue overflowed.
+    rescued: VecDeque<Id>,
+    // The name and the panic hook of the task being polled, for `recover_from_panic`.
+    running: Option<(&'static str, Option<PanicHook>)>,
+    skipped: [u32; NUM_OF_PRIORITIES],
+    // Tasks which were woken after running out of their budget in the current round. Their
+    // `scheduled` flags stay set.
+    deferred: Vec<Id>,
 }
 impl Collection {
     pub fn new() -> Self {
         Self {
             tasks: BTreeMap::new(),
-            woken_task_ids: Arc::new(ArrayQueue::new(100)),
+            woken: Arc::new(Woken::new()),
+            rescued: VecDeque::new(),
+            running: None,
+            skipped: [0; NUM_OF_PRIORITIES],
+            deferred: Vec::new(),
         }
     }
 
     pub fn add_task_as_woken(&mut self, task: Task) {
         let id = task.id();
+        let priority = task.priority;
+        task.scheduled.store(true, Ordering::Release);
         self.push_task(task);
-        self.push_woken_task_id(id);
+        self.woken.push(id, priority);
+    }
+
+    pub fn defer(&mut self, id: Id) {
+        self.deferred.push(id);
+    }
+
+    // Returns `true` if any task was deferred.
+    pub fn requeue_deferred(&mut self) -> bool {
+        if self.deferred.is_empty() {
+            return false;
+        }
+
+        for id in self.deferred.drain(..) {
+            if let Some(task) = self.tasks.get(&id) {
+                self.woken.push(id, task.priority);
+            }
+        }
+        true
     }
 
     pub fn add_task_as_sleep(&mut self, task: Task) {
@@ -40,26 +93,119 @@ impl Collection {
         }
     }
 
-    fn push_woken_task_id(&mut self, id: Id) {
-        self.woken_task_ids
-            .push(id)
-            .expect("Woken task id queue is full.");
-    }
+    // Returns a function which blocks the current thread until a task is woken. The collection
+    // must not be borrowed while blocking.
+    pub fn waiter(&self) -> impl Fn() {
+        let woken = self.woken.clone();
 
-    pub fn woken_task_exists(&self) -> bool {
-        !self.woken_task_ids.is_empty()
+        move || woken.idle_thread.wait_while(|| woken.is_empty())
     }
 
     pub fn pop_woken_task_id(&mut self) -> Option<Id> {
-        self.woken_task_ids.pop()
+        if let Some(id) = self.rescued.pop_front() {
+            return Some(id);
+        }
+
+        if let Some(id) = self.pick_queue().and_then(|i| self.woken.ids[i].pop()) {
+            return Some(id);
+        }
+
+        // Some IDs were dropped. Every task whose flag is set is woken.
+        if self.woken.overflowed.swap(false, Ordering::AcqRel) {
+            warn!("The queue of woken tasks overflowed.");
+
+            let tasks = &self.tasks;
+            self.rescued
+                .extend(tasks.values().filter(|t| t.is_scheduled()).map(Task::id));
+            return self.rescued.pop_front();
+        }
+
+        None
+    }
+
+    // The queue of the highest priority is chosen unless a lower one has been skipped too many
+    // times.
+    fn pick_queue(&mut self) -> Option<usize> {
+        let ids = &self.woken.ids;
+        let highest = (0..NUM_OF_PRIORITIES).find(|&i| !ids[i].is_empty())?;
+        let skipped = &self.skipped;
+        let chosen = (highest + 1..NUM_OF_PRIORITIES)
+            .find(|&i| !ids[i].is_empty() && skipped[i] >= MAX_SKIPS)
+            .unwrap_or(highest);
+
+        for (queue, skipped) in ids.iter().zip(self.skipped.iter_mut()).skip(chosen + 1) {
+            if !queue.is_empty() {
+                *skipped += 1;
+            }
+        }
+        self.skipped[chosen] = 0;
+
+        Some(chosen)
     }
 
     pub fn remove_task(&mut self, id: Id) -> Option<Task> {
         self.tasks.remove(&id)
     }
 
-    pub fn create_waker(&mut self, id: Id) -> Waker {
-        Waker::from(Arc::new(TaskWaker::new(id, self.woken_task_ids.clone())))
+    pub fn create_waker(&mut self, task: &Task) -> Waker {
+        Waker::from(Arc::new(TaskWaker {
+            id: task.id,
+            priority: task.priority,
+            scheduled: task.scheduled.clone(),
+            woken: self.woken.clone(),
+        }))
+    }
+
+    // The task being polled is not included.
+    pub fn stats(&self) -> Vec<Stats> {
+        self.tasks.values().map(Task::stats).collect()
+    }
+
+    pub fn set_running(&mut self, task: Option<&Task>) {
+        self.running = task.map(|task| (task.name, task.on_panic.clone()));
+    }
+
+    pub fn running(&self) -> Option<&'static str> {
+        self.running.as_ref().map(|(name, _)| *name)
+    }
+
+    pub fn take_panic_hook(&mut self) -> Option<PanicHook> {
+        self.running.as_mut().and_then(|(_, hook)| hook.take())
+    }
+}
+
+// Each task is in the queue at most once thanks to its `scheduled` flag. IDs which do not fit
+// are dropped, and the executor scans every task instead of panicking.
+struct Woken {
+    // Indexed by `Priority`.
+    ids: [ArrayQueue<Id>; NUM_OF_PRIORITIES],
+    overflowed: AtomicBool,
+    // The thread running the executor waits here while no task is woken.
+    idle_thread: WaitQueue,
+}
+impl Woken {
+    fn new() -> Self {
+        Self {
+            ids: [
+                ArrayQueue::new(MAX_WOKEN_TASKS),
+                ArrayQueue::new(MAX_WOKEN_TASKS),
+                ArrayQueue::new(MAX_WOKEN_TASKS),
+            ],
+            overflowed: AtomicBool::new(false),
+            idle_thread: WaitQueue::new(),
+        }
+    }
+
+    // This may be called in an interrupt handler.
+    fn push(&self, id: Id, priority: Priority) {
+        if self.ids[priority.index()].push(id).is_err() {
+            self.overflowed.store(true, Ordering::Release);
+        }
+        self.idle_thread.notify_one();
+    }
+
+    fn is_empty(&self) -> bool {
+        self.ids.iter().all(ArrayQueue::is_empty) && !self.overflowed.load(Ordering::Acquire)
     }
 }
 
@@ -67,18 +213,17 @@ impl Collection {
 #[allow(clippy::module_name_repetitions)]
 pub struct TaskWaker {
     id: Id,
-    woken_task_ids: Arc<ArrayQueue<Id>>,
+    priority: Priority,
+    scheduled: Arc<AtomicBool>,
+    woken: Arc<Woken>,
 }
 
 impl TaskWaker {
-    pub fn new(id: Id, woken_task_ids: Arc<ArrayQueue<Id>>) -> Self {
-        Self { id, woken_task_ids }
-    }
-
+    // A task woken many times before it is polled is queued only once.
     fn wake_task(&self) {
-        self.woken_task_ids
-            .push(self.id)
-            .expect("task_queue is full");
+        if !self.scheduled.swap(true, Ordering::AcqRel) {
+            self.woken.push(self.id, self.priority);
+        }
     }
 }
 
@@ -102,24 +247,135 @@ impl Id {
     }
 }
 
+// Woken tasks of a higher priority run first.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Priority {
+    Input,
+    Device,
+    Background,
+}
+impl Priority {
+    fn index(self) -> usize {
+        match self {
+            Self::Input => 0,
+            Self::Device => 1,
+            Self::Background => 2,
+        }
+    }
+}
+impl Default for Priority {
+    fn default() -> Self {
+        Self::Device
+    }
+}
+
 pub struct Task {
     id: Id,
+    name: &'static str,
+    priority: Priority,
+    // Set while the ID is in the queue of woken tasks.
+    scheduled: Arc<AtomicBool>,
     future: Pin<Box<dyn Future<Output = ()>>>,
+    on_panic: Option<PanicHook>,
+    polls: u64,
+    cycles: u64,
+    last_polled: Option<u64>,
 }
 
 impl Task {
     pub fn new(future: impl Future<Output = ()> + 'static) -> Self {
+        Self::new_named("unnamed", future)
+    }
+
+    pub fn new_named(name: &'static str, future: impl Future<Output = ()> + 'static) -> Self {
         Self {
             id: Id::new(),
+            name,
+            priority: Priority::default(),
+            scheduled: Arc::new(AtomicBool::new(false)),
             future: Box::pin(future),
+            on_panic: None,
+            polls: 0,
+            cycles: 0,
+            last_polled: None,
         }
     }
 
+    pub fn with_priority(self, priority: Priority) -> Self {
+        Self { priority, ..self }
+    }
+
+    pub fn with_panic_hook(self, hook: PanicHook) -> Self {
+        Self {
+            on_panic: Some(hook),
+            ..self
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    // The flag is cleared before polling so that a wake during the poll queues the task again.
     pub fn poll(&mut self, context: &mut Context) -> Poll<()> {
-        self.future.as_mut().poll(context)
+        self.scheduled.store(false, Ordering::Release);
+
+        // Safety: `rdtsc` has no side effect.
+        let start = unsafe { _rdtsc() };
+        let poll = self.future.as_mut().poll(context);
+        // Safety: Same as above.
+        let end = unsafe { _rdtsc() };
+
+        self.polls += 1;
+        self.cycles += end.wrapping_sub(start);
+        self.last_polled = Some(timer::ticks());
+
+        poll
+    }
+
+    fn stats(&self) -> Stats {
+        Stats {
+            name: self.name,
+            priority: self.priority,
+            polls: self.polls,
+            cycles: self.cycles,
+            last_polled: self.last_polled,
+        }
     }
 
     pub(super) fn id(&self) -> Id {
         self.id
     }
+
+    fn is_scheduled(&self) -> bool {
+        self.scheduled.load(Ordering::Acquire)
+    }
+}
+
+// The time stamp counter is used for the running time because a tick of the timer is too long for
+// most polls.
+pub struct Stats {
+    name: &'static str,
+    priority: Priority,
+    polls: u64,
+    cycles: u64,
+    last_polled: Option<u64>,
+}
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} ({:?}): {} polls, {} cycles",
+            self.name, self.priority, self.polls, self.cycles
+        )?;
+
+        match self.last_polled {
+            Some(tick) => write!(
+                f,
+                ", last polled {} ms ago",
+                (timer::ticks() - tick) * 1000 / u64::from(timer::FREQUENCY)
+            ),
+            None => write!(f, ", never polled"),
+        }
+    }
 }
@@ -0,0 +1,57 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use {
+    core::{convert::TryFrom, mem},
+    x86_64::VirtAddr,
+};
+
+// Callee-saved registers are pushed on the stack of the old thread, and popped from the stack
+// of the new thread. Caller-saved registers are saved by the caller, including the handler of
+// the timer interrupt. The kernel does not use SSE, so there is no floating-point state.
+//
+// Safety: `old_rsp` must be valid for writes, and `new_rsp` must be a value saved by this
+// function or returned by `init_stack`. Interrupts must be disabled.
+#[naked]
+pub unsafe extern "C" fn switch(old_rsp: *mut u64, new_rsp: u64) {
+    asm!(
+        "push rbp
+        push rbx
+        push r12
+        push r13
+        push r14
+        push r15
+        mov [rdi], rsp
+        mov rsp, rsi
+        pop r15
+        pop r14
+        pop r13
+        pop r12
+        pop rbx
+        pop rbp
+        ret",
+        options(noreturn)
+    );
+}
+
+// Builds a stack from which `switch` returns to `entry`. Returns the initial stack pointer.
+pub fn init_stack(stack: &mut [u8], entry: extern "C" fn() -> !) -> u64 {
+    const NUM_OF_CALLEE_SAVED: usize = 6;
+
+    let top = (VirtAddr::from_ptr(stack.as_ptr()) + stack.len()).align_down(16_u64);
+    let entry = u64::try_from(entry as usize).unwrap();
+
+    // `entry` is entered with `ret`, not `call`. The dummy return address keeps the stack aligned
+    // as the ABI requires, and the zero `rbp` ends the chain of frames.
+    let mut frame = [0_u64; NUM_OF_CALLEE_SAVED + 2];
+    frame[NUM_OF_CALLEE_SAVED] = entry;
+
+    let start = top - mem::size_of_val(&frame);
+    // Safety: `start..top` is in `stack`, which is not used by anyone yet.
+    unsafe {
+        start
+            .as_mut_ptr::<[u64; NUM_OF_CALLEE_SAVED + 2]>()
+            .write(frame)
+    }
+
+    start.as_u64()
+}
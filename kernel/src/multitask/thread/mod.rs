@@ -0,0 +1,294 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Kernel threads with their own stacks. The timer interrupt switches them in round-robin order,
+// so a thread which loops forever does not freeze the others.
+//
+// A thread may be preempted at any point where interrupts are enabled. Locks which an interrupt
+// or exception handler also takes must be held with interrupts disabled.
+
+mod context;
+mod scheduler;
+pub mod sync;
+
+use {
+    crate::{
+        device::timer,
+        gdt,
+        mem::{allocator::page_box::PageBox, paging},
+        process::{self, Process},
+        syscall,
+    },
+    alloc::{boxed::Box, sync::Arc},
+    conquer_once::spin::OnceCell,
+    core::{
+        ops::Range,
+        ptr::NonNull,
+        sync::atomic::{AtomicU64, Ordering},
+    },
+    scheduler::{Scheduler, State, WaitList},
+    spinning_top::Spinlock,
+    x86_64::{instructions::interrupts, structures::paging::PhysFrame, VirtAddr},
+};
+
+const BYTES_STACK: usize = 64 * 1024;
+
+static SCHEDULER: OnceCell<Spinlock<Scheduler>> = OnceCell::uninit();
+
+// The caller becomes the first thread.
+pub fn init() {
+    let boot = Box::new(Thread::boot());
+    let idle = Box::new(Thread::new(Box::new(idle_loop)));
+
+    SCHEDULER
+        .try_init_once(|| Spinlock::new(Scheduler::new(boot, idle)))
+        .expect("Threads are already initialized.");
+}
+
+pub fn spawn<F>(f: F) -> Result<Id, Error>
+where
+    F: FnOnce() + Send + 'static,
+{
+    add(Thread::new(Box::new(f)))
+}
+
+// The thread runs with the PML4 of `process`. The process is dropped when the thread exits.
+pub fn spawn_process<F>(process: Arc<Process>, f: F) -> Result<Id, Error>
+where
+    F: FnOnce() + Send + 'static,
+{
+    let mut thread = Thread::new(Box::new(f));
+    thread.process = Some(process);
+
+    add(thread)
+}
+
+fn add(thread: Thread) -> Result<Id, Error> {
+    free_dead_threads();
+
+    let thread = Box::new(thread);
+    let id = thread.id;
+
+    // If the thread is not added, it is freed after interrupts are enabled again.
+    interrupts::without_interrupts(|| scheduler().lock().add(thread))
+        .map(|_| id)
+        .map_err(|(e, _)| e)
+}
+
+pub fn current() -> Id {
+    interrupts::without_interrupts(|| scheduler().lock().current_mut().id)
+}
+
+// For the heap debugger, which must not wait for a lock. `None` if the lock is held or the current
+// thread runs on the boot stack.
+pub fn try_current_stack() -> Option<Range<VirtAddr>> {
+    let scheduler = SCHEDULER.try_get().ok()?;
+    interrupts::without_interrupts(|| {
+        let mut scheduler = scheduler.try_lock()?;
+        let stack = scheduler.current_mut().stack.as_ref()?;
+
+        let start = VirtAddr::from_ptr(stack.as_ptr());
+        Some(start..start + stack.len())
+    })
+}
+
+// For the panic handler, which must not wait for a lock.
+pub fn try_current() -> Option<Id> {
+    let scheduler = SCHEDULER.try_get().ok()?;
+    interrupts::without_interrupts(|| scheduler.try_lock().map(|mut s| s.current_mut().id))
+}
+
+// Exception handlers call this, so the lock is not waited for.
+pub fn current_process() -> Option<Arc<Process>> {
+    let scheduler = SCHEDULER.try_get().ok()?;
+    interrupts::without_interrupts(|| scheduler.try_lock()?.current_mut().process.clone())
+}
+
+// Wakes the blocked and sleeping threads of the process so that they notice its termination.
+pub fn interrupt_process(id: process::Id) {
+    interrupts::without_interrupts(|| scheduler().lock().interrupt_process(id));
+}
+
+pub fn yield_now() {
+    interrupts::without_interrupts(|| switch(State::Ready));
+}
+
+pub fn sleep(ms: u64) {
+    let until = timer::ticks() + timer::ms_to_ticks(ms);
+    interrupts::without_interrupts(|| switch(State::Sleeping(until)));
+}
+
+// Called with interrupts disabled. Interrupts are enabled on return.
+fn idle() {
+    let other_ready = SCHEDULER
+        .try_get()
+        .map_or(false, |s| s.lock().other_ready());
+
+    if other_ready {
+        switch(State::Ready);
+        interrupts::enable();
+    } else {
+        interrupts::enable_and_hlt();
+    }
+}
+
+// Called by the handler of the timer interrupt.
+pub fn on_timer() {
+    let scheduler = match SCHEDULER.try_get() {
+        Ok(scheduler) => scheduler,
+        Err(_) => return,
+    };
+
+    let preempt = match scheduler.try_lock() {
+        Some(mut scheduler) => scheduler.tick(timer::ticks()),
+        None => return,
+    };
+
+    if preempt {
+        switch(State::Ready);
+    }
+}
+
+fn scheduler() -> &'static Spinlock<Scheduler> {
+    SCHEDULER.try_get().expect("Threads are not initialized.")
+}
+
+// Call these with interrupts disabled. The lock is released before switching.
+fn switch(state: State) {
+    switch_after(state, |_| {});
+}
+
+fn switch_after<F>(state: State, f: F)
+where
+    F: FnOnce(&mut Scheduler),
+{
+    let (rsps, pml4, stack_top) = {
+        let mut scheduler = scheduler().lock();
+        f(&mut scheduler);
+        let rsps = scheduler.switch(state);
+
+        let next = scheduler.current_mut();
+        (rsps, next.pml4(), next.kernel_stack_top)
+    };
+
+    if let Some((old, new)) = rsps {
+        paging::activate(pml4);
+        if let Some(top) = stack_top {
+            gdt::set_kernel_stack(top);
+            syscall::set_kernel_stack(top);
+        }
+
+        // Safety: `old` points to a field of a boxed thread, and `new` was saved by `switch` or
+        // built by `init_stack`.
+        unsafe { context::switch(old, new) }
+    }
+}
+
+fn free_dead_threads() {
+    while let Some(thread) = interrupts::without_interrupts(|| scheduler().lock().take_dead()) {
+        drop(thread);
+    }
+}
+
+// New threads start here with interrupts disabled because they are switched to from `switch`.
+extern "C" fn start() -> ! {
+    let entry = scheduler()
+        .lock()
+        .current_mut()
+        .entry
+        .take()
+        .expect("A thread is started twice.");
+
+    interrupts::enable();
+    entry();
+
+    exit();
+}
+
+pub fn exit() -> ! {
+    interrupts::disable();
+    switch(State::Dead);
+
+    unreachable!("A dead thread is scheduled.");
+}
+
+// Runs when every other thread is blocked or sleeping.
+fn idle_loop() {
+    loop {
+        interrupts::disable();
+        idle();
+    }
+}
+
+struct Thread {
+    id: Id,
+    rsp: u64,
+    state: State,
+    // `None` for the first thread, which uses the boot stack.
+    stack: Option<PageBox<[u8]>>,
+    // Loaded to RSP0 of the TSS while the thread runs.
+    kernel_stack_top: Option<VirtAddr>,
+    entry: Option<Box<dyn FnOnce() + Send>>,
+    next_waiter: Option<usize>,
+    // The list which the thread is blocked on, so that it can be removed from there when woken
+    // by other means.
+    waiting_on: Option<NonNull<Spinlock<WaitList>>>,
+    process: Option<Arc<Process>>,
+}
+// Safety: `waiting_on` is set only while the thread blocks in `WaitQueue::wait_while`, which
+// borrows the list, and is read only with the scheduler lock held.
+unsafe impl Send for Thread {}
+impl Thread {
+    fn new(entry: Box<dyn FnOnce() + Send>) -> Self {
+        // `PageBox` maps every page now. A lazily mapped stack would cause a double fault because
+        // the CPU pushes the frame of a page fault to the same stack.
+        let mut stack = PageBox::new_slice(0_u8, BYTES_STACK);
+        let rsp = context::init_stack(&mut stack, start);
+        let top = (VirtAddr::from_ptr(stack.as_ptr()) + stack.len()).align_down(16_u64);
+
+        Self {
+            id: Id::new(),
+            rsp,
+            state: State::Ready,
+            stack: Some(stack),
+            kernel_stack_top: Some(top),
+            entry: Some(entry),
+            next_waiter: None,
+            waiting_on: None,
+            process: None,
+        }
+    }
+
+    fn boot() -> Self {
+        Self {
+            id: Id::new(),
+            rsp: 0,
+            state: State::Running,
+            stack: None,
+            kernel_stack_top: None,
+            entry: None,
+            next_waiter: None,
+            waiting_on: None,
+            process: None,
+        }
+    }
+
+    fn pml4(&self) -> PhysFrame {
+        self.process
+            .as_ref()
+            .map_or_else(paging::kernel_pml4, |p| p.pml4())
+    }
+}
+
+#[derive(PartialOrd, PartialEq, Ord, Eq, Copy, Clone, Debug)]
+pub struct Id(u64);
+impl Id {
+    fn new() -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        Id(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    TooManyThreads,
+}
@@ -0,0 +1,226 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use {
+    super::{Error, Thread},
+    crate::{device::timer, process},
+    alloc::{boxed::Box, collections::VecDeque, vec::Vec},
+    core::ptr::NonNull,
+    spinning_top::Spinlock,
+};
+
+pub const MAX_THREADS: usize = 64;
+const TICKS_PER_SLICE: u64 = 2;
+
+// The scheduler runs with interrupts disabled, where allocating memory may deadlock if a
+// preempted thread holds the heap lock. Thus the list and the queue are allocated up front, and
+// each thread appears in the queue at most once.
+pub struct Scheduler {
+    threads: Vec<Option<Box<Thread>>>,
+    ready: VecDeque<usize>,
+    current: usize,
+    idle: usize,
+    slice_end: u64,
+}
+impl Scheduler {
+    // `boot` is the running thread.
+    pub fn new(boot: Box<Thread>, idle: Box<Thread>) -> Self {
+        let mut threads: Vec<_> = (0..MAX_THREADS).map(|_| None).collect();
+        threads[0] = Some(boot);
+        threads[1] = Some(idle);
+
+        Self {
+            threads,
+            ready: VecDeque::with_capacity(MAX_THREADS),
+            current: 0,
+            idle: 1,
+            slice_end: 0,
+        }
+    }
+
+    // The thread is returned if there is no room, so that it is not freed here.
+    pub fn add(&mut self, thread: Box<Thread>) -> Result<(), (Error, Box<Thread>)> {
+        let slot = match self.threads.iter().position(Option::is_none) {
+            Some(slot) => slot,
+            None => return Err((Error::TooManyThreads, thread)),
+        };
+
+        self.threads[slot] = Some(thread);
+        self.wake(slot);
+        Ok(())
+    }
+
+    // Changes the state of the current thread and picks the next one. Returns where to save the
+    // stack pointer of the current thread and the stack pointer of the next one, or `None` if the
+    // current thread keeps running.
+    pub fn switch(&mut self, state: State) -> Option<(*mut u64, u64)> {
+        let current = self.current;
+        self.thread_mut(current).state = state;
+
+        if state == State::Ready && current != self.idle {
+            self.ready.push_back(current);
+        }
+
+        let next = self.ready.pop_front().unwrap_or(self.idle);
+        self.thread_mut(next).state = State::Running;
+
+        if next == current {
+            return None;
+        }
+
+        self.current = next;
+        self.slice_end = timer::ticks() + TICKS_PER_SLICE;
+
+        let old_rsp: *mut u64 = &mut self.thread_mut(current).rsp;
+        Some((old_rsp, self.thread_mut(next).rsp))
+    }
+
+    // Wakes sleeping threads. Returns true if the current thread should be preempted.
+    pub fn tick(&mut self, now: u64) -> bool {
+        for slot in 0..MAX_THREADS {
+            if let Some(thread) = &self.threads[slot] {
+                if matches!(thread.state, State::Sleeping(until) if until <= now) {
+                    self.wake(slot);
+                }
+            }
+        }
+
+        !self.ready.is_empty() && (self.current == self.idle || now >= self.slice_end)
+    }
+
+    pub fn other_ready(&self) -> bool {
+        !self.ready.is_empty()
+    }
+
+    pub fn current_mut(&mut self) -> &mut Thread {
+        let current = self.current;
+        self.thread_mut(current)
+    }
+
+    pub fn push_waiter(&mut self, queue: &Spinlock<WaitList>) {
+        let current = self.current;
+        let mut list = queue.lock();
+
+        let thread = self.thread_mut(current);
+        thread.next_waiter = None;
+        thread.waiting_on = Some(NonNull::from(queue));
+
+        match list.tail {
+            Some(tail) => self.thread_mut(tail).next_waiter = Some(current),
+            None => list.head = Some(current),
+        }
+        list.tail = Some(current);
+    }
+
+    // Returns false if no thread is waiting.
+    pub fn wake_waiter(&mut self, queue: &Spinlock<WaitList>) -> bool {
+        let mut list = queue.lock();
+        let head = match list.head {
+            Some(head) => head,
+            None => return false,
+        };
+
+        let thread = self.thread_mut(head);
+        thread.waiting_on = None;
+        list.head = thread.next_waiter.take();
+        if list.head.is_none() {
+            list.tail = None;
+        }
+
+        self.wake(head);
+        true
+    }
+
+    // Wakes the threads of `process` which are blocked or sleeping, so that they notice that the
+    // process is asked to terminate. For the others, this looks like a spurious wakeup.
+    pub fn interrupt_process(&mut self, process: process::Id) {
+        for slot in 0..MAX_THREADS {
+            let interrupted = match &self.threads[slot] {
+                Some(thread) => {
+                    thread.process.as_ref().map_or(false, |p| p.id() == process)
+                        && matches!(thread.state, State::Blocked | State::Sleeping(_))
+                }
+                None => false,
+            };
+
+            if interrupted {
+                self.remove_waiter(slot);
+                self.wake(slot);
+            }
+        }
+    }
+
+    // The stack of a dead thread cannot be freed while it is running on it. Other threads free it
+    // with interrupts enabled.
+    pub fn take_dead(&mut self) -> Option<Box<Thread>> {
+        let slot = self
+            .threads
+            .iter()
+            .position(|t| matches!(t, Some(t) if t.state == State::Dead))?;
+
+        self.threads[slot].take()
+    }
+
+    fn remove_waiter(&mut self, slot: usize) {
+        let queue = match self.thread_mut(slot).waiting_on.take() {
+            Some(queue) => queue,
+            None => return,
+        };
+
+        // Safety: The thread is still in `wait_while` of the queue, which borrows it.
+        let mut list = unsafe { queue.as_ref() }.lock();
+
+        let next = self.thread_mut(slot).next_waiter.take();
+        let mut prev = None;
+        let mut cursor = list.head;
+        while let Some(i) = cursor {
+            if i == slot {
+                match prev {
+                    Some(prev) => self.thread_mut(prev).next_waiter = next,
+                    None => list.head = next,
+                }
+                if list.tail == Some(slot) {
+                    list.tail = prev;
+                }
+                return;
+            }
+
+            prev = cursor;
+            cursor = self.thread_mut(i).next_waiter;
+        }
+    }
+
+    fn wake(&mut self, slot: usize) {
+        self.thread_mut(slot).state = State::Ready;
+        self.ready.push_back(slot);
+    }
+
+    fn thread_mut(&mut self, slot: usize) -> &mut Thread {
+        self.threads[slot]
+            .as_mut()
+            .unwrap_or_else(|| panic!("No thread in slot {}", slot))
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum State {
+    Running,
+    Ready,
+    Blocked,
+    // Until the tick.
+    Sleeping(u64),
+    Dead,
+}
+
+// Threads waiting on a `WaitQueue`, linked through `Thread::next_waiter`.
+pub struct WaitList {
+    head: Option<usize>,
+    tail: Option<usize>,
+}
+impl WaitList {
+    pub const fn new() -> Self {
+        Self {
+            head: None,
+            tail: None,
+        }
+    }
+}
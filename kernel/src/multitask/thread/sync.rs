@@ -0,0 +1,106 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Blocking primitives for threads. Do not use them in interrupt handlers or async tasks, which
+// must not block.
+
+use {
+    super::scheduler::{State, WaitList},
+    core::{
+        cell::UnsafeCell,
+        ops::{Deref, DerefMut},
+        sync::atomic::{AtomicBool, Ordering},
+    },
+    spinning_top::Spinlock,
+    x86_64::instructions::interrupts,
+};
+
+pub struct WaitQueue(Spinlock<WaitList>);
+impl WaitQueue {
+    pub const fn new() -> Self {
+        Self(Spinlock::new(WaitList::new()))
+    }
+
+    // Blocks the current thread while `cond` returns true. `cond` is checked with interrupts
+    // disabled, so a notification between the check and blocking is never lost. `kill` also wakes
+    // the thread, so a wait of a process which may last long should stop when
+    // `process::current_is_terminating` returns true.
+    pub fn wait_while<F>(&self, cond: F)
+    where
+        F: Fn() -> bool,
+    {
+        interrupts::without_interrupts(|| {
+            while cond() {
+                super::switch_after(State::Blocked, |s| s.push_waiter(&self.0));
+            }
+        });
+    }
+
+    // Returns false if no thread is waiting.
+    pub fn notify_one(&self) -> bool {
+        interrupts::without_interrupts(|| super::scheduler().lock().wake_waiter(&self.0))
+    }
+
+    pub fn notify_all(&self) {
+        while self.notify_one() {}
+    }
+}
+
+// Unlike `Spinlock`, a waiting thread sleeps instead of spinning until it is preempted.
+pub struct Mutex<T> {
+    locked: AtomicBool,
+    waiters: WaitQueue,
+    data: UnsafeCell<T>,
+}
+impl<T> Mutex<T> {
+    pub const fn new(data: T) -> Self {
+        Self {
+            locked: AtomicBool::new(false),
+            waiters: WaitQueue::new(),
+            data: UnsafeCell::new(data),
+        }
+    }
+
+    pub fn lock(&self) -> MutexGuard<'_, T> {
+        loop {
+            if let Some(guard) = self.try_lock() {
+                return guard;
+            }
+
+            self.waiters
+                .wait_while(|| self.locked.load(Ordering::Relaxed));
+        }
+    }
+
+    pub fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+        self.locked
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .ok()
+            .map(|_| MutexGuard { mutex: self })
+    }
+}
+// Safety: The data is accessed only through `MutexGuard`, and only one guard exists at a time.
+unsafe impl<T: Send> Send for Mutex<T> {}
+unsafe impl<T: Send> Sync for Mutex<T> {}
+
+pub struct MutexGuard<'a, T> {
+    mutex: &'a Mutex<T>,
+}
+impl<'a, T> Deref for MutexGuard<'a, T> {
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        // Safety: This guard holds the lock.
+        unsafe { &*self.mutex.data.get() }
+    }
+}
+impl<'a, T> DerefMut for MutexGuard<'a, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // Safety: This guard holds the lock.
+        unsafe { &mut *self.mutex.data.get() }
+    }
+}
+impl<'a, T> Drop for MutexGuard<'a, T> {
+    fn drop(&mut self) {
+        self.mutex.locked.store(false, Ordering::Release);
+        self.mutex.waiters.notify_one();
+    }
+}
@@ -1,8 +1,11 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+use crate::{lock, multitask::executor};
+
 #[panic_handler]
 fn panic(info: &core::panic::PanicInfo) -> ! {
     x86_64::instructions::interrupts::disable();
+    lock::stop_order_checking();
     error!("*************");
     error!("*   PANIC   *");
     error!("*************");
@@ -16,6 +19,8 @@ fn panic(info: &core::panic::PanicInfo) -> ! {
         );
     }
 
+    executor::recover_from_panic();
+
     loop {
         x86_64::instructions::hlt();
     }
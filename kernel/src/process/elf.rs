@@ -0,0 +1,318 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Loads static and position-independent ELF64 executables. There is no dynamic linker, so an
+// executable with `PT_INTERP` is rejected, and a PIE may only have `R_X86_64_RELATIVE`
+// relocations, as a static PIE does.
+
+use {
+    super::{memory::USER_END, Id, Process, USER_STACK_TOP},
+    alloc::{vec, vec::Vec},
+    common::elf_rs::{Elf, ElfHeader, ElfMachine, ElfType, ProgramHeader, ProgramType},
+    core::convert::TryFrom,
+    os_units::NumOfPages,
+    x86_64::{
+        structures::paging::{Page, PageSize, PageTableFlags, Size4KiB},
+        VirtAddr,
+    },
+};
+
+// PIEs are loaded here.
+const PIE_BASE: u64 = 0x5555_0000_0000;
+
+// The strings and the vectors at the top of the stack must fit in this.
+const MAX_BYTES_INITIAL_STACK: usize = 16 * 1024;
+
+const BYTES_ELF_HEADER: usize = 64;
+const BYTES_PROGRAM_HEADER: u16 = 56;
+
+const PF_X: u32 = 1;
+const PF_W: u32 = 2;
+
+const DT_NULL: u64 = 0;
+const DT_RELA: u64 = 7;
+const DT_RELASZ: u64 = 8;
+const DT_RELAENT: u64 = 9;
+
+const R_X86_64_NONE: u64 = 0;
+const R_X86_64_RELATIVE: u64 = 8;
+const BYTES_RELA: usize = 24;
+
+const AT_NULL: u64 = 0;
+const AT_PHDR: u64 = 3;
+const AT_PHENT: u64 = 4;
+const AT_PHNUM: u64 = 5;
+const AT_PAGESZ: u64 = 6;
+const AT_BASE: u64 = 7;
+const AT_ENTRY: u64 = 9;
+
+// `bytes` must be aligned to 8 bytes because `elf_rs` reads the headers in place.
+pub fn spawn(bytes: &[u8], args: &[&str], envs: &[&str]) -> Result<Id, Error> {
+    assert_eq!(
+        bytes.as_ptr().align_offset(8),
+        0,
+        "The ELF image is not aligned."
+    );
+
+    if bytes.len() < BYTES_ELF_HEADER {
+        return Err(Error::Malformed);
+    }
+
+    let elf = match Elf::from_bytes(bytes).map_err(|_| Error::Malformed)? {
+        Elf::Elf64(elf) => elf,
+        Elf::Elf32(_) => return Err(Error::Not64Bit),
+    };
+
+    let header = elf.header();
+    if header.machine() != ElfMachine::x86_64 {
+        return Err(Error::NotX86_64);
+    }
+    let bias = match header.elftype() {
+        ElfType::ET_EXEC => 0,
+        ElfType::ET_DYN => PIE_BASE,
+        _ => return Err(Error::NotExecutable),
+    };
+
+    // `elf_rs` reads the program headers without checking that they are in the image.
+    let phoff = header.program_header_offset();
+    if header.program_header_entry_size() != BYTES_PROGRAM_HEADER {
+        return Err(Error::Malformed);
+    }
+    u64::from(header.program_header_entry_num())
+        .checked_mul(u64::from(BYTES_PROGRAM_HEADER))
+        .and_then(|bytes_headers| phoff.checked_add(bytes_headers))
+        .filter(|&end| u64::try_from(bytes.len()).map_or(false, |len| end <= len))
+        .ok_or(Error::Malformed)?;
+
+    let process = Process::new()?;
+    let mut phdr = None;
+    let mut dynamic = None;
+
+    for ph in elf.program_header_iter().map(|w| w.ph) {
+        match ph.ph_type() {
+            ProgramType::LOAD => {
+                load_segment(&process, bytes, ph, bias)?;
+
+                // `AT_PHDR` is the address of the program headers in the segment which contains
+                // them.
+                let end = ph
+                    .offset()
+                    .checked_add(ph.filesz())
+                    .ok_or(Error::Malformed)?;
+                if (ph.offset()..end).contains(&phoff) {
+                    let addr = ph
+                        .vaddr()
+                        .checked_add(bias)
+                        .and_then(|addr| addr.checked_add(phoff - ph.offset()))
+                        .ok_or(Error::Malformed)?;
+                    phdr = Some(addr);
+                }
+            }
+            ProgramType::DYNAMIC => dynamic = Some(segment(bytes, ph)?),
+            ProgramType::INTERP => return Err(Error::Interpreter),
+            _ => {}
+        }
+    }
+
+    if let Some(dynamic) = dynamic {
+        relocate(&process, bytes, dynamic, bias)?;
+    }
+
+    let entry = to_addr(header.entry_point(), bias)?;
+    let auxv = [
+        (AT_PHDR, phdr.unwrap_or(0)),
+        (AT_PHENT, u64::from(header.program_header_entry_size())),
+        (AT_PHNUM, u64::from(header.program_header_entry_num())),
+        (AT_PAGESZ, Size4KiB::SIZE),
+        (AT_BASE, 0),
+        (AT_ENTRY, entry.as_u64()),
+    ];
+
+    super::map_stack(&process)?;
+    let rsp = init_stack(&process, args, envs, &auxv)?;
+
+    Ok(super::start(process, entry, rsp)?)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn load_segment(
+    process: &Process,
+    bytes: &[u8],
+    ph: &dyn ProgramHeader,
+    bias: u64,
+) -> Result<(), Error> {
+    if ph.memsz() == 0 {
+        return Ok(());
+    }
+    if ph.filesz() > ph.memsz() {
+        return Err(Error::Malformed);
+    }
+
+    let start = ph.vaddr().checked_add(bias).ok_or(Error::Malformed)?;
+    let end = start.checked_add(ph.memsz()).ok_or(Error::Malformed)?;
+    // Aligning an address near the end of the lower half up makes it non-canonical.
+    if end > USER_END.as_u64() {
+        return Err(Error::Malformed);
+    }
+    let start = VirtAddr::try_new(start).map_err(|_| Error::Malformed)?;
+    let end = VirtAddr::try_new(end).map_err(|_| Error::Malformed)?;
+
+    let first_page = Page::<Size4KiB>::containing_address(start);
+    let num_of_pages = (end.align_up(Size4KiB::SIZE) - first_page.start_address()) / Size4KiB::SIZE;
+
+    let mut flags = PageTableFlags::empty();
+    if ph.flags() & PF_W != 0 {
+        flags |= PageTableFlags::WRITABLE;
+    }
+    if ph.flags() & PF_X == 0 {
+        flags |= PageTableFlags::NO_EXECUTE;
+    }
+
+    process.map(
+        first_page,
+        NumOfPages::new(usize::try_from(num_of_pages).unwrap()),
+        flags,
+    )?;
+
+    // The rest of the segment is left zeroed.
+    process.write(start, segment(bytes, ph)?)?;
+
+    Ok(())
+}
+
+// The part of the segment in the file.
+fn segment<'a>(bytes: &'a [u8], ph: &dyn ProgramHeader) -> Result<&'a [u8], Error> {
+    let start = usize::try_from(ph.offset()).map_err(|_| Error::Malformed)?;
+    let len = usize::try_from(ph.filesz()).map_err(|_| Error::Malformed)?;
+    let end = start.checked_add(len).ok_or(Error::Malformed)?;
+
+    bytes.get(start..end).ok_or(Error::Malformed)
+}
+
+// Applies the relocations listed in the dynamic section. The table is read from the loaded
+// image, so it must be in a `PT_LOAD` segment.
+#[allow(clippy::too_many_arguments)]
+fn relocate(process: &Process, bytes: &[u8], dynamic: &[u8], bias: u64) -> Result<(), Error> {
+    let mut rela = None;
+    let mut rela_size = 0;
+
+    for entry in dynamic.chunks_exact(16) {
+        let (tag, value) = (read_u64(&entry[..8]), read_u64(&entry[8..]));
+        match tag {
+            DT_NULL => break,
+            DT_RELA => rela = Some(value),
+            DT_RELASZ => rela_size = value,
+            DT_RELAENT if usize::try_from(value) != Ok(BYTES_RELA) => return Err(Error::Malformed),
+            _ => {}
+        }
+    }
+
+    let rela = match rela {
+        Some(rela) => rela,
+        None => return Ok(()),
+    };
+
+    // The table is a part of the file, so it cannot be larger than that.
+    let rela_size = usize::try_from(rela_size).map_err(|_| Error::Malformed)?;
+    if rela_size > bytes.len() || rela_size % BYTES_RELA != 0 {
+        return Err(Error::Malformed);
+    }
+
+    let mut table = vec![0; rela_size];
+    process.copy_from_user(to_addr(rela, bias)?, &mut table)?;
+
+    for entry in table.chunks_exact(BYTES_RELA) {
+        let offset = read_u64(&entry[..8]);
+        let kind = read_u64(&entry[8..16]) & 0xffff_ffff;
+        let addend = read_u64(&entry[16..]);
+
+        match kind {
+            R_X86_64_NONE => {}
+            // The addend is signed, and wrapping addition gives the right value.
+            R_X86_64_RELATIVE => {
+                let value = bias.wrapping_add(addend);
+                process.write(to_addr(offset, bias)?, &value.to_le_bytes())?;
+            }
+            _ => return Err(Error::Relocation(kind)),
+        }
+    }
+
+    Ok(())
+}
+
+// Builds the stack `_start` expects: `argc`, `argv`, `envp` and `auxv` from `rsp` upwards, and
+// the strings at the top. Returns `rsp`.
+#[allow(clippy::too_many_arguments)]
+fn init_stack(
+    process: &Process,
+    args: &[&str],
+    envs: &[&str],
+    auxv: &[(u64, u64)],
+) -> Result<VirtAddr, Error> {
+    let bytes_strings: usize = args.iter().chain(envs).map(|s| s.len() + 1).sum();
+    let num_of_words = 1 + (args.len() + 1) + (envs.len() + 1) + (auxv.len() + 1) * 2;
+    if bytes_strings + num_of_words * 8 + 16 > MAX_BYTES_INITIAL_STACK {
+        return Err(Error::TooLongArguments);
+    }
+
+    let strings_start = USER_STACK_TOP - bytes_strings;
+    let rsp = (strings_start - num_of_words * 8).align_down(16_u64);
+
+    let mut strings = Vec::with_capacity(bytes_strings);
+    let mut pointers = Vec::with_capacity(args.len() + envs.len());
+    for s in args.iter().chain(envs) {
+        pointers.push((strings_start + strings.len()).as_u64());
+        strings.extend_from_slice(s.as_bytes());
+        strings.push(0);
+    }
+    let (argv, envp) = pointers.split_at(args.len());
+
+    let mut words = Vec::with_capacity(num_of_words);
+    words.push(u64::try_from(args.len()).unwrap());
+    words.extend_from_slice(argv);
+    words.push(0);
+    words.extend_from_slice(envp);
+    words.push(0);
+    for &(kind, value) in auxv.iter().chain(&[(AT_NULL, 0)]) {
+        words.push(kind);
+        words.push(value);
+    }
+
+    let mut vectors = Vec::with_capacity(num_of_words * 8);
+    for w in words {
+        vectors.extend_from_slice(&w.to_le_bytes());
+    }
+
+    process.write(rsp, &vectors)?;
+    process.write(strings_start, &strings)?;
+
+    Ok(rsp)
+}
+
+fn to_addr(addr: u64, bias: u64) -> Result<VirtAddr, Error> {
+    addr.checked_add(bias)
+        .and_then(|addr| VirtAddr::try_new(addr).ok())
+        .ok_or(Error::Malformed)
+}
+
+fn read_u64(bytes: &[u8]) -> u64 {
+    let mut buf = [0; 8];
+    buf.copy_from_slice(bytes);
+    u64::from_le_bytes(buf)
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Malformed,
+    Not64Bit,
+    NotX86_64,
+    NotExecutable,
+    Interpreter,
+    Relocation(u64),
+    TooLongArguments,
+    Process(super::Error),
+}
+impl From<super::Error> for Error {
+    fn from(e: super::Error) -> Self {
+        Error::Process(e)
+    }
+}
@@ -0,0 +1,20 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// The first user program. The Makefile builds it from `init/` before the kernel. There is no
+// file system yet, so it is embedded in the kernel image.
+
+use super::elf;
+
+// The loader needs the image aligned to 8 bytes, which `include_bytes!` does not promise.
+#[repr(C, align(8))]
+struct Aligned<T: ?Sized>(T);
+
+static IMAGE: &Aligned<[u8]> = &Aligned(*include_bytes!("../../../build/init"));
+
+// The kernel is the parent, so nothing waits for it.
+pub fn spawn() {
+    match elf::spawn(&IMAGE.0, &["init"], &[]) {
+        Ok(id) => info!("Started init as {:?}.", id),
+        Err(e) => warn!("Failed to start init: {:?}", e),
+    }
+}
@@ -0,0 +1,412 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// The lower half of a process's PML4 is edited through the direct map, so it does not need to be
+// the active one. Pages from `map` are mapped when they are added. Those from `map_anywhere` are
+// recorded as areas of the process and mapped when they are touched first, either by a page fault
+// in user mode or by the kernel accessing them. Nothing in the lower half is swapped out.
+//
+// User memory is also accessed through the direct map. A bad pointer from a process is an error,
+// not a page fault in the kernel.
+
+use {
+    super::Error,
+    crate::{
+        ipc::{grant::Frames, Grant},
+        mem::{
+            allocator::phys::{Tag, FRAME_MANAGER},
+            paging, phys_to_virt,
+            vma::{Access, AddressSpace, Area},
+        },
+    },
+    alloc::{sync::Arc, vec::Vec},
+    core::{cmp, convert::TryFrom, ops::Range, ptr},
+    os_units::NumOfPages,
+    x86_64::{
+        structures::paging::{
+            Page, PageSize, PageTable, PageTableEntry, PageTableFlags, PageTableIndex, PhysFrame,
+            Size4KiB,
+        },
+        VirtAddr,
+    },
+};
+
+const PML4_INDEX_UPPER_HALF: usize = 256;
+
+// Nothing is mapped from here to the top of the lower half.
+pub(super) const USER_END: VirtAddr = VirtAddr::new_truncate(0x7fff_ffff_0000);
+
+// `mmap` returns addresses from here upwards.
+const MMAP_START: VirtAddr = VirtAddr::new_truncate(0x1000_0000_0000);
+
+// Set on the leaf entries of granted pages, both in the sender and in the receivers. Their frames
+// are owned by `Frames`, not by the processes.
+const SHARED: PageTableFlags = PageTableFlags::BIT_9;
+
+pub(super) struct Memory {
+    pml4: PhysFrame,
+    mmap_next: Page<Size4KiB>,
+    shared: Vec<Arc<Frames>>,
+    // Lazily mapped memory. The page fault handler finds this through CR3.
+    areas: AddressSpace,
+}
+impl Memory {
+    pub(super) fn new(pml4: PhysFrame) -> Self {
+        Self {
+            pml4,
+            mmap_next: Page::containing_address(MMAP_START),
+            shared: Vec::new(),
+            areas: AddressSpace::new(),
+        }
+    }
+
+    // Maps zeroed pages. `USER_ACCESSIBLE` is added to `flags`.
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn map(
+        &mut self,
+        start: Page<Size4KiB>,
+        num_of_pages: NumOfPages<Size4KiB>,
+        flags: PageTableFlags,
+    ) -> Result<(), Error> {
+        let flags = flags | PageTableFlags::PRESENT | PageTableFlags::USER_ACCESSIBLE;
+
+        // Checked first so that no frame is allocated for a range which does not fit.
+        user_range_end(start, num_of_pages.as_usize())?;
+
+        for i in 0..u64::try_from(num_of_pages.as_usize()).unwrap() {
+            let page = start + i;
+            let entry = self.entry_mut(page)?;
+            if !entry.is_unused() {
+                return Err(Error::AlreadyMapped(page.start_address()));
+            }
+
+            entry.set_frame(alloc_zeroed_frame()?, flags);
+        }
+
+        Ok(())
+    }
+
+    // Reserves pages above every page reserved by this function before. They are mapped when they
+    // are touched first.
+    pub(super) fn map_anywhere(
+        &mut self,
+        num_of_pages: NumOfPages<Size4KiB>,
+        flags: PageTableFlags,
+    ) -> Result<VirtAddr, Error> {
+        let flags = flags | PageTableFlags::PRESENT | PageTableFlags::USER_ACCESSIBLE;
+
+        // The range is skipped even if adding the area fails.
+        let start = self.mmap_next;
+        self.mmap_next = user_range_end(start, num_of_pages.as_usize())?;
+
+        let area = Area::anonymous(start.start_address(), num_of_pages, flags, "mmap");
+        self.areas.add(area).map_err(|_| Error::TooManyAreas)?;
+
+        Ok(start.start_address())
+    }
+
+    // Maps the page of `addr` if it is in an area which allows `access`.
+    pub(super) fn handle_page_fault(
+        &mut self,
+        addr: VirtAddr,
+        access: Access,
+    ) -> Result<(), Error> {
+        match self.areas.find(addr) {
+            Some(area) if area.allows(access) => {
+                self.touch(Page::containing_address(addr))?;
+                Ok(())
+            }
+            _ => Err(Error::NotMapped(addr)),
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn grant(
+        &mut self,
+        addr: VirtAddr,
+        num_of_pages: NumOfPages<Size4KiB>,
+        writable: bool,
+    ) -> Result<Grant, Error> {
+        if !addr.is_aligned(Size4KiB::SIZE) {
+            return Err(Error::Unaligned(addr));
+        }
+
+        let mut required = PageTableFlags::PRESENT | PageTableFlags::USER_ACCESSIBLE;
+        if writable {
+            required |= PageTableFlags::WRITABLE;
+        }
+
+        // Every page is checked before any is marked so that a failure changes nothing.
+        let start = Page::<Size4KiB>::containing_address(addr);
+        let pages = (0..u64::try_from(num_of_pages.as_usize()).unwrap()).map(|i| start + i);
+        let mut frames = Vec::new();
+        for page in pages.clone() {
+            let virt = page.start_address();
+            if virt >= USER_END {
+                return Err(Error::OutOfUserSpace(virt));
+            }
+
+            let entry = self.touch(page)?;
+            if !entry.flags().contains(required) {
+                return Err(Error::NotMapped(virt));
+            }
+            if entry.flags().contains(SHARED) {
+                return Err(Error::AlreadyShared(virt));
+            }
+
+            frames.push(entry.frame().unwrap());
+        }
+
+        for page in pages {
+            let entry = self.entry_mut(page)?;
+            entry.set_flags(entry.flags() | SHARED);
+        }
+
+        let frames = Arc::new(Frames::new(frames));
+        self.shared.push(Arc::clone(&frames));
+
+        Ok(Grant::new(frames, writable))
+    }
+
+    // Granted pages are never executable.
+    pub(super) fn map_grant(&mut self, grant: &Grant) -> Result<VirtAddr, Error> {
+        let mut flags = PageTableFlags::PRESENT
+            | PageTableFlags::USER_ACCESSIBLE
+            | PageTableFlags::NO_EXECUTE
+            | SHARED;
+        if grant.writable() {
+            flags |= PageTableFlags::WRITABLE;
+        }
+
+        // Same as `map_anywhere`.
+        let start = self.mmap_next;
+        self.mmap_next = user_range_end(start, grant.num_of_pages())?;
+
+        // Before mapping so that the frames outlive the pages mapped before a failure.
+        self.shared.push(Arc::clone(grant.frames()));
+
+        for (page, frame) in (0..).map(|i| start + i).zip(grant.frames().iter()) {
+            let entry = self.entry_mut(page)?;
+            if !entry.is_unused() {
+                return Err(Error::AlreadyMapped(page.start_address()));
+            }
+            entry.set_frame(frame, flags);
+        }
+
+        Ok(start.start_address())
+    }
+
+    // Copies `data` to mapped pages, ignoring their protection. This is for loading programs.
+    pub(super) fn write(&mut self, addr: VirtAddr, data: &[u8]) -> Result<(), Error> {
+        self.for_each_chunk(addr, data.len(), PageTableFlags::empty(), |dst, range| {
+            let src = &data[range];
+
+            // Safety: `dst` points to `src.len()` bytes in a frame of this process.
+            unsafe { ptr::copy_nonoverlapping(src.as_ptr(), dst.as_mut_ptr::<u8>(), src.len()) }
+        })
+    }
+
+    pub(super) fn copy_from_user(&mut self, addr: VirtAddr, buf: &mut [u8]) -> Result<(), Error> {
+        self.for_each_chunk(
+            addr,
+            buf.len(),
+            PageTableFlags::USER_ACCESSIBLE,
+            |src, range| {
+                let dst = &mut buf[range];
+
+                // Safety: `src` points to `dst.len()` bytes in a frame of this process.
+                unsafe { ptr::copy_nonoverlapping(src.as_ptr::<u8>(), dst.as_mut_ptr(), dst.len()) }
+            },
+        )
+    }
+
+    pub(super) fn copy_to_user(&mut self, addr: VirtAddr, data: &[u8]) -> Result<(), Error> {
+        self.for_each_chunk(
+            addr,
+            data.len(),
+            PageTableFlags::USER_ACCESSIBLE | PageTableFlags::WRITABLE,
+            |dst, range| {
+                let src = &data[range];
+
+                // Safety: Same as `write`.
+                unsafe { ptr::copy_nonoverlapping(src.as_ptr(), dst.as_mut_ptr::<u8>(), src.len()) }
+            },
+        )
+    }
+
+    pub(super) fn pml4(&self) -> PhysFrame {
+        self.pml4
+    }
+
+    // Calls `f` with the address in the direct map and the range in the buffer of each piece of
+    // `addr..addr + len` within a page. Every page must be mapped with `required` flags.
+    fn for_each_chunk<F>(
+        &mut self,
+        addr: VirtAddr,
+        len: usize,
+        required: PageTableFlags,
+        mut f: F,
+    ) -> Result<(), Error>
+    where
+        F: FnMut(VirtAddr, Range<usize>),
+    {
+        let end = u64::try_from(len)
+            .ok()
+            .and_then(|len| addr.as_u64().checked_add(len))
+            .ok_or(Error::OutOfUserSpace(addr))?;
+        if end > USER_END.as_u64() {
+            return Err(Error::OutOfUserSpace(addr));
+        }
+
+        let mut offset = 0;
+        while offset < len {
+            let virt = addr + offset;
+            let page = Page::<Size4KiB>::containing_address(virt);
+            let entry = self.touch(page)?;
+            if !entry.flags().contains(required | PageTableFlags::PRESENT) {
+                return Err(Error::NotMapped(virt));
+            }
+
+            let in_page = usize::try_from(virt - page.start_address()).unwrap();
+            let chunk = cmp::min(
+                len - offset,
+                usize::try_from(Size4KiB::SIZE).unwrap() - in_page,
+            );
+
+            f(phys_to_virt(entry.addr()) + in_page, offset..offset + chunk);
+            offset += chunk;
+        }
+
+        Ok(())
+    }
+
+    // Returns a copy of the leaf entry of `page`. The page is mapped first if it is in an area and
+    // not touched yet.
+    fn touch(&mut self, page: Page<Size4KiB>) -> Result<PageTableEntry, Error> {
+        let virt = page.start_address();
+
+        if self.entry(page).map_or(true, PageTableEntry::is_unused) {
+            if let Some(area) = self.areas.find(virt) {
+                let frame = alloc_zeroed_frame()?;
+                self.entry_mut(page)?.set_frame(frame, area.flags());
+            }
+        }
+
+        self.entry(page).cloned().ok_or(Error::NotMapped(virt))
+    }
+
+    // Returns the leaf entry of `page`, creating page tables on the way.
+    fn entry_mut(&mut self, page: Page<Size4KiB>) -> Result<&mut PageTableEntry, Error> {
+        let table_flags =
+            PageTableFlags::PRESENT | PageTableFlags::WRITABLE | PageTableFlags::USER_ACCESSIBLE;
+
+        // Safety: Tables of the lower half belong to this process only, and `&mut self` is held.
+        let mut table = unsafe { paging::table_mut(self.pml4) };
+        for &i in &indices(page)[..3] {
+            let entry = &mut table[i];
+            if entry.is_unused() {
+                entry.set_frame(alloc_zeroed_frame()?, table_flags);
+            }
+
+            // Safety: Same as above.
+            table = unsafe { paging::table_mut(entry.frame().unwrap()) };
+        }
+
+        Ok(&mut table[indices(page)[3]])
+    }
+
+    // Returns `None` if any table on the way is missing.
+    fn entry(&self, page: Page<Size4KiB>) -> Option<&PageTableEntry> {
+        // Safety: Tables are only edited with `&mut self`.
+        let mut table: &PageTable = unsafe { paging::table_mut(self.pml4) };
+        for &i in &indices(page)[..3] {
+            let entry = &table[i];
+            if !entry.flags().contains(PageTableFlags::PRESENT) {
+                return None;
+            }
+
+            // Safety: Same as above.
+            table = unsafe { paging::table_mut(entry.frame().unwrap()) };
+        }
+
+        Some(&table[indices(page)[3]])
+    }
+}
+// The thread of the process is dead when this is dropped, so the PML4 is not active. Shared frames
+// are freed by `Frames` when `shared` is dropped after this.
+impl Drop for Memory {
+    fn drop(&mut self) {
+        let mut frame_manager = FRAME_MANAGER.lock();
+        let mut free = |frame: PhysFrame| frame_manager.free(frame.start_address());
+
+        // Safety: The process is gone, and no one else uses its tables.
+        let table = unsafe { paging::table_mut(self.pml4) };
+        for entry in table.iter().take(PML4_INDEX_UPPER_HALF) {
+            free_table(entry, 3, &mut free);
+        }
+
+        free(self.pml4);
+    }
+}
+
+// Returns the page after the range, or `OutOfUserSpace` if the range does not fit below
+// `USER_END`. The length may come from user space, so nothing here may overflow.
+fn user_range_end(start: Page<Size4KiB>, num_of_pages: usize) -> Result<Page<Size4KiB>, Error> {
+    u64::try_from(num_of_pages)
+        .ok()
+        .and_then(|n| n.checked_mul(Size4KiB::SIZE))
+        .and_then(|bytes| start.start_address().as_u64().checked_add(bytes))
+        .filter(|&end| end <= USER_END.as_u64())
+        .map(|end| Page::containing_address(VirtAddr::new(end)))
+        .ok_or_else(|| Error::OutOfUserSpace(start.start_address()))
+}
+
+fn indices(page: Page<Size4KiB>) -> [PageTableIndex; 4] {
+    [
+        page.p4_index(),
+        page.p3_index(),
+        page.p2_index(),
+        page.p1_index(),
+    ]
+}
+
+fn alloc_zeroed_frame() -> Result<PhysFrame, Error> {
+    let addr = FRAME_MANAGER
+        .lock()
+        .alloc(NumOfPages::new(1), Tag::User)
+        .ok_or(Error::NoMemory)?;
+
+    // Safety: The frame was just allocated and no one uses it.
+    unsafe {
+        ptr::write_bytes(
+            phys_to_virt(addr).as_mut_ptr::<u8>(),
+            0,
+            usize::try_from(Size4KiB::SIZE).unwrap(),
+        )
+    }
+
+    Ok(PhysFrame::from_start_address(addr).unwrap())
+}
+
+// `level` is that of the table `entry` points to. Zero means `entry` points to a page.
+fn free_table<F>(entry: &PageTableEntry, level: u8, free: &mut F)
+where
+    F: FnMut(PhysFrame),
+{
+    if !entry.flags().contains(PageTableFlags::PRESENT) {
+        return;
+    }
+    if level == 0 && entry.flags().contains(SHARED) {
+        return;
+    }
+    let frame = entry.frame().unwrap();
+
+    if level > 0 {
+        // Safety: Same as `drop`.
+        let table: &PageTable = unsafe { paging::table_mut(frame) };
+        for entry in table.iter() {
+            free_table(entry, level - 1, free);
+        }
+    }
+
+    free(frame);
+}
@@ -0,0 +1,287 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// User processes. Each process has its own PML4 whose upper half is shared with the kernel, and
+// runs in a kernel thread which enters ring 3 with `iretq`. An interrupt or an exception in user
+// mode switches to the kernel stack of the thread through RSP0 of the TSS.
+
+pub mod elf;
+pub mod init;
+mod memory;
+mod tree;
+
+use {
+    crate::{
+        gdt::GDT,
+        ipc::{Grant, Handles},
+        mem::{paging, vma::Access},
+        multitask::thread,
+    },
+    abi::process::{Fault, Status},
+    alloc::sync::Arc,
+    core::{
+        fmt,
+        sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+    memory::Memory,
+    os_units::NumOfPages,
+    spinning_top::{Spinlock, SpinlockGuard},
+    x86_64::{
+        structures::paging::{Page, PageTableFlags, PhysFrame, Size4KiB},
+        VirtAddr,
+    },
+};
+
+const USER_STACK_TOP: VirtAddr = memory::USER_END;
+const NUM_OF_PAGES_USER_STACK: NumOfPages<Size4KiB> = NumOfPages::new(16);
+
+// Interrupts are enabled in user mode.
+const RFLAGS_USER: u64 = 0x202;
+
+pub struct Process {
+    id: Id,
+    // A copy of the one in `memory` so that the scheduler does not take the lock.
+    pml4: PhysFrame,
+    memory: Spinlock<Memory>,
+    handles: Spinlock<Handles>,
+    // Set by `kill`.
+    terminating: AtomicBool,
+}
+impl Process {
+    pub fn new() -> Result<Self, Error> {
+        let pml4 = paging::new_pml4().ok_or(Error::NoMemory)?;
+
+        Ok(Self {
+            id: Id::new(),
+            pml4,
+            memory: Spinlock::new(Memory::new(pml4)),
+            handles: Spinlock::new(Handles::default()),
+            terminating: AtomicBool::new(false),
+        })
+    }
+
+    pub fn id(&self) -> Id {
+        self.id
+    }
+
+    // Maps zeroed pages. `USER_ACCESSIBLE` is added to `flags`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn map(
+        &self,
+        start: Page<Size4KiB>,
+        num_of_pages: NumOfPages<Size4KiB>,
+        flags: PageTableFlags,
+    ) -> Result<(), Error> {
+        self.memory.lock().map(start, num_of_pages, flags)
+    }
+
+    // Reserves zeroed pages somewhere in the process and returns their address. They are mapped
+    // when they are touched first.
+    pub fn map_anywhere(
+        &self,
+        num_of_pages: NumOfPages<Size4KiB>,
+        flags: PageTableFlags,
+    ) -> Result<VirtAddr, Error> {
+        self.memory.lock().map_anywhere(num_of_pages, flags)
+    }
+
+    // Copies `data` to mapped pages, ignoring their protection.
+    pub fn write(&self, addr: VirtAddr, data: &[u8]) -> Result<(), Error> {
+        self.memory.lock().write(addr, data)
+    }
+
+    // Fails unless every byte is readable from user mode.
+    pub fn copy_from_user(&self, addr: VirtAddr, buf: &mut [u8]) -> Result<(), Error> {
+        self.memory.lock().copy_from_user(addr, buf)
+    }
+
+    // Fails unless every byte is writable from user mode.
+    pub fn copy_to_user(&self, addr: VirtAddr, data: &[u8]) -> Result<(), Error> {
+        self.memory.lock().copy_to_user(addr, data)
+    }
+
+    // Shares the pages from `addr` with whoever receives the grant. They must be mapped, and
+    // writable if `writable` is true.
+    #[allow(clippy::too_many_arguments)]
+    pub fn grant(
+        &self,
+        addr: VirtAddr,
+        num_of_pages: NumOfPages<Size4KiB>,
+        writable: bool,
+    ) -> Result<Grant, Error> {
+        self.memory.lock().grant(addr, num_of_pages, writable)
+    }
+
+    // Maps the pages of `grant` somewhere in the process and returns their address.
+    pub fn map_grant(&self, grant: &Grant) -> Result<VirtAddr, Error> {
+        self.memory.lock().map_grant(grant)
+    }
+
+    pub fn handles(&self) -> SpinlockGuard<'_, Handles> {
+        self.handles.lock()
+    }
+
+    fn request_termination(&self) {
+        self.terminating.store(true, Ordering::Release);
+    }
+
+    fn termination_requested(&self) -> bool {
+        self.terminating.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn pml4(&self) -> PhysFrame {
+        self.pml4
+    }
+}
+impl fmt::Debug for Process {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Process({})", self.id.0)
+    }
+}
+
+// Maps a stack and starts running the process from `entry`.
+pub fn spawn(process: Process, entry: VirtAddr) -> Result<Id, Error> {
+    map_stack(&process)?;
+    start(process, entry, USER_STACK_TOP)
+}
+
+// The stack ends at `USER_STACK_TOP`.
+fn map_stack(process: &Process) -> Result<(), Error> {
+    let stack_bottom = USER_STACK_TOP - NUM_OF_PAGES_USER_STACK.as_bytes().as_usize();
+    process.map(
+        Page::containing_address(stack_bottom),
+        NUM_OF_PAGES_USER_STACK,
+        PageTableFlags::WRITABLE | PageTableFlags::NO_EXECUTE,
+    )
+}
+
+// The current process, if any, becomes the parent.
+fn start(process: Process, entry: VirtAddr, rsp: VirtAddr) -> Result<Id, Error> {
+    let id = process.id;
+    let process = Arc::new(process);
+    tree::add(
+        &process,
+        thread::current_process().map(|parent| parent.id()),
+    );
+
+    if let Err(e) = thread::spawn_process(process, move || enter(entry, rsp)) {
+        tree::remove(id);
+        return Err(Error::Thread(e));
+    }
+
+    Ok(id)
+}
+
+// Ends the current process. Its parent gets `status` with `wait`.
+pub fn exit_current(status: Status) -> ! {
+    let process = thread::current_process().expect("A kernel thread tried to exit as a process.");
+    info!("{:?} ended: {:?}", process, status);
+
+    tree::exit(process.id(), status);
+
+    // The thread holds the last reference, and frees the process when it exits.
+    drop(process);
+    thread::exit();
+}
+
+// Called by exception handlers when user code causes an exception.
+pub fn kill_current(fault: Fault, reason: fmt::Arguments) -> ! {
+    match thread::current_process() {
+        Some(process) => warn!("{:?} is killed: {}", process, reason),
+        None => panic!("A kernel thread is in user mode: {}", reason),
+    }
+
+    exit_current(Status::Faulted(fault));
+}
+
+// Blocking calls of processes check this to return early, so that the process can end.
+pub fn current_is_terminating() -> bool {
+    thread::current_process().map_or(false, |p| p.termination_requested())
+}
+
+// Called before returning to user mode.
+pub fn exit_if_terminated() {
+    if current_is_terminating() {
+        exit_current(Status::Killed);
+    }
+}
+
+// Returns `None` if `pid` is not a child of `parent`, or `parent` is being terminated.
+pub fn wait(parent: &Process, pid: Option<Id>) -> Option<(Id, Status)> {
+    tree::wait(parent, pid)
+}
+
+// Returns `false` if `pid` is not a child of `parent`.
+pub fn kill(parent: &Process, pid: Id) -> bool {
+    tree::kill(parent.id(), pid)
+}
+
+fn enter(entry: VirtAddr, rsp: VirtAddr) -> ! {
+    let cs = u64::from(GDT.user_code().0);
+    let ss = u64::from(GDT.user_data().0);
+
+    // Safety: The thread's PML4 maps `entry` and the stack in user mode, and RSP0 points to the
+    // kernel stack of this thread. Nothing on the kernel stack is used after this.
+    unsafe {
+        asm!(
+            "push {ss}
+            push {rsp}
+            push {rflags}
+            push {cs}
+            push {rip}
+            iretq",
+            ss = in(reg) ss,
+            rsp = in(reg) rsp.as_u64(),
+            rflags = in(reg) RFLAGS_USER,
+            cs = in(reg) cs,
+            rip = in(reg) entry.as_u64(),
+            options(noreturn)
+        )
+    }
+}
+
+// Called by the page fault handler for a fault in user mode, with interrupts enabled. `pml4` is the
+// one in CR3, which selects the memory areas to look up. Returns `false` if the access is invalid.
+pub fn handle_page_fault(pml4: PhysFrame, addr: VirtAddr, access: Access) -> bool {
+    let process = thread::current_process().expect("A kernel thread is in user mode.");
+    assert_eq!(
+        process.pml4, pml4,
+        "CR3 does not point to the PML4 of the current process."
+    );
+
+    let result = process.memory.lock().handle_page_fault(addr, access);
+    result.is_ok()
+}
+
+// Returns `true` if the exception happened in user mode.
+pub fn from_user(code_segment: u64) -> bool {
+    code_segment & 3 == 3
+}
+
+#[derive(PartialOrd, PartialEq, Ord, Eq, Copy, Clone, Debug)]
+pub struct Id(u64);
+impl Id {
+    fn new() -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+        Id(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
+
+    pub fn from_u64(raw: u64) -> Self {
+        Id(raw)
+    }
+
+    pub fn as_u64(self) -> u64 {
+        self.0
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    NoMemory,
+    NotMapped(VirtAddr),
+    AlreadyMapped(VirtAddr),
+    OutOfUserSpace(VirtAddr),
+    Unaligned(VirtAddr),
+    AlreadyShared(VirtAddr),
+    TooManyAreas,
+    Thread(thread::Error),
+}
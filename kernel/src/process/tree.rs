@@ -0,0 +1,144 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// The parent of a process is the one which spawned it, or none if the kernel did. A process
+// which ended stays as a zombie until its parent waits for it. A process without a parent is
+// freed as soon as it ends, and the children of an ending process lose their parent.
+
+use {
+    super::{Id, Process},
+    crate::{
+        lock::IrqSpinlock,
+        multitask::thread::{self, sync::WaitQueue},
+    },
+    abi::process::Status,
+    alloc::{
+        collections::BTreeMap,
+        sync::{Arc, Weak},
+        vec::Vec,
+    },
+    conquer_once::spin::Lazy,
+};
+
+// Exception handlers end processes, so this is locked with interrupts disabled.
+static TREE: Lazy<IrqSpinlock<BTreeMap<Id, Node>>> =
+    Lazy::new(|| IrqSpinlock::new("process tree", BTreeMap::new()));
+
+// Woken when any process ends or is asked to terminate.
+static CHANGED: WaitQueue = WaitQueue::new();
+
+struct Node {
+    parent: Option<Id>,
+    state: State,
+}
+
+enum State {
+    Running(Weak<Process>),
+    Zombie(Status),
+}
+
+pub(super) fn add(process: &Arc<Process>, parent: Option<Id>) {
+    TREE.lock().insert(
+        process.id(),
+        Node {
+            parent,
+            state: State::Running(Arc::downgrade(process)),
+        },
+    );
+}
+
+// For a process which failed to start.
+pub(super) fn remove(id: Id) {
+    TREE.lock().remove(&id);
+}
+
+pub(super) fn exit(id: Id, status: Status) {
+    {
+        let mut tree = TREE.lock();
+
+        let children: Vec<Id> = tree
+            .iter()
+            .filter(|(_, node)| node.parent == Some(id))
+            .map(|(&child, _)| child)
+            .collect();
+        for child in children {
+            if let State::Zombie(_) = tree[&child].state {
+                tree.remove(&child);
+            } else if let Some(node) = tree.get_mut(&child) {
+                node.parent = None;
+            }
+        }
+
+        let has_parent = tree.get(&id).map_or(false, |node| node.parent.is_some());
+        if has_parent {
+            if let Some(node) = tree.get_mut(&id) {
+                node.state = State::Zombie(status);
+            }
+        } else {
+            tree.remove(&id);
+        }
+    }
+
+    CHANGED.notify_all();
+}
+
+// Blocks until a matching child ends, and frees it. `pid` of `None` matches any child. Returns
+// `None` if no child matches, or if `parent` is asked to terminate.
+pub(super) fn wait(parent: &Process, pid: Option<Id>) -> Option<(Id, Status)> {
+    let is_target =
+        |id: Id, node: &Node| node.parent == Some(parent.id()) && pid.map_or(true, |p| p == id);
+
+    loop {
+        if parent.termination_requested() {
+            return None;
+        }
+
+        {
+            let mut tree = TREE.lock();
+            if !tree.iter().any(|(&id, node)| is_target(id, node)) {
+                return None;
+            }
+
+            let zombie = tree
+                .iter()
+                .filter(|(&id, node)| is_target(id, node))
+                .find_map(|(&id, node)| match node.state {
+                    State::Zombie(status) => Some((id, status)),
+                    State::Running(_) => None,
+                });
+            if let Some((id, status)) = zombie {
+                tree.remove(&id);
+                return Some((id, status));
+            }
+        }
+
+        CHANGED.wait_while(|| {
+            !parent.termination_requested()
+                && TREE.lock().iter().all(|(&id, node)| {
+                    !is_target(id, node) || matches!(node.state, State::Running(_))
+                })
+        });
+    }
+}
+
+// Returns `false` if `pid` is not a child of `parent`. A zombie is already dead, so asking it to
+// terminate succeeds.
+pub(super) fn kill(parent: Id, pid: Id) -> bool {
+    let process = {
+        let tree = TREE.lock();
+        match tree.get(&pid) {
+            Some(node) if node.parent == Some(parent) => match &node.state {
+                State::Running(process) => process.upgrade(),
+                State::Zombie(_) => None,
+            },
+            _ => return false,
+        }
+    };
+
+    if let Some(process) = process {
+        process.request_termination();
+        CHANGED.notify_all();
+        thread::interrupt_process(pid);
+    }
+
+    true
+}
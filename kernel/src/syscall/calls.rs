@@ -0,0 +1,125 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use {
+    super::{current, from_process_error, user_addr, usize_arg, Args},
+    crate::{
+        device::{keyboard, mouse, timer},
+        multitask::thread,
+        process,
+    },
+    abi::{
+        process::Status,
+        syscall::{MAX_WRITE_LEN, PROT_EXEC, PROT_WRITE},
+        Error, MouseEvent,
+    },
+    alloc::{vec, vec::Vec},
+    core::{convert::TryFrom, str},
+    os_units::NumOfPages,
+    x86_64::structures::paging::{PageSize, PageTableFlags, Size4KiB},
+};
+
+pub(super) fn write(args: &Args) -> Result<u64, Error> {
+    let len = usize_arg(args[1])?;
+    if len > MAX_WRITE_LEN {
+        return Err(Error::InvalidArgument);
+    }
+
+    let process = current();
+    let mut buf = vec![0; len];
+    process
+        .copy_from_user(user_addr(args[0])?, &mut buf)
+        .map_err(from_process_error)?;
+
+    let text = str::from_utf8(&buf).map_err(|_| Error::InvalidArgument)?;
+    info!("{:?}: {}", process, text);
+
+    Ok(args[1])
+}
+
+pub(super) fn read_keys(args: &Args) -> Result<u64, Error> {
+    let len = usize_arg(args[1])?;
+    let addr = user_addr(args[0])?;
+
+    let mut keys = Vec::new();
+    while keys.len() < len {
+        match keyboard::pop_for_process() {
+            Some(code) => keys.push(code),
+            None => break,
+        }
+    }
+
+    current()
+        .copy_to_user(addr, &keys)
+        .map_err(from_process_error)?;
+
+    Ok(u64::try_from(keys.len()).unwrap())
+}
+
+pub(super) fn read_mouse(args: &Args) -> Result<u64, Error> {
+    let max = usize_arg(args[1])? / MouseEvent::BYTES;
+    let addr = user_addr(args[0])?;
+
+    let mut events = Vec::new();
+    while events.len() < max * MouseEvent::BYTES {
+        match mouse::pop_for_process() {
+            Some(event) => events.extend_from_slice(&event.to_bytes()),
+            None => break,
+        }
+    }
+
+    current()
+        .copy_to_user(addr, &events)
+        .map_err(from_process_error)?;
+
+    Ok(u64::try_from(events.len() / MouseEvent::BYTES).unwrap())
+}
+
+pub(super) fn uptime(_: &Args) -> Result<u64, Error> {
+    Ok(timer::ticks_to_ms(timer::ticks()))
+}
+
+pub(super) fn exit(args: &Args) -> Result<u64, Error> {
+    let code = u32::try_from(args[0]).map_err(|_| Error::InvalidArgument)?;
+    process::exit_current(Status::Exited(code));
+}
+
+pub(super) fn yield_now(_: &Args) -> Result<u64, Error> {
+    thread::yield_now();
+    Ok(0)
+}
+
+pub(super) fn sleep(args: &Args) -> Result<u64, Error> {
+    thread::sleep(args[0]);
+    Ok(0)
+}
+
+pub(super) fn mmap(args: &Args) -> Result<u64, Error> {
+    let len = args[0];
+    let prot = args[1];
+    if len == 0 || prot & !(PROT_WRITE | PROT_EXEC) != 0 {
+        return Err(Error::InvalidArgument);
+    }
+
+    let num_of_pages = len
+        .checked_add(Size4KiB::SIZE - 1)
+        .map(|len| len / Size4KiB::SIZE)
+        .ok_or(Error::InvalidArgument)?;
+    let num_of_pages = NumOfPages::new(usize_arg(num_of_pages)?);
+
+    let mut flags = PageTableFlags::empty();
+    if prot & PROT_WRITE != 0 {
+        flags |= PageTableFlags::WRITABLE;
+    }
+    if prot & PROT_EXEC == 0 {
+        flags |= PageTableFlags::NO_EXECUTE;
+    }
+
+    current()
+        .map_anywhere(num_of_pages, flags)
+        .map(|addr| addr.as_u64())
+        .map_err(from_process_error)
+}
+
+pub(super) fn getpid(_: &Args) -> Result<u64, Error> {
+    Ok(current().id().as_u64())
+}
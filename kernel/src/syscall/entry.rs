@@ -0,0 +1,46 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// `syscall` does not switch stacks. The user stack pointer is saved in the per-CPU area, and the
+// kernel stack of the current thread is loaded from there. `SFMASK` clears IF, so nothing runs
+// between `syscall` and the switch.
+//
+// The registers pushed below `rsp`, `rcx` and `r11` form `super::Frame`. Ten registers are
+// pushed, so the stack is aligned to 16 bytes when `dispatch` is called.
+//
+// Safety: This must be entered only by `syscall` from user mode.
+#[naked]
+pub(super) unsafe extern "C" fn entry() {
+    asm!(
+        "swapgs
+        mov gs:[8], rsp
+        mov rsp, gs:[0]
+        push qword ptr gs:[8]
+        swapgs
+        push rcx
+        push r11
+        push r9
+        push r8
+        push r10
+        push rdx
+        push rsi
+        push rdi
+        push rax
+        mov rdi, rsp
+        sti
+        call {dispatch}
+        cli
+        add rsp, 8
+        pop rdi
+        pop rsi
+        pop rdx
+        pop r10
+        pop r8
+        pop r9
+        pop r11
+        pop rcx
+        pop rsp
+        sysretq",
+        dispatch = sym super::dispatch,
+        options(noreturn)
+    );
+}
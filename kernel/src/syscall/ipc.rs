@@ -0,0 +1,203 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use {
+    super::{current, from_process_error, user_addr, usize_arg, Args},
+    crate::{
+        ipc::{self, service, Capability, Endpoint, Handle, Message, Rights},
+        process::{self, Process},
+    },
+    abi::{
+        ipc::{Grant, GRANT_WRITE, NO_HANDLE},
+        Error,
+    },
+    alloc::{sync::Arc, vec},
+    core::{convert::TryFrom, str},
+    os_units::NumOfPages,
+    x86_64::VirtAddr,
+};
+
+const MAX_NAME_LEN: usize = 64;
+
+const RECEIVE: Rights = Rights {
+    send: false,
+    receive: true,
+};
+
+pub(super) fn endpoint_create(_: &Args) -> Result<u64, Error> {
+    let handle = current().handles().insert(Capability::Endpoint {
+        endpoint: Arc::new(Endpoint::new()),
+        rights: Rights::ALL,
+    });
+
+    Ok(handle.as_u64())
+}
+
+pub(super) fn lookup(args: &Args) -> Result<u64, Error> {
+    let len = usize_arg(args[1])?;
+    if len > MAX_NAME_LEN {
+        return Err(Error::InvalidArgument);
+    }
+
+    let process = current();
+    let mut name = vec![0; len];
+    process
+        .copy_from_user(user_addr(args[0])?, &mut name)
+        .map_err(from_process_error)?;
+    let name = str::from_utf8(&name).map_err(|_| Error::InvalidArgument)?;
+
+    let endpoint = service::lookup(name).ok_or(Error::NotFound)?;
+    let handle = process.handles().insert(Capability::Endpoint {
+        endpoint,
+        rights: Rights::SEND,
+    });
+
+    Ok(handle.as_u64())
+}
+
+pub(super) fn send(args: &Args) -> Result<u64, Error> {
+    let process = current();
+    let endpoint = endpoint(&process, args[0], Rights::SEND)?;
+    let message = read_message(&process, args[1])?;
+
+    endpoint.send(message).map_err(|_| Error::WouldBlock)?;
+    Ok(0)
+}
+
+pub(super) fn receive(args: &Args) -> Result<u64, Error> {
+    let process = current();
+    let endpoint = endpoint(&process, args[0], RECEIVE)?;
+    let addr = writable_buffer(&process, args[1])?;
+
+    // The process ends in `dispatch` if it is interrupted, so the error is never seen.
+    let message = endpoint.receive_blocking().ok_or(Error::WouldBlock)?;
+    write_message(&process, addr, message)
+}
+
+pub(super) fn call(args: &Args) -> Result<u64, Error> {
+    let process = current();
+    let endpoint = endpoint(&process, args[0], Rights::SEND)?;
+    let addr = writable_buffer(&process, args[1])?;
+    let message = read_message(&process, args[1])?;
+
+    let reply = endpoint.call(message).map_err(|e| match e {
+        ipc::Error::Full => Error::WouldBlock,
+        ipc::Error::NoReply => Error::NoReply,
+        ipc::Error::Interrupted => Error::WouldBlock,
+    })?;
+    write_message(&process, addr, reply)
+}
+
+pub(super) fn reply(args: &Args) -> Result<u64, Error> {
+    let process = current();
+    // Read before taking the reply so that a bad message does not fail the call.
+    let message = read_message(&process, args[1])?;
+
+    let reply = process
+        .handles()
+        .take_reply(Handle::from_u64(args[0]))
+        .ok_or(Error::BadHandle)?;
+    reply.send(message);
+
+    Ok(0)
+}
+
+pub(super) fn close(args: &Args) -> Result<u64, Error> {
+    let capability = current().handles().remove(Handle::from_u64(args[0]));
+    capability.map(|_| 0).ok_or(Error::BadHandle)
+}
+
+fn endpoint(process: &Process, handle: u64, rights: Rights) -> Result<Arc<Endpoint>, Error> {
+    process
+        .handles()
+        .endpoint(Handle::from_u64(handle), rights)
+        .ok_or(Error::BadHandle)
+}
+
+// Checked before blocking so that a received message is not lost because of a bad buffer.
+fn writable_buffer(process: &Process, arg: u64) -> Result<VirtAddr, Error> {
+    let addr = user_addr(arg)?;
+    process
+        .copy_to_user(addr, &[0; abi::Message::BYTES])
+        .map_err(from_process_error)?;
+
+    Ok(addr)
+}
+
+fn read_message(process: &Process, arg: u64) -> Result<Message, Error> {
+    let mut bytes = [0; abi::Message::BYTES];
+    process
+        .copy_from_user(user_addr(arg)?, &mut bytes)
+        .map_err(from_process_error)?;
+    let raw = abi::Message::from_bytes(bytes);
+
+    // The handle is checked first because a grant marks the pages as shared.
+    let capability = match raw.handle {
+        NO_HANDLE => None,
+        handle => Some(
+            process
+                .handles()
+                .duplicate(Handle::from_u64(handle))
+                .ok_or(Error::BadHandle)?,
+        ),
+    };
+
+    let grant = match raw.grant {
+        Grant {
+            num_of_pages: 0, ..
+        } => None,
+        Grant { flags, .. } if flags & !GRANT_WRITE != 0 => return Err(Error::InvalidArgument),
+        Grant {
+            addr,
+            num_of_pages,
+            flags,
+        } => Some(
+            process
+                .grant(
+                    user_addr(addr)?,
+                    NumOfPages::new(usize_arg(num_of_pages)?),
+                    flags & GRANT_WRITE != 0,
+                )
+                .map_err(from_process_error)?,
+        ),
+    };
+
+    Ok(Message {
+        sender: Some(process.id()),
+        label: raw.label,
+        data: raw.data,
+        grant,
+        capability,
+        reply: None,
+    })
+}
+
+fn write_message(process: &Process, addr: VirtAddr, message: Message) -> Result<u64, Error> {
+    let mut raw = abi::Message::new(message.label, message.data);
+    raw.sender = message.sender.map_or(0, process::Id::as_u64);
+
+    if let Some(grant) = &message.grant {
+        raw.grant = Grant {
+            addr: process
+                .map_grant(grant)
+                .map_err(from_process_error)?
+                .as_u64(),
+            num_of_pages: u64::try_from(grant.num_of_pages()).unwrap(),
+            flags: if grant.writable() { GRANT_WRITE } else { 0 },
+        };
+    }
+
+    {
+        let mut handles = process.handles();
+        if let Some(capability) = message.capability {
+            raw.handle = handles.insert(capability).as_u64();
+        }
+        if let Some(reply) = message.reply {
+            raw.reply = handles.insert(Capability::Reply(reply)).as_u64();
+        }
+    }
+
+    process
+        .copy_to_user(addr, &raw.to_bytes())
+        .map_err(from_process_error)?;
+    Ok(0)
+}
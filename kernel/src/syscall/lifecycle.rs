@@ -0,0 +1,80 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use {
+    super::{current, from_process_error, user_addr, usize_arg, Args},
+    crate::{
+        mem::swap,
+        process::{self, elf, Id},
+    },
+    abi::{
+        syscall::{MAX_SPAWN_ARGS_LEN, MAX_SPAWN_IMAGE_LEN},
+        Error,
+    },
+    alloc::{vec, vec::Vec},
+    core::str,
+    os_units::Bytes,
+};
+
+pub(super) fn wait(args: &Args) -> Result<u64, Error> {
+    let process = current();
+    let status_addr = user_addr(args[1])?;
+
+    // Checks the buffer before freeing the child, so that the status is not lost.
+    process
+        .copy_to_user(status_addr, &[0; 8])
+        .map_err(from_process_error)?;
+
+    let pid = match args[0] {
+        0 => None,
+        pid => Some(Id::from_u64(pid)),
+    };
+    let (pid, status) = process::wait(&process, pid).ok_or(Error::NotFound)?;
+
+    process
+        .copy_to_user(status_addr, &status.to_u64().to_le_bytes())
+        .map_err(from_process_error)?;
+
+    Ok(pid.as_u64())
+}
+
+pub(super) fn kill(args: &Args) -> Result<u64, Error> {
+    if process::kill(&current(), Id::from_u64(args[0])) {
+        Ok(0)
+    } else {
+        Err(Error::NotFound)
+    }
+}
+
+pub(super) fn spawn(args: &Args) -> Result<u64, Error> {
+    let image_len = usize_arg(args[1])?;
+    let args_len = usize_arg(args[3])?;
+    if image_len == 0 || image_len > MAX_SPAWN_IMAGE_LEN || args_len > MAX_SPAWN_ARGS_LEN {
+        return Err(Error::InvalidArgument);
+    }
+
+    let process = current();
+
+    // The image is large and read only once, so it may be paged out meanwhile. The memory is
+    // aligned to a page, as the loader needs.
+    let mut memory = swap::Memory::new(Bytes::new(image_len)).ok_or(Error::NoMemory)?;
+    let image = &mut memory[..image_len];
+    process
+        .copy_from_user(user_addr(args[0])?, image)
+        .map_err(from_process_error)?;
+
+    let mut joined = vec![0; args_len];
+    process
+        .copy_from_user(user_addr(args[2])?, &mut joined)
+        .map_err(from_process_error)?;
+    let joined = str::from_utf8(&joined).map_err(|_| Error::InvalidArgument)?;
+    let argv: Vec<&str> = joined.split_terminator('\0').collect();
+
+    match elf::spawn(image, &argv, &[]) {
+        Ok(pid) => Ok(pid.as_u64()),
+        Err(elf::Error::Process(e)) => Err(from_process_error(e)),
+        Err(e) => {
+            info!("{:?} failed to spawn: {:?}", process, e);
+            Err(Error::InvalidArgument)
+        }
+    }
+}
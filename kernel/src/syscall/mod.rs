@@ -0,0 +1,152 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// System calls from user processes. The numbers and the calling convention are defined in the
+// `abi` crate.
+
+mod calls;
+mod entry;
+mod ipc;
+mod lifecycle;
+
+use {
+    crate::{
+        gdt::GDT,
+        multitask::thread,
+        process::{self, Process},
+    },
+    abi::{syscall, Error},
+    alloc::sync::Arc,
+    core::convert::TryFrom,
+    x86_64::{
+        registers::{
+            model_specific::{Efer, EferFlags, Msr},
+            rflags::RFlags,
+        },
+        VirtAddr,
+    },
+};
+
+const MSR_STAR: u32 = 0xc000_0081;
+const MSR_LSTAR: u32 = 0xc000_0082;
+const MSR_SFMASK: u32 = 0xc000_0084;
+const MSR_KERNEL_GS_BASE: u32 = 0xc000_0102;
+
+type Handler = fn(&Args) -> Result<u64, Error>;
+type Args = [u64; 6];
+
+static TABLE: [(u64, &str, Handler); 19] = [
+    (syscall::WRITE, "write", calls::write),
+    (syscall::READ_KEYS, "read_keys", calls::read_keys),
+    (syscall::EXIT, "exit", calls::exit),
+    (syscall::YIELD, "yield", calls::yield_now),
+    (syscall::SLEEP, "sleep", calls::sleep),
+    (syscall::MMAP, "mmap", calls::mmap),
+    (syscall::GETPID, "getpid", calls::getpid),
+    (syscall::READ_MOUSE, "read_mouse", calls::read_mouse),
+    (syscall::UPTIME, "uptime", calls::uptime),
+    (
+        syscall::ENDPOINT_CREATE,
+        "endpoint_create",
+        ipc::endpoint_create,
+    ),
+    (syscall::LOOKUP, "lookup", ipc::lookup),
+    (syscall::SEND, "send", ipc::send),
+    (syscall::RECEIVE, "receive", ipc::receive),
+    (syscall::CALL, "call", ipc::call),
+    (syscall::REPLY, "reply", ipc::reply),
+    (syscall::CLOSE, "close", ipc::close),
+    (syscall::WAIT, "wait", lifecycle::wait),
+    (syscall::KILL, "kill", lifecycle::kill),
+    (syscall::SPAWN, "spawn", lifecycle::spawn),
+];
+
+// `entry` reads this through `gs` after `swapgs`. There is only one CPU.
+#[repr(C)]
+struct PerCpu {
+    kernel_rsp: u64,
+    user_rsp: u64,
+}
+
+static mut PER_CPU: PerCpu = PerCpu {
+    kernel_rsp: 0,
+    user_rsp: 0,
+};
+
+// Pushed by `entry`.
+#[repr(C)]
+struct Frame {
+    number: u64,
+    args: Args,
+}
+
+// Call this after `gdt::init`.
+pub fn init() {
+    let kernel_code = u64::from(GDT.kernel_code().0);
+    // `sysret` loads `SS` from this plus 8 and `CS` from this plus 16.
+    let sysret_base = u64::from(GDT.user_data().0) - 8;
+    let entry = u64::try_from(entry::entry as usize).unwrap();
+    let mask = RFlags::INTERRUPT_FLAG | RFlags::DIRECTION_FLAG | RFlags::TRAP_FLAG;
+
+    // Safety: The GDT has the layout `sysret` expects, `entry` is the handler, and `PER_CPU`
+    // lives forever.
+    unsafe {
+        Msr::new(MSR_STAR).write((sysret_base << 48) | (kernel_code << 32));
+        Msr::new(MSR_LSTAR).write(entry);
+        Msr::new(MSR_SFMASK).write(mask.bits());
+        Msr::new(MSR_KERNEL_GS_BASE).write(VirtAddr::from_ptr(&PER_CPU).as_u64());
+
+        Efer::update(|flags| flags.insert(EferFlags::SYSTEM_CALL_EXTENSIONS));
+    }
+}
+
+// Call this with interrupts disabled before running a thread which may enter user mode.
+pub fn set_kernel_stack(top: VirtAddr) {
+    // Safety: `entry` reads this with interrupts disabled, and so this is not written meanwhile.
+    unsafe { PER_CPU.kernel_rsp = top.as_u64() }
+}
+
+extern "C" fn dispatch(frame: &Frame) -> u64 {
+    let result = match TABLE.iter().find(|(n, ..)| *n == frame.number) {
+        Some((_, name, handler)) => {
+            trace!("System call: {}{:x?}", name, frame.args);
+            handler(&frame.args)
+        }
+        None => Err(Error::NoSuchCall),
+    };
+
+    // A process killed while it was in a system call ends here instead of returning.
+    process::exit_if_terminated();
+
+    syscall::encode(result)
+}
+
+// Only threads of processes enter user mode, so only they make system calls.
+fn current() -> Arc<Process> {
+    thread::current_process().expect("A kernel thread made a system call.")
+}
+
+// Checks only that the address is in the lower half. Whether it is mapped is checked when it is
+// accessed.
+fn user_addr(arg: u64) -> Result<VirtAddr, Error> {
+    match VirtAddr::try_new(arg) {
+        Ok(addr) if addr.as_u64() >> 47 == 0 => Ok(addr),
+        _ => Err(Error::BadAddress),
+    }
+}
+
+fn usize_arg(arg: u64) -> Result<usize, Error> {
+    usize::try_from(arg).map_err(|_| Error::InvalidArgument)
+}
+
+fn from_process_error(e: process::Error) -> Error {
+    match e {
+        process::Error::NoMemory | process::Error::TooManyAreas | process::Error::Thread(_) => {
+            Error::NoMemory
+        }
+        process::Error::NotMapped(_)
+        | process::Error::AlreadyMapped(_)
+        | process::Error::OutOfUserSpace(_)
+        | process::Error::Unaligned(_)
+        | process::Error::AlreadyShared(_) => Error::BadAddress,
+    }
+}
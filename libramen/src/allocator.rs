@@ -0,0 +1,117 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Small blocks are rounded up to powers of two and kept in a free list for each size. They are
+// carved from chunks which are `mmap`ed on demand. Larger blocks are `mmap`ed directly. There is
+// no `munmap`, so memory is never returned to the kernel, and freed large blocks are leaked.
+
+use {
+    crate::syscall,
+    abi::syscall::PROT_WRITE,
+    core::{
+        alloc::{GlobalAlloc, Layout},
+        cell::UnsafeCell,
+        convert::TryFrom,
+        ptr,
+    },
+};
+
+const MIN_BLOCK_SIZE: usize = 16;
+const MAX_BLOCK_SIZE: usize = 2048;
+const NUM_OF_CLASSES: usize = 8;
+
+const BYTES_CHUNK: usize = 64 * 1024;
+// Pages returned by `mmap` are aligned to this.
+const BYTES_PAGE: usize = 4096;
+
+#[global_allocator]
+static ALLOCATOR: Allocator = Allocator(UnsafeCell::new(Heap {
+    free: [ptr::null_mut(); NUM_OF_CLASSES],
+    next: 0,
+    end: 0,
+}));
+
+// A process has only one thread, and the allocator does not allocate by itself.
+struct Allocator(UnsafeCell<Heap>);
+unsafe impl Sync for Allocator {}
+unsafe impl GlobalAlloc for Allocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let heap = &mut *self.0.get();
+
+        match class(layout) {
+            Some(class) => heap.alloc(class),
+            None if layout.align() <= BYTES_PAGE => {
+                syscall::mmap(layout.size(), PROT_WRITE).unwrap_or(ptr::null_mut())
+            }
+            None => ptr::null_mut(),
+        }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        if let Some(class) = class(layout) {
+            (&mut *self.0.get()).free(ptr, class);
+        }
+    }
+}
+
+struct Heap {
+    // Each free block holds the address of the next one.
+    free: [*mut u8; NUM_OF_CLASSES],
+    // The unused part of the current chunk.
+    next: usize,
+    end: usize,
+}
+impl Heap {
+    fn alloc(&mut self, class: usize) -> *mut u8 {
+        let block = self.free[class];
+        if !block.is_null() {
+            // Safety: `block` was freed by `free`, which wrote the next one to it.
+            self.free[class] = unsafe { block.cast::<*mut u8>().read() };
+            return block;
+        }
+
+        self.carve(block_size(class))
+    }
+
+    // Blocks are aligned to their sizes, which are powers of two.
+    fn carve(&mut self, size: usize) -> *mut u8 {
+        let start = (self.next + size - 1) & !(size - 1);
+        if start + size > self.end {
+            match syscall::mmap(BYTES_CHUNK, PROT_WRITE) {
+                Ok(chunk) => {
+                    self.next = chunk as usize;
+                    self.end = self.next + BYTES_CHUNK;
+                }
+                Err(_) => return ptr::null_mut(),
+            }
+
+            return self.carve(size);
+        }
+
+        self.next = start + size;
+        start as *mut u8
+    }
+
+    // Safety: `block` must have been allocated with `class`.
+    unsafe fn free(&mut self, block: *mut u8, class: usize) {
+        block.cast::<*mut u8>().write(self.free[class]);
+        self.free[class] = block;
+    }
+}
+
+// Returns `None` if the block is too large for the free lists.
+fn class(layout: Layout) -> Option<usize> {
+    let size = layout
+        .size()
+        .max(layout.align())
+        .max(MIN_BLOCK_SIZE)
+        .next_power_of_two();
+    if size > MAX_BLOCK_SIZE {
+        return None;
+    }
+
+    Some(usize::try_from((size / MIN_BLOCK_SIZE).trailing_zeros()).unwrap())
+}
+
+fn block_size(class: usize) -> usize {
+    MIN_BLOCK_SIZE << class
+}
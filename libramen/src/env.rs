@@ -0,0 +1,49 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use core::{convert::TryFrom, slice, str};
+
+// Written once by `init` before `main` runs. A process has only one thread.
+static mut ARGS: &[*const u8] = &[];
+static mut VARS: &[*const u8] = &[];
+
+// Safety: `stack` must point to `argc` followed by the NULL-terminated `argv` and `envp`, and
+// their strings must live forever.
+pub(crate) unsafe fn init(stack: *const u64) {
+    let num_of_args = usize::try_from(*stack).unwrap();
+    let argv = stack.add(1).cast::<*const u8>();
+    let envp = argv.add(num_of_args + 1);
+
+    let mut num_of_vars = 0;
+    while !(*envp.add(num_of_vars)).is_null() {
+        num_of_vars += 1;
+    }
+
+    ARGS = slice::from_raw_parts(argv, num_of_args);
+    VARS = slice::from_raw_parts(envp, num_of_vars);
+}
+
+// The arguments given to the process, including its name if the spawner passed it.
+pub fn args() -> impl Iterator<Item = &'static str> {
+    // Safety: `ARGS` is not written after `init`.
+    unsafe { ARGS }.iter().map(|&s| to_str(s))
+}
+
+// The environment variables in the `KEY=VALUE` form.
+pub fn vars() -> impl Iterator<Item = &'static str> {
+    // Safety: Same as `args`.
+    unsafe { VARS }.iter().map(|&s| to_str(s))
+}
+
+// The kernel passes UTF-8 strings. Anything else is replaced with an empty string.
+fn to_str(s: *const u8) -> &'static str {
+    // Safety: `s` is a NUL-terminated string which `init` found on the stack.
+    let bytes = unsafe {
+        let mut len = 0;
+        while *s.add(len) != 0 {
+            len += 1;
+        }
+        slice::from_raw_parts(s, len)
+    };
+
+    str::from_utf8(bytes).unwrap_or("")
+}
@@ -0,0 +1,94 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// The kernel shows each `write` on its own line, so the output is buffered until a newline.
+
+use {
+    crate::syscall,
+    abi::syscall::MAX_WRITE_LEN,
+    core::{cell::UnsafeCell, cmp, fmt, str},
+};
+
+static STDOUT: Stdout = Stdout(UnsafeCell::new(Line {
+    buf: [0; MAX_WRITE_LEN],
+    len: 0,
+}));
+
+#[macro_export]
+macro_rules! print {
+    ($($arg:tt)*) => ($crate::io::_print(format_args!($($arg)*)));
+}
+
+#[macro_export]
+macro_rules! println {
+    () => ($crate::print!("\n"));
+    ($($arg:tt)*) => ($crate::print!("{}\n", format_args!($($arg)*)));
+}
+
+#[doc(hidden)]
+pub fn _print(args: fmt::Arguments) {
+    // Errors are ignored as there is nowhere else to report them.
+    let _ = fmt::write(STDOUT.line(), args);
+}
+
+// Writes the buffered text even if it does not end with a newline.
+pub fn flush() {
+    STDOUT.line().flush();
+}
+
+// A process has only one thread, and `_print` does not call itself.
+struct Stdout(UnsafeCell<Line>);
+unsafe impl Sync for Stdout {}
+impl Stdout {
+    #[allow(clippy::mut_from_ref)]
+    fn line(&self) -> &mut Line {
+        // Safety: See the comment of this struct.
+        unsafe { &mut *self.0.get() }
+    }
+}
+
+struct Line {
+    buf: [u8; MAX_WRITE_LEN],
+    len: usize,
+}
+impl Line {
+    fn push(&mut self, mut s: &str) {
+        while !s.is_empty() {
+            // A character is never split so that the kernel receives valid UTF-8.
+            let mut n = cmp::min(MAX_WRITE_LEN - self.len, s.len());
+            while !s.is_char_boundary(n) {
+                n -= 1;
+            }
+
+            if n == 0 {
+                self.flush();
+                continue;
+            }
+
+            self.buf[self.len..self.len + n].copy_from_slice(&s.as_bytes()[..n]);
+            self.len += n;
+            s = &s[n..];
+        }
+    }
+
+    fn flush(&mut self) {
+        // Safety: `push` copies whole characters only.
+        let text = unsafe { str::from_utf8_unchecked(&self.buf[..self.len]) };
+        let _ = syscall::write(text);
+        self.len = 0;
+    }
+}
+impl fmt::Write for Line {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let mut lines = s.split('\n');
+        if let Some(first) = lines.next() {
+            self.push(first);
+        }
+
+        for line in lines {
+            self.flush();
+            self.push(line);
+        }
+
+        Ok(())
+    }
+}
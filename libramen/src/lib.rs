@@ -0,0 +1,51 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// The runtime of programs running on Ramen OS. A program is a `no_std` binary which depends on
+// this crate and names its main function with `ramen::entry!`:
+//
+// ```
+// #![no_std]
+// #![no_main]
+//
+// ramen::entry!(main);
+//
+// fn main() {
+//     ramen::println!("Hello from user mode!");
+// }
+// ```
+
+#![no_std]
+#![feature(asm)]
+#![feature(naked_functions)]
+#![feature(alloc_error_handler)]
+#![deny(clippy::pedantic)]
+#![deny(clippy::all)]
+#![allow(clippy::missing_errors_doc)]
+
+extern crate alloc;
+
+#[macro_use]
+pub mod io;
+mod allocator;
+pub mod env;
+mod panic;
+pub mod process;
+mod start;
+pub mod syscall;
+pub mod task;
+
+pub use abi;
+
+// Defines the function called after the runtime is initialized. The process exits with zero when
+// it returns.
+#[macro_export]
+macro_rules! entry {
+    ($main:path) => {
+        #[doc(hidden)]
+        #[no_mangle]
+        fn __ramen_main() {
+            let main: fn() = $main;
+            main()
+        }
+    };
+}
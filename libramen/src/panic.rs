@@ -0,0 +1,20 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use {
+    crate::process,
+    core::{alloc::Layout, panic::PanicInfo},
+};
+
+// The exit code of a process which panicked.
+const EXIT_CODE_PANIC: u32 = 101;
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    println!("{}", info);
+    process::exit(EXIT_CODE_PANIC);
+}
+
+#[alloc_error_handler]
+fn alloc_error(layout: Layout) -> ! {
+    panic!("Allocation failed: {:?}", layout);
+}
@@ -0,0 +1,48 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use {
+    crate::{io, syscall},
+    abi::{process::Status, Error},
+    alloc::vec::Vec,
+};
+
+// Flushes the output, and ends the process.
+pub fn exit(code: u32) -> ! {
+    io::flush();
+    syscall::exit(code)
+}
+
+#[must_use]
+pub fn id() -> u64 {
+    syscall::getpid()
+}
+
+// Starts the ELF executable in `image` as a child, and returns its ID. `args` must not contain
+// NULs.
+pub fn spawn(image: &[u8], args: &[&str]) -> Result<u64, Error> {
+    let mut joined = Vec::new();
+    for arg in args {
+        if arg.contains('\0') {
+            return Err(Error::InvalidArgument);
+        }
+
+        joined.extend_from_slice(arg.as_bytes());
+        joined.push(0);
+    }
+
+    syscall::spawn(image, &joined)
+}
+
+// Waits for the child `pid` to end.
+pub fn wait(pid: u64) -> Result<Status, Error> {
+    syscall::wait(pid).map(|(_, status)| status)
+}
+
+// Waits for any child to end. Returns its ID and status.
+pub fn wait_any() -> Result<(u64, Status), Error> {
+    syscall::wait(0)
+}
+
+pub fn kill(pid: u64) -> Result<(), Error> {
+    syscall::kill(pid)
+}
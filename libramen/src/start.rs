@@ -0,0 +1,34 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use crate::{env, process};
+
+extern "Rust" {
+    // Defined by `entry!`.
+    fn __ramen_main();
+}
+
+// The kernel jumps here with `argc`, `argv`, `envp` and `auxv` at `rsp`. `rsp` is aligned to 16
+// bytes, but it is aligned again in case another loader does not do so.
+#[naked]
+#[no_mangle]
+unsafe extern "C" fn _start() -> ! {
+    asm!(
+        "xor rbp, rbp
+        mov rdi, rsp
+        and rsp, -16
+        call {}
+        ud2",
+        sym start,
+        options(noreturn)
+    );
+}
+
+extern "C" fn start(stack: *const u64) -> ! {
+    // Safety: `stack` is the one the kernel built, and nothing has run yet.
+    unsafe {
+        env::init(stack);
+        __ramen_main();
+    }
+
+    process::exit(0);
+}
@@ -0,0 +1,213 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Wrappers of the system calls. See `abi::syscall` for what each one does.
+
+use {
+    abi::{process::Status, syscall, Error, Message, MouseEvent},
+    core::convert::TryFrom,
+};
+
+pub fn write(text: &str) -> Result<usize, Error> {
+    // Safety: The kernel only reads `text`.
+    let written = unsafe { call(syscall::WRITE, text.as_ptr() as u64, to_u64(text.len())) };
+    syscall::decode(written).map(to_usize)
+}
+
+// Returns the number of scancodes copied. This does not block.
+pub fn read_keys(buf: &mut [u8]) -> Result<usize, Error> {
+    // Safety: The kernel writes at most `buf.len()` bytes to `buf`.
+    let read = unsafe {
+        call(
+            syscall::READ_KEYS,
+            buf.as_mut_ptr() as u64,
+            to_u64(buf.len()),
+        )
+    };
+    syscall::decode(read).map(to_usize)
+}
+
+// Returns the number of events copied. This does not block.
+pub fn read_mouse(events: &mut [MouseEvent]) -> Result<usize, Error> {
+    const MAX_EVENTS: usize = 16;
+
+    let mut buf = [0; MAX_EVENTS * MouseEvent::BYTES];
+    let len = core::cmp::min(events.len(), MAX_EVENTS) * MouseEvent::BYTES;
+
+    // Safety: Same as `read_keys`.
+    let read = unsafe { call(syscall::READ_MOUSE, buf.as_mut_ptr() as u64, to_u64(len)) };
+    let read = to_usize(syscall::decode(read)?);
+
+    for (event, bytes) in events
+        .iter_mut()
+        .zip(buf.chunks_exact(MouseEvent::BYTES))
+        .take(read)
+    {
+        let mut raw = [0; MouseEvent::BYTES];
+        raw.copy_from_slice(bytes);
+        *event = MouseEvent::from_bytes(raw);
+    }
+
+    Ok(read)
+}
+
+// Use `process::exit` instead so that the output is flushed.
+pub fn exit(code: u32) -> ! {
+    // Safety: The process ends here.
+    unsafe { call(syscall::EXIT, u64::from(code), 0) };
+
+    unreachable!("The process did not exit.");
+}
+
+pub fn yield_now() {
+    // Safety: Yielding does not touch the memory of the process.
+    unsafe { call(syscall::YIELD, 0, 0) };
+}
+
+pub fn sleep(ms: u64) {
+    // Safety: Same as `yield_now`.
+    unsafe { call(syscall::SLEEP, ms, 0) };
+}
+
+// Returns the address of zeroed pages of at least `len` bytes. `prot` is a combination of
+// `abi::syscall::PROT_*`.
+pub fn mmap(len: usize, prot: u64) -> Result<*mut u8, Error> {
+    // Safety: The kernel maps new pages only, so no memory in use changes.
+    let addr = unsafe { call(syscall::MMAP, to_u64(len), prot) };
+    syscall::decode(addr).map(|addr| to_usize(addr) as *mut u8)
+}
+
+#[must_use]
+pub fn getpid() -> u64 {
+    // Safety: Same as `yield_now`.
+    unsafe { call(syscall::GETPID, 0, 0) }
+}
+
+// Milliseconds since the boot.
+#[must_use]
+pub fn uptime() -> u64 {
+    // Safety: Same as `yield_now`.
+    unsafe { call(syscall::UPTIME, 0, 0) }
+}
+
+pub fn endpoint_create() -> Result<u64, Error> {
+    // Safety: No memory is passed.
+    syscall::decode(unsafe { call(syscall::ENDPOINT_CREATE, 0, 0) })
+}
+
+// Returns a handle to send messages to the kernel service `name`.
+pub fn lookup(name: &str) -> Result<u64, Error> {
+    // Safety: The kernel only reads `name`.
+    let handle = unsafe { call(syscall::LOOKUP, name.as_ptr() as u64, to_u64(name.len())) };
+    syscall::decode(handle)
+}
+
+// Fails with `WouldBlock` instead of waiting if the queue is full.
+pub fn send(handle: u64, message: &Message) -> Result<(), Error> {
+    let bytes = message.to_bytes();
+
+    // Safety: The kernel only reads `bytes`.
+    let result = unsafe { call(syscall::SEND, handle, bytes.as_ptr() as u64) };
+    syscall::decode(result).map(|_| ())
+}
+
+pub fn receive(handle: u64) -> Result<Message, Error> {
+    let mut bytes = [0; Message::BYTES];
+
+    // Safety: The kernel writes a message to `bytes`.
+    let result = unsafe { call(syscall::RECEIVE, handle, bytes.as_mut_ptr() as u64) };
+    syscall::decode(result).map(|_| Message::from_bytes(bytes))
+}
+
+// Sends `message`, and waits for the reply.
+pub fn call_endpoint(handle: u64, message: &Message) -> Result<Message, Error> {
+    let mut bytes = message.to_bytes();
+
+    // Safety: The kernel reads the message from `bytes`, and writes the reply to it.
+    let result = unsafe { call(syscall::CALL, handle, bytes.as_mut_ptr() as u64) };
+    syscall::decode(result).map(|_| Message::from_bytes(bytes))
+}
+
+// `reply` is the handle in `Message::reply` of a received message.
+pub fn reply(reply: u64, message: &Message) -> Result<(), Error> {
+    let bytes = message.to_bytes();
+
+    // Safety: Same as `send`.
+    let result = unsafe { call(syscall::REPLY, reply, bytes.as_ptr() as u64) };
+    syscall::decode(result).map(|_| ())
+}
+
+pub fn close(handle: u64) -> Result<(), Error> {
+    // Safety: No memory is passed.
+    syscall::decode(unsafe { call(syscall::CLOSE, handle, 0) }).map(|_| ())
+}
+
+// Blocks until the child `pid` ends, or any child if `pid` is zero. Returns its ID and status.
+pub fn wait(pid: u64) -> Result<(u64, Status), Error> {
+    let mut status = 0_u64;
+
+    // Safety: The kernel writes the status to `status`.
+    let pid = unsafe { call(syscall::WAIT, pid, &mut status as *mut u64 as u64) };
+    syscall::decode(pid).map(|pid| (pid, Status::from_u64(status)))
+}
+
+pub fn kill(pid: u64) -> Result<(), Error> {
+    // Safety: No memory is passed.
+    syscall::decode(unsafe { call(syscall::KILL, pid, 0) }).map(|_| ())
+}
+
+// `args` holds the arguments, each followed by a NUL. Returns the ID of the child.
+pub fn spawn(image: &[u8], args: &[u8]) -> Result<u64, Error> {
+    // Safety: The kernel only reads `image` and `args`.
+    let pid = unsafe {
+        call4(
+            syscall::SPAWN,
+            [
+                image.as_ptr() as u64,
+                to_u64(image.len()),
+                args.as_ptr() as u64,
+                to_u64(args.len()),
+            ],
+        )
+    };
+    syscall::decode(pid)
+}
+
+// Safety: The arguments must be valid for the system call `number`.
+unsafe fn call(number: u64, arg0: u64, arg1: u64) -> u64 {
+    let result;
+    asm!(
+        "syscall",
+        inlateout("rax") number => result,
+        in("rdi") arg0,
+        in("rsi") arg1,
+        lateout("rcx") _,
+        lateout("r11") _,
+        options(nostack)
+    );
+    result
+}
+
+// Safety: Same as `call`.
+unsafe fn call4(number: u64, args: [u64; 4]) -> u64 {
+    let result;
+    asm!(
+        "syscall",
+        inlateout("rax") number => result,
+        in("rdi") args[0],
+        in("rsi") args[1],
+        in("rdx") args[2],
+        in("r10") args[3],
+        lateout("rcx") _,
+        lateout("r11") _,
+        options(nostack)
+    );
+    result
+}
+
+fn to_u64(x: usize) -> u64 {
+    u64::try_from(x).unwrap()
+}
+
+fn to_usize(x: u64) -> usize {
+    usize::try_from(x).unwrap()
+}
@@ -0,0 +1,93 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use {
+    crate::syscall,
+    abi::MouseEvent,
+    core::{
+        future::Future,
+        pin::Pin,
+        task::{Context, Poll},
+    },
+};
+
+const BUF_LEN: usize = 16;
+
+// Scancodes of the PS/2 keyboard.
+#[derive(Default)]
+pub struct Keys {
+    buf: [u8; BUF_LEN],
+    start: usize,
+    end: usize,
+}
+impl Keys {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn next(&mut self) -> Next<'_, Self> {
+        Next(self)
+    }
+
+    fn poll_next(&mut self) -> Poll<u8> {
+        if self.start == self.end {
+            self.start = 0;
+            self.end = syscall::read_keys(&mut self.buf).unwrap_or(0);
+        }
+
+        if self.start == self.end {
+            Poll::Pending
+        } else {
+            self.start += 1;
+            Poll::Ready(self.buf[self.start - 1])
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Mouse {
+    buf: [MouseEvent; BUF_LEN],
+    start: usize,
+    end: usize,
+}
+impl Mouse {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn next(&mut self) -> Next<'_, Self> {
+        Next(self)
+    }
+
+    fn poll_next(&mut self) -> Poll<MouseEvent> {
+        if self.start == self.end {
+            self.start = 0;
+            self.end = syscall::read_mouse(&mut self.buf).unwrap_or(0);
+        }
+
+        if self.start == self.end {
+            Poll::Pending
+        } else {
+            self.start += 1;
+            Poll::Ready(self.buf[self.start - 1])
+        }
+    }
+}
+
+// Returned by `Keys::next` and `Mouse::next`.
+pub struct Next<'a, T>(&'a mut T);
+impl<'a> Future for Next<'a, Keys> {
+    type Output = u8;
+
+    fn poll(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Self::Output> {
+        self.0.poll_next()
+    }
+}
+impl<'a> Future for Next<'a, Mouse> {
+    type Output = MouseEvent;
+
+    fn poll(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Self::Output> {
+        self.0.poll_next()
+    }
+}
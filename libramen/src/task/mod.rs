@@ -0,0 +1,53 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// Helpers to write programs with `async`. The kernel does not notify processes of events, so
+// `block_on` polls the future again after sleeping while it is pending.
+
+mod input;
+mod time;
+
+pub use {
+    input::{Keys, Mouse},
+    time::{sleep, Interval, Sleep},
+};
+
+use {
+    crate::syscall,
+    core::{
+        future::Future,
+        pin::Pin,
+        ptr,
+        task::{Context, Poll, RawWaker, RawWakerVTable, Waker},
+    },
+};
+
+const POLL_INTERVAL_MS: u64 = 10;
+
+// Runs `future` to completion.
+pub fn block_on<F: Future>(mut future: F) -> F::Output {
+    // Safety: `future` is not moved after this.
+    let mut future = unsafe { Pin::new_unchecked(&mut future) };
+
+    // Safety: The vtable of `noop_waker` does nothing with the data pointer.
+    let waker = unsafe { Waker::from_raw(noop_waker()) };
+    let mut cx = Context::from_waker(&waker);
+
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+
+        syscall::sleep(POLL_INTERVAL_MS);
+    }
+}
+
+// Every future is polled periodically, so waking up does nothing.
+fn noop_waker() -> RawWaker {
+    fn clone(_: *const ()) -> RawWaker {
+        noop_waker()
+    }
+    fn noop(_: *const ()) {}
+
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+    RawWaker::new(ptr::null(), &VTABLE)
+}
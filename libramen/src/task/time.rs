@@ -0,0 +1,58 @@
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use {
+    crate::syscall,
+    core::{
+        future::Future,
+        pin::Pin,
+        task::{Context, Poll},
+    },
+};
+
+// Completes after `ms` milliseconds.
+#[must_use]
+pub fn sleep(ms: u64) -> Sleep {
+    Sleep {
+        until: syscall::uptime().saturating_add(ms),
+    }
+}
+
+pub struct Sleep {
+    until: u64,
+}
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Self::Output> {
+        if syscall::uptime() >= self.until {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+// Ticks every `period_ms` milliseconds. Missed ticks are skipped rather than fired at once.
+pub struct Interval {
+    period_ms: u64,
+    next: u64,
+}
+impl Interval {
+    #[must_use]
+    pub fn new(period_ms: u64) -> Self {
+        Self {
+            period_ms,
+            next: syscall::uptime().saturating_add(period_ms),
+        }
+    }
+
+    pub async fn tick(&mut self) {
+        Sleep { until: self.next }.await;
+
+        let now = syscall::uptime();
+        self.next = self.next.saturating_add(self.period_ms);
+        if self.next <= now {
+            self.next = now.saturating_add(self.period_ms);
+        }
+    }
+}